@@ -0,0 +1,144 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+use serde_json::Map;
+
+use crate::config::Overrides;
+use crate::export::ExportKind;
+use crate::scan::ScanOptions;
+
+pub const DEFAULT_CONFIG_PATH: &str = "data/config.json";
+
+/// Bridges sACN to Neewer Bluetooth lights.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Local config file, relative to the working directory. It's merged over any config in
+    /// /etc/sacn-neewer-lite and then ~/.config/sacn-neewer-lite, and wins over both.
+    #[arg(
+        long,
+        short,
+        global = true,
+        env = "SACN_NEEWER_CONFIG",
+        default_value = DEFAULT_CONFIG_PATH
+    )]
+    pub config: String,
+
+    /// Start with this profile from the config, in place of its default.
+    #[arg(long, global = true, env = "SACN_NEEWER_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Only use this Bluetooth adapter, by HCI name or controller address.
+    #[arg(long, global = true, env = "SACN_NEEWER_ADAPTER")]
+    pub adapter: Option<String>,
+
+    /// Receive sACN on the interface with this address, in place of the config's. The
+    /// variable for it is SACN_NEEWER_SACN_INTERFACE, like every other setting's.
+    #[arg(long, global = true)]
+    pub interface: Option<Ipv4Addr>,
+
+    /// Run without the TUI, logging status changes instead. The variable for it is
+    /// SACN_NEEWER_UI_HEADLESS, like every other setting's.
+    #[arg(long, global = true)]
+    pub headless: bool,
+
+    /// How much to log when not running the TUI.
+    #[arg(
+        long,
+        global = true,
+        env = "SACN_NEEWER_LOG_LEVEL",
+        default_value = "info"
+    )]
+    pub log_level: log::LevelFilter,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum Command {
+    /// Run the bridge; the default when no command is given.
+    Run,
+    /// List nearby lights and whether they are patched.
+    Scan(ScanOptions),
+    /// Identify, name and address unpatched lights, adding them to the config.
+    Patch,
+    /// Check the merged config (or one file) for problems without starting.
+    Validate { path: Option<String> },
+    /// Upgrade a legacy config (or another file) to the current version.
+    Migrate { path: Option<String> },
+    /// Write a JSON config (or another file) out as a commented TOML template.
+    Convert {
+        path: Option<String>,
+        /// Where to write it; the input with a .toml extension when absent.
+        #[arg(long, short)]
+        output: Option<String>,
+    },
+    /// Show where the config is read from, or with --effective every setting in use.
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Write the patch as a sheet, or fixture definitions for consoles.
+    Export {
+        #[command(subcommand)]
+        kind: ExportKind,
+    },
+    /// Flash a light on the running bridge.
+    Identify {
+        /// Label or address of the light, or the name of a group.
+        target: String,
+        /// How long to flash for, in seconds.
+        #[arg(value_parser = parse_seconds)]
+        duration: Option<Duration>,
+    },
+    /// Switch the running bridge to another profile, or show the one running.
+    Profile { name: Option<String> },
+    /// Send a control command, such as `identify key 3`, to the running bridge.
+    Send {
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+}
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum ConfigAction {
+    /// List the config files and variables that are merged, lowest first.
+    Show {
+        /// Print the merged settings, each with the file, variable or flag it came from.
+        #[arg(long)]
+        effective: bool,
+    },
+}
+
+impl Cli {
+    pub fn get_command(&self) -> &Command {
+        self.command.as_ref().unwrap_or(&Command::Run)
+    }
+
+    /// The settings given here that win over the config file.
+    pub fn get_overrides(&self) -> Overrides {
+        Overrides {
+            interface: self.interface,
+            headless: self.headless,
+            settings: Map::new(),
+        }
+    }
+
+    /// Whether the TUI will own the terminal, which rules out logging to it.
+    pub fn shows_tui(&self, config_headless: bool) -> bool {
+        *self.get_command() == Command::Run && !self.headless && !config_headless
+    }
+}
+
+/// Parses a positive number of seconds. The control connection reads durations with this
+/// too, so whatever the command line accepts the running bridge does.
+pub fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|s| s.is_finite() && *s > 0.0)
+        .map(Duration::from_secs_f32)
+        .ok_or(format!("invalid number of seconds {}", value))
+}
@@ -1,28 +1,450 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::time::Duration;
 
 use btleplug::api::BDAddr;
 use serde::de::{self, Deserializer};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 
+use crate::config_format::ConfigFormat;
+use crate::control::DEFAULT_CONTROL_ADDRESS;
+use crate::interpolation::Interpolation;
+use crate::personality::Personality;
+use crate::protocol::Protocol;
+use crate::state_file::DEFAULT_STATE_PATH;
+use crate::write_policy::WritePolicy;
+
+/// How a configured light picks out its peripheral.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LightMatch {
+    /// The peripheral's Bluetooth address.
+    Address(BDAddr),
+    /// The `index`th peripheral advertising exactly this local name, counted from 0 in the
+    /// order the running bridge first heard them (see `NameSlots`). For hosts where BlueZ only
+    /// ever sees random or resolvable addresses.
+    Name { name: String, index: usize },
+}
+
+/// Shown where a tool without the running bridge's `NameSlots` would have to name the
+/// peripheral a name and index rule picks.
+pub const UNRESOLVED_NAME_ORDER: &str = "first-heard order, unresolved";
+
+impl LightMatch {
+    /// Whether this rule could pick the peripheral at `address` advertising `local_name`. A
+    /// name rule only might; which one it picks is up to the `NameSlots` of a running bridge.
+    pub fn may_select(&self, address: BDAddr, local_name: Option<&str>) -> bool {
+        match self {
+            LightMatch::Address(id) => *id == address,
+            LightMatch::Name { name, .. } => local_name == Some(name.as_str()),
+        }
+    }
+
+    pub fn get_address(&self) -> Option<BDAddr> {
+        match self {
+            LightMatch::Address(id) => Some(*id),
+            LightMatch::Name { .. } => None,
+        }
+    }
+}
+
+/// Every address heard advertising each name, in slots in the order first heard: the order
+/// name and index rules count in. An address that goes away leaves its slot to the next new
+/// one, so a light whose address rotates keeps its index.
+#[derive(Debug)]
+pub struct NameSlots<T> {
+    slots: HashMap<String, Vec<Option<(BDAddr, T)>>>,
+}
+
+impl<T> Default for NameSlots<T> {
+    fn default() -> Self {
+        Self {
+            slots: HashMap::new(),
+        }
+    }
+}
+
+impl<T> NameSlots<T> {
+    /// Records `address` advertising `name`, keeping its slot if it has one already.
+    pub fn heard(&mut self, name: &str, address: BDAddr, value: T) {
+        let slots = self.slots.entry(name.to_string()).or_default();
+        let slot = match slots
+            .iter()
+            .position(|slot| slot.as_ref().is_some_and(|(heard, _)| *heard == address))
+            .or_else(|| slots.iter().position(Option::is_none))
+        {
+            Some(slot) => slot,
+            None => {
+                slots.push(None);
+                slots.len() - 1
+            }
+        };
+        slots[slot] = Some((address, value));
+    }
+
+    /// What was recorded in the `index`th slot for `name`.
+    pub fn pick(&self, name: &str, index: usize) -> Option<&T> {
+        let (_, value) = self.slots.get(name)?.get(index)?.as_ref()?;
+        Some(value)
+    }
+
+    /// Frees the slot of every address `gone` picks out.
+    pub fn forget_where(&mut self, gone: impl Fn(BDAddr, &T) -> bool) {
+        for slot in self.slots.values_mut().flatten() {
+            if slot
+                .as_ref()
+                .is_some_and(|(address, value)| gone(*address, value))
+            {
+                *slot = None;
+            }
+        }
+    }
+}
+
+impl fmt::Display for LightMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LightMatch::Address(id) => write!(f, "{}", id),
+            LightMatch::Name { name, index: 0 } => write!(f, "{}", name),
+            LightMatch::Name { name, index } => write!(f, "{} #{}", name, index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct LightConfig {
-    pub id: BDAddr,
+    pub matcher: LightMatch,
+    /// What the crew calls the light, shown everywhere in place of its address.
+    pub label: Option<String>,
+    /// The group whose defaults the light took, shown together in the TUI.
+    pub group: Option<String>,
     pub universe: u16,
     pub address: u16,
+    /// Forces a command framing; detected from the advertised name when absent.
+    pub protocol: Option<Protocol>,
+    pub personality: Personality,
+    /// HCI name or controller address of the adapter to connect through; balanced when absent.
+    pub adapter: Option<String>,
+    /// Shortest gap between two writes to this light; the update rate alone sets it when
+    /// absent.
+    pub min_write_interval: Option<Duration>,
+    /// Caps writes per second; defaults to what the model is known to handle.
+    pub max_update_hz: Option<u16>,
+    pub interpolation: Interpolation,
+    pub write_policy: WritePolicy,
+}
+
+impl LightConfig {
+    /// Whether `other` is this light moved to a new universe, address, personality, label or
+    /// group, with the same match rule and connection settings.
+    pub fn is_repatch_of(&self, other: &LightConfig) -> bool {
+        let moved = LightConfig {
+            label: other.label.clone(),
+            group: other.group.clone(),
+            universe: other.universe,
+            address: other.address,
+            personality: other.personality,
+            ..self.clone()
+        };
+        moved == *other
+    }
+
+    /// The label, or failing that whatever the light is matched by.
+    pub fn get_label(&self) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| self.matcher.to_string())
+    }
+}
+
+/// The config document version this build writes. Bare arrays of lights are version 0.
+pub const CONFIG_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SacnConfig {
+    /// Address of the network interface to join multicast groups on.
+    pub interface: Ipv4Addr,
+    pub port: u16,
+    /// How long without a packet before the sACN status shows a timeout.
+    pub timeout_ms: u64,
+}
+
+impl SacnConfig {
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+impl Default for SacnConfig {
+    fn default() -> Self {
+        Self {
+            interface: Ipv4Addr::UNSPECIFIED,
+            port: 5568,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BluetoothConfig {
+    /// Connection attempts allowed in flight at once, across every adapter.
+    pub max_concurrent_connects: usize,
+}
+
+impl Default for BluetoothConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_connects: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct UiConfig {
+    /// Runs without the terminal UI, for services and containers.
+    pub headless: bool,
+    /// Where the control API listens for commands like `identify`.
+    pub control_address: String,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        Self {
+            headless: false,
+            control_address: DEFAULT_CONTROL_ADDRESS.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StateConfig {
+    /// File the lights' last states are kept in between runs.
+    pub path: String,
+    /// How often the states are saved while running, besides on shutdown.
+    #[serde(deserialize_with = "deserialize_save_interval")]
+    pub save_interval_ms: u64,
+    /// Sends each light its saved state as soon as it connects after a restart, rather
+    /// than leaving it dark until sACN arrives.
+    pub restore_on_connect: bool,
+}
+
+impl StateConfig {
+    pub fn get_save_interval(&self) -> Duration {
+        Duration::from_millis(self.save_interval_ms)
+    }
+}
+
+// zero would spin the save loop, and anything short rewrites the file all through a fade
+const MIN_SAVE_INTERVAL_MS: u64 = 1000;
+
+fn deserialize_save_interval<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let interval = u64::deserialize(deserializer)?;
+    if interval < MIN_SAVE_INTERVAL_MS {
+        return Err(de::Error::custom(format!(
+            "save_interval_ms must be at least {}, not {}",
+            MIN_SAVE_INTERVAL_MS, interval
+        )));
+    }
+    Ok(interval)
+}
+
+impl Default for StateConfig {
+    fn default() -> Self {
+        Self {
+            path: DEFAULT_STATE_PATH.to_string(),
+            save_interval_ms: 5000,
+            restore_on_connect: false,
+        }
+    }
+}
+
+/// Settings from the command line or environment, which win over the config file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    pub interface: Option<Ipv4Addr>,
+    pub headless: bool,
+    /// Fields of the settings sections, by section, such as those from `SACN_NEEWER_*`
+    /// variables. The flags above win over them.
+    pub settings: Map<String, Value>,
+}
+
+impl Overrides {
+    /// `config` with these overrides applied, and kept in it so switching profiles can
+    /// apply them again.
+    pub fn apply(&self, mut config: Config) -> Config {
+        config.overrides = self.clone();
+        for (section, fields) in self.settings.iter() {
+            let Value::Object(fields) = fields else {
+                continue;
+            };
+            // checked against the defaults when they were read, so this can't fail on type
+            if let Err(e) = config.set_section_fields(section, fields) {
+                log::warn!("Ignoring {} overrides: {}", section, e);
+            }
+        }
+        if let Some(interface) = self.interface {
+            config.sacn.interface = interface;
+        }
+        if self.headless {
+            config.ui.headless = true;
+        }
+        config
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Default, Deserialize)]
 pub struct Config {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub sacn: SacnConfig,
+    #[serde(default)]
+    pub bluetooth: BluetoothConfig,
+    #[serde(default)]
+    pub ui: UiConfig,
+    #[serde(default)]
+    pub state: StateConfig,
+    /// Defaults for the lights that name each group, kept as written since any light setting
+    /// can go in them.
+    #[serde(default)]
+    pub groups: Map<String, Value>,
     pub lights: Vec<LightConfig>,
+    /// The profile in use, picked by name from `profiles`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Named sets of settings, usually a venue's lights and groups, that replace the
+    /// top-level ones when picked.
+    #[serde(default)]
+    pub profiles: Map<String, Value>,
+    /// The document as written, to pick another profile from.
+    #[serde(skip)]
+    pub source: Value,
+    /// The overrides applied, to apply again to another profile.
+    #[serde(skip)]
+    pub overrides: Overrides,
 }
 
 impl Config {
+    /// Loads a JSON, TOML or YAML config, going by the file extension.
     pub async fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_file_with_profile(path, None).await
+    }
+
+    /// Loads a config with `profile` in use, or its default profile if None.
+    pub async fn from_file_with_profile(
+        path: &str,
+        profile: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let data = tokio::fs::read_to_string(path).await?;
-        let lights: Vec<LightConfig> = serde_json::from_str(&data)?;
-        let config = Config { lights };
+        Self::from_value_with_profile(ConfigFormat::from_path(path).parse(&data)?, profile)
+    }
+
+    /// Loads either a versioned document or a legacy bare array of lights.
+    pub fn from_value(document: Value) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_value_with_profile(document, None)
+    }
+
+    /// Loads a document with `profile` in use, falling back to the one the document names.
+    pub fn from_value_with_profile(
+        document: Value,
+        profile: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let source = document.clone();
+        let mut document = document;
+        let profile = profile.map(str::to_string).or(document
+            .get("profile")
+            .and_then(Value::as_str)
+            .map(str::to_string));
+        if let Some(profile) = profile {
+            select_profile(&mut document, &profile)?;
+        }
+
+        let mut config = Self::from_document(document)?;
+        config.source = source;
         Ok(config)
     }
 
+    /// The same config with another profile in use, and the same overrides.
+    pub fn with_profile(&self, profile: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = Self::from_value_with_profile(self.source.clone(), Some(profile))?;
+        Ok(self.overrides.apply(config))
+    }
+
+    /// Replaces some fields of a settings section, such as `sacn`, leaving the rest.
+    pub fn set_section_fields(
+        &mut self,
+        section: &str,
+        fields: &Map<String, Value>,
+    ) -> Result<(), String> {
+        fn set<T: Serialize + de::DeserializeOwned>(
+            section: &mut T,
+            fields: &Map<String, Value>,
+        ) -> Result<(), String> {
+            let mut value = serde_json::to_value(&*section).map_err(|e| e.to_string())?;
+            if let Value::Object(current) = &mut value {
+                current.extend(fields.clone());
+            }
+            *section = serde_json::from_value(value).map_err(|e| e.to_string())?;
+            Ok(())
+        }
+
+        match section {
+            "sacn" => set(&mut self.sacn, fields),
+            "bluetooth" => set(&mut self.bluetooth, fields),
+            "ui" => set(&mut self.ui, fields),
+            "state" => set(&mut self.state, fields),
+            _ => Err(format!("Unknown settings section {}", section)),
+        }
+    }
+
+    pub fn get_profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    fn from_document(mut document: Value) -> Result<Self, Box<dyn std::error::Error>> {
+        if let Value::Array(entries) = document {
+            let lights = expand_lights(entries, &Map::new())?;
+            let lights: Vec<LightConfig> = serde_json::from_value(Value::Array(lights))?;
+            return Ok(Config {
+                lights,
+                ..Default::default()
+            });
+        }
+
+        match document.get("version").and_then(Value::as_u64) {
+            Some(version) if version == CONFIG_VERSION as u64 => {
+                let groups = match document.get("groups") {
+                    Some(Value::Object(groups)) => groups.clone(),
+                    Some(_) => return Err("groups must be a table of group names".into()),
+                    None => Map::new(),
+                };
+                if let Some(Value::Array(entries)) = document.get_mut("lights") {
+                    let entries = std::mem::take(entries);
+                    document["lights"] = Value::Array(expand_lights(entries, &groups)?);
+                }
+                Ok(serde_json::from_value(document)?)
+            }
+            Some(version) => Err(format!(
+                "Config version {} is not supported, expected {}",
+                version, CONFIG_VERSION
+            )
+            .into()),
+            None => Err("Config needs a version, or to be a list of lights".into()),
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.version == 0
+    }
+
     pub fn get_universes(&self) -> Vec<u16> {
         let mut universes = HashSet::new();
         for light in &self.lights {
@@ -31,6 +453,273 @@ impl Config {
         universes.into_iter().collect()
     }
 }
+
+/// Applies group defaults and range shorthand to every entry of a light list.
+pub fn expand_lights(
+    entries: Vec<Value>,
+    groups: &Map<String, Value>,
+) -> Result<Vec<Value>, String> {
+    let mut lights = vec![];
+    for (index, entry) in entries.iter().enumerate() {
+        let expanded =
+            expand_light(entry, groups).map_err(|e| format!("Light {}: {}", index + 1, e))?;
+        lights.extend(expanded);
+    }
+    Ok(lights)
+}
+
+/// Turns one light entry as written into the lights it stands for. Settings from the entry's
+/// group are filled in where the entry doesn't set them. An `ids` list, or a `name` with a
+/// `count`, patches one light per device at consecutive addresses, `spacing` channels apart
+/// (the personality's footprint unless given), numbering any label.
+pub fn expand_light(entry: &Value, groups: &Map<String, Value>) -> Result<Vec<Value>, String> {
+    let Value::Object(fields) = entry else {
+        // leave it for the light parser to complain about
+        return Ok(vec![entry.clone()]);
+    };
+
+    let mut light = Map::new();
+    if let Some(group_value) = fields.get("group") {
+        let group = group_value.as_str().ok_or("group must be a name")?;
+        let defaults = match groups.get(group) {
+            Some(Value::Object(defaults)) => defaults,
+            Some(_) => return Err(format!("Group {} must be a table of settings", group)),
+            None => return Err(format!("Unknown group {}", group)),
+        };
+        if let Some(key) = PER_LIGHT_KEYS
+            .iter()
+            .find(|key| defaults.contains_key(**key))
+        {
+            return Err(format!(
+                "Group {} can't set {}, it belongs on each light",
+                group, key
+            ));
+        }
+        light.extend(defaults.clone());
+    }
+    // the entry's own settings win over its group's
+    light.extend(fields.clone());
+
+    let ids = light.remove("ids");
+    let count = light.remove("count");
+    let spacing = light.remove("spacing");
+    let devices: Vec<(&str, Value)> = match (ids, count) {
+        (None, None) => {
+            if spacing.is_some() {
+                return Err("spacing only applies with ids or count".to_string());
+            }
+            return Ok(vec![Value::Object(light)]);
+        }
+        (Some(_), Some(_)) => return Err("use ids or count, not both".to_string()),
+        (Some(ids), None) => {
+            if light.contains_key("id") || light.contains_key("name") {
+                return Err("ids replaces id and name".to_string());
+            }
+            let ids = ids.as_array().ok_or("ids must be a list of addresses")?;
+            ids.iter().map(|id| ("id", id.clone())).collect()
+        }
+        (None, Some(count)) => {
+            if !light.contains_key("name") {
+                return Err("count needs a name to number the lights by".to_string());
+            }
+            let count = count.as_u64().ok_or("count must be a number")?;
+            let room = range_room(&light, spacing.as_ref())?;
+            if count > room {
+                return Err(format!(
+                    "count {} is more than the {} light(s) that fit in the universe",
+                    count, room
+                ));
+            }
+            let first = light.get("index").and_then(Value::as_u64).unwrap_or(0);
+            (first..first + count)
+                .map(|index| ("index", json!(index)))
+                .collect()
+        }
+    };
+    if devices.is_empty() {
+        return Err("ids or count must give at least one light".to_string());
+    }
+
+    let spacing = range_spacing(&light, spacing.as_ref())?;
+    let address = light.get("address").and_then(Value::as_u64);
+    let label = light
+        .get("label")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    Ok(devices
+        .into_iter()
+        .enumerate()
+        .map(|(n, (key, value))| {
+            let mut device = light.clone();
+            device.insert(key.to_string(), value);
+            if let Some(address) = address {
+                device.insert("address".to_string(), json!(address + n as u64 * spacing));
+            }
+            if let Some(label) = label.as_ref() {
+                device.insert("label".to_string(), json!(format!("{} {}", label, n + 1)));
+            }
+            Value::Object(device)
+        })
+        .collect())
+}
+
+fn footprint(light: &Map<String, Value>) -> Result<u64, String> {
+    let personality: Personality = match light.get("personality") {
+        Some(personality) => {
+            serde_json::from_value(personality.clone()).map_err(|e| e.to_string())?
+        }
+        None => Personality::default(),
+    };
+    Ok(personality.footprint() as u64)
+}
+
+// Channels from one light of a range to the next: `spacing`, or the footprint if not given.
+fn range_spacing(light: &Map<String, Value>, spacing: Option<&Value>) -> Result<u64, String> {
+    match spacing {
+        Some(spacing) => Ok(spacing.as_u64().ok_or("spacing must be a number")?),
+        None => footprint(light),
+    }
+}
+
+// How many lights of a range fit between its address and the end of the universe.
+fn range_room(light: &Map<String, Value>, spacing: Option<&Value>) -> Result<u64, String> {
+    let start = light.get("address").and_then(Value::as_u64).unwrap_or(1);
+    let first_end = start.saturating_add(footprint(light)?.saturating_sub(1));
+    if first_end > DMX_CHANNELS {
+        return Ok(0);
+    }
+    let step = range_spacing(light, spacing)?.max(1);
+    Ok((DMX_CHANNELS - first_end) / step + 1)
+}
+
+/// Merges the named profile's settings over the top-level ones, and records it as the
+/// profile in use. Tables merge key by key, so a profile only needs the settings it
+/// changes; anything else, the light list included, is replaced whole.
+pub fn select_profile(document: &mut Value, profile: &str) -> Result<(), String> {
+    let Value::Object(fields) = document else {
+        return Err("Profiles need a versioned config, run `migrate` first".to_string());
+    };
+    let profiles = match fields.get("profiles") {
+        Some(Value::Object(profiles)) => profiles,
+        Some(_) => return Err("profiles must be a table of profile names".to_string()),
+        None => {
+            return Err(format!(
+                "No profile {}, the config has no profiles",
+                profile
+            ))
+        }
+    };
+    let settings = match profiles.get(profile) {
+        Some(Value::Object(settings)) => settings.clone(),
+        Some(_) => return Err(format!("Profile {} must be a table of settings", profile)),
+        None => {
+            let names: Vec<&str> = profiles.keys().map(String::as_str).collect();
+            return Err(format!(
+                "Unknown profile {}, expected one of {}",
+                profile,
+                names.join(", ")
+            ));
+        }
+    };
+    if let Some(key) = DOCUMENT_KEYS
+        .iter()
+        .find(|key| settings.contains_key(**key))
+    {
+        return Err(format!("Profile {} can't set {}", profile, key));
+    }
+
+    for (key, value) in settings {
+        merge_table_value(fields.entry(key).or_insert(Value::Null), value);
+    }
+    fields.insert("profile".to_string(), json!(profile));
+    Ok(())
+}
+
+// Tables merge key by key; anything else, lists included, is replaced whole.
+fn merge_table_value(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            for (key, value) in value {
+                merge_table_value(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+// Describe the document itself rather than the rig, so a profile can't replace them.
+const DOCUMENT_KEYS: [&str; 3] = ["version", "profile", "profiles"];
+
+// Identify a single light, so a group can't supply them.
+const PER_LIGHT_KEYS: [&str; 7] = ["id", "ids", "name", "index", "count", "label", "group"];
+
+// Channels in a universe, which a range of lights has to fit in.
+const DMX_CHANNELS: u64 = 512;
+
+/// The settings sections with their default values, as a document would spell them out.
+pub fn default_settings() -> Map<String, Value> {
+    let mut settings = Map::new();
+    settings.insert("sacn".to_string(), json!(SacnConfig::default()));
+    settings.insert("bluetooth".to_string(), json!(BluetoothConfig::default()));
+    settings.insert("ui".to_string(), json!(UiConfig::default()));
+    settings.insert("state".to_string(), json!(StateConfig::default()));
+    settings
+}
+
+/// Upgrades the config at `path` in place, keeping the original alongside it. Returns the
+/// backup's path, or None if the file was already current.
+pub async fn migrate_file(path: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let data = tokio::fs::read_to_string(path).await?;
+    let format = ConfigFormat::from_path(path);
+    let document = format.parse(&data)?;
+    if !document.is_array() {
+        migrate(document)?;
+        return Ok(None);
+    }
+    if format != ConfigFormat::Json {
+        return Err(format!(
+            "Only JSON configs are migrated in place; run `convert` to turn {} into TOML",
+            path
+        )
+        .into());
+    }
+
+    let migrated = migrate(document)?;
+    // make sure the lights still load before touching anything
+    Config::from_value(migrated.clone())?;
+
+    let backup_path = format!("{}.v0.bak", path);
+    tokio::fs::copy(path, &backup_path).await?;
+    // write alongside and rename, so an interrupted write can't leave a half-written config
+    let temp_path = format!("{}.tmp", path);
+    tokio::fs::write(&temp_path, serde_json::to_string_pretty(&migrated)? + "\n").await?;
+    tokio::fs::rename(&temp_path, path).await?;
+    Ok(Some(backup_path))
+}
+
+/// Rewrites a legacy bare array of lights as a current document, spelling out the default
+/// settings so they're easy to find and change. Light entries are carried over untouched.
+/// Current documents are returned as they are.
+pub fn migrate(document: Value) -> Result<Value, String> {
+    match document {
+        Value::Array(lights) => Ok(json!({
+            "version": CONFIG_VERSION,
+            "sacn": SacnConfig::default(),
+            "bluetooth": BluetoothConfig::default(),
+            "ui": UiConfig::default(),
+            "state": StateConfig::default(),
+            "lights": lights,
+        })),
+        Value::Object(ref fields)
+            if fields.get("version").and_then(Value::as_u64) == Some(CONFIG_VERSION as u64) =>
+        {
+            Ok(document)
+        }
+        _ => Err("Not a config this version knows how to migrate".to_string()),
+    }
+}
+
 impl<'de> Deserialize<'de> for LightConfig {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -38,17 +727,63 @@ impl<'de> Deserialize<'de> for LightConfig {
     {
         #[derive(Deserialize)]
         struct LightConfigHelper {
-            id: String,
+            #[serde(default)]
+            id: Option<String>,
+            #[serde(default)]
+            name: Option<String>,
+            #[serde(default)]
+            index: Option<usize>,
+            #[serde(default)]
+            label: Option<String>,
+            #[serde(default)]
+            group: Option<String>,
             universe: u16,
             address: u16,
+            #[serde(default)]
+            protocol: Option<Protocol>,
+            #[serde(default)]
+            personality: Personality,
+            #[serde(default)]
+            adapter: Option<String>,
+            #[serde(default)]
+            min_write_interval_ms: Option<u64>,
+            #[serde(default)]
+            max_update_hz: Option<u16>,
+            #[serde(default)]
+            interpolation: Interpolation,
+            #[serde(default)]
+            write_policy: WritePolicy,
         }
 
         let helper = LightConfigHelper::deserialize(deserializer)?;
-        let id = helper.id.parse::<BDAddr>().map_err(de::Error::custom)?;
+        let matcher = match (helper.id, helper.name, helper.index) {
+            (Some(id), None, None) => {
+                LightMatch::Address(id.parse::<BDAddr>().map_err(de::Error::custom)?)
+            }
+            (None, Some(name), index) => LightMatch::Name {
+                name,
+                index: index.unwrap_or(0),
+            },
+            (None, None, _) => return Err(de::Error::custom("light needs an id or a name")),
+            (Some(_), _, _) => {
+                return Err(de::Error::custom(
+                    "light can match by id or by name and index, not both",
+                ))
+            }
+        };
         Ok(LightConfig {
-            id,
+            matcher,
+            label: helper.label,
+            group: helper.group,
             universe: helper.universe,
             address: helper.address,
+            protocol: helper.protocol,
+            personality: helper.personality,
+            adapter: helper.adapter,
+            min_write_interval: helper.min_write_interval_ms.map(Duration::from_millis),
+            max_update_hz: helper.max_update_hz,
+            interpolation: helper.interpolation,
+            write_policy: helper.write_policy,
         })
     }
 }
@@ -0,0 +1,344 @@
+use std::fmt;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::config::{self, Config};
+
+/// The file formats a config can be written in, all sharing the same schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+/// A document that isn't valid in its format, with the line it went wrong on if known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFormat::Json => write!(f, "JSON"),
+            ConfigFormat::Toml => write!(f, "TOML"),
+            ConfigFormat::Yaml => write!(f, "YAML"),
+        }
+    }
+}
+
+impl ConfigFormat {
+    /// Picks the format from the file extension, falling back to JSON.
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    /// Reads a document into the JSON model the rest of the config code works on.
+    pub fn parse(&self, text: &str) -> Result<Value, ParseError> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(text).map_err(|e| ParseError {
+                line: Some(e.line()),
+                message: format!("Invalid JSON: {}", e),
+            }),
+            ConfigFormat::Toml => toml::from_str(text).map_err(|e| ParseError {
+                line: e
+                    .span()
+                    .map(|span| text[..span.start].matches('\n').count() + 1),
+                message: format!("Invalid TOML: {}", e.message()),
+            }),
+            ConfigFormat::Yaml => serde_norway::from_str(text).map_err(|e| ParseError {
+                line: e.location().map(|location| location.line()),
+                message: format!("Invalid YAML: {}", e),
+            }),
+        }
+    }
+}
+
+/// Reads the config at `path` in whatever format it's in and writes it to `output` as a
+/// commented TOML template. Won't overwrite an existing file.
+pub async fn convert_file(path: &str, output: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let data = tokio::fs::read_to_string(path).await?;
+    let document = ConfigFormat::from_path(path).parse(&data)?;
+    // make sure it loads before writing anything
+    Config::from_value(document.clone())?;
+    let template = to_toml_template(document, path)?;
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(output)
+        .await
+        .map_err(|e| format!("Can't write {}: {}", output, e))?;
+    tokio::io::AsyncWriteExt::write_all(&mut file, template.as_bytes()).await?;
+    Ok(())
+}
+
+/// Writes a config document as TOML, with a comment on every setting and the optional light
+/// fields listed commented out, for a crew to edit by hand. Legacy documents are migrated
+/// first, since TOML has no bare arrays.
+pub fn to_toml_template(document: Value, source: &str) -> Result<String, String> {
+    let Value::Object(document) = config::migrate(document)? else {
+        return Err("A config must be a table".to_string());
+    };
+    let mut out = format!(
+        "# sacn-neewer-lite config, converted from {}.\n\
+         # Lines starting with # are comments; remove the # to use a setting.\n",
+        source
+    );
+
+    write_document(&mut out, "", &document);
+    Ok(out)
+}
+
+/// Writes the settings of a document, or of a profile within one, under `prefix`: plain keys
+/// first as TOML wants them before any table, then a table per section, then the lists of
+/// tables.
+fn write_document(out: &mut String, prefix: &str, document: &Map<String, Value>) {
+    for (key, value) in document.iter() {
+        if !is_table(value) && !is_table_array(value) && !value.is_null() {
+            out.push_str(&format!("{} = {}\n", toml_key(key), toml_value(value)));
+        }
+    }
+
+    for (key, value) in document.iter() {
+        let Value::Object(fields) = value else {
+            continue;
+        };
+        let name = format!("{}{}", prefix, toml_key(key));
+        out.push('\n');
+        if let Some(comment) = comment_for(SECTION_COMMENTS, key) {
+            out.push_str(&format!("# {}\n", comment));
+        }
+        if !NAMED_TABLES.contains(&key.as_str()) {
+            out.push_str(&format!("[{}]\n", name));
+            write_fields(out, value, &[]);
+            continue;
+        }
+        // one [key.name] table per entry, such as each group
+        for (i, (entry, table)) in fields.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            let entry_name = format!("{}.{}", name, toml_key(entry));
+            out.push_str(&format!("[{}]\n", entry_name));
+            match table {
+                Value::Object(table) if key == "profiles" => {
+                    write_document(out, &format!("{}.", entry_name), table)
+                }
+                _ => write_fields(out, table, &[]),
+            }
+        }
+    }
+
+    for (key, value) in document.iter().filter(|(_, value)| is_table_array(value)) {
+        if let Some(comment) = comment_for(SECTION_COMMENTS, key) {
+            out.push_str(&format!("\n# {}\n", comment));
+        }
+        let optional: &[(&str, &str, &str)] = if key == "lights" {
+            OPTIONAL_LIGHT_FIELDS
+        } else {
+            &[]
+        };
+        for item in value.as_array().into_iter().flatten() {
+            out.push_str(&format!("\n[[{}{}]]\n", prefix, toml_key(key)));
+            write_fields(out, item, optional);
+        }
+    }
+}
+
+// Sections holding a table per name rather than settings of their own.
+const NAMED_TABLES: [&str; 2] = ["groups", "profiles"];
+
+const SECTION_COMMENTS: &[(&str, &str)] = &[
+    ("sacn", "Where sACN is received."),
+    ("bluetooth", "How lights are connected to."),
+    ("ui", "The terminal display and the control API."),
+    ("state", "The lights' last states, kept between runs."),
+    (
+        "profiles",
+        "Named sets of settings, such as each venue's lights, that replace the ones above when\n\
+         # picked with --profile, `profile = \"name\"` at the top, or from the running bridge.",
+    ),
+    (
+        "groups",
+        "Settings shared by the lights that name the group; a light's own settings win.",
+    ),
+    (
+        "lights",
+        "One [[lights]] table per light. Match it by id (its MAC address), or by name and\n\
+         # index when several lights advertise the same name; index counts from 0 in address\n\
+         # order. A list of ids, or a name with a count, patches that many lights at\n\
+         # consecutive addresses, spacing channels apart (the footprint by default).",
+    ),
+];
+
+const FIELD_COMMENTS: &[(&str, &str)] = &[
+    (
+        "interface",
+        "Address of the network interface to listen on; 0.0.0.0 listens on all of them.",
+    ),
+    ("port", "UDP port, 5568 unless the console says otherwise."),
+    (
+        "timeout_ms",
+        "How long without packets before the sACN status shows a timeout.",
+    ),
+    (
+        "max_concurrent_connects",
+        "Connection attempts in flight at once; BlueZ rejects too many overlapping ones.",
+    ),
+    (
+        "headless",
+        "Run without the TUI, logging status changes instead.",
+    ),
+    (
+        "path",
+        "File the states are saved to, relative to the working directory.",
+    ),
+    (
+        "save_interval_ms",
+        "How often the states are saved while running, besides on shutdown; at least 1000.",
+    ),
+    (
+        "restore_on_connect",
+        "Send each light its saved state as it connects after a restart, until sACN arrives.",
+    ),
+    (
+        "control_address",
+        "Where the control API listens, for `identify` and `send`.",
+    ),
+];
+
+// Listed commented out under each light that doesn't set them: an example value, then what
+// else it can be.
+const OPTIONAL_LIGHT_FIELDS: &[(&str, &str, &str)] = &[
+    (
+        "label",
+        "\"Stage left key\"",
+        "shown in place of the address",
+    ),
+    ("personality", "\"rgb\"", "or \"cct\""),
+    (
+        "protocol",
+        "\"legacy\"",
+        "or \"infinity\"; detected when absent",
+    ),
+    (
+        "adapter",
+        "\"hci0\"",
+        "balanced across adapters when absent",
+    ),
+    (
+        "min_write_interval_ms",
+        "50",
+        "shortest gap between writes; from the update rate when absent",
+    ),
+    ("max_update_hz", "20", "from the model table when absent"),
+    (
+        "interpolation",
+        "{ mode = \"smooth\", time_constant_ms = 100 }",
+        "or \"snap\", or \"snap_above\" with a threshold",
+    ),
+    (
+        "write_policy",
+        "\"without_response\"",
+        "or \"with_response\", \"adaptive\"",
+    ),
+    ("group", "\"wash\"", "takes the group's settings"),
+];
+
+fn comment_for<'a>(comments: &[(&str, &'a str)], key: &str) -> Option<&'a str> {
+    comments
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, comment)| *comment)
+}
+
+fn is_table(value: &Value) -> bool {
+    value.is_object()
+}
+
+/// Quotes a key unless it's bare-safe.
+pub fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    }
+}
+
+fn is_table_array(value: &Value) -> bool {
+    value
+        .as_array()
+        .is_some_and(|items| !items.is_empty() && items.iter().all(Value::is_object))
+}
+
+fn write_fields(out: &mut String, table: &Value, optional: &[(&str, &str, &str)]) {
+    let Value::Object(fields) = table else {
+        return;
+    };
+    // TOML has no null, and a missing key means the same here
+    for (key, value) in fields.iter().filter(|(_, value)| !value.is_null()) {
+        if let Some(comment) = comment_for(FIELD_COMMENTS, key) {
+            out.push_str(&format!("# {}\n", comment));
+        }
+        out.push_str(&format!("{} = {}\n", toml_key(key), toml_value(value)));
+    }
+    for (key, example, note) in optional {
+        if !fields.contains_key(*key) {
+            out.push_str(&format!("# {} = {}  # {}\n", key, example, note));
+        }
+    }
+}
+
+/// Formats a value for the right-hand side of a TOML key, with tables inline. Nulls are left
+/// to the caller to drop.
+fn toml_value(value: &Value) -> String {
+    match value {
+        // JSON string escapes are all valid in a TOML basic string
+        Value::Null | Value::String(_) | Value::Number(_) | Value::Bool(_) => value.to_string(),
+        Value::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .filter(|item| !item.is_null())
+                .map(toml_value)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Object(fields) => format!(
+            "{{ {} }}",
+            fields
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| format!("{} = {}", key, toml_value(value)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
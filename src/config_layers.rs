@@ -0,0 +1,536 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Map, Value};
+
+use crate::config::{self, Config, Overrides};
+use crate::config_format::{toml_key, ConfigFormat};
+use crate::validate::{self, Problem};
+
+/// Where settings shared by everything on the host go, such as a fleet's base config.
+pub const SYSTEM_CONFIG_DIR: &str = "/etc/sacn-neewer-lite";
+
+/// Starts the name of every variable that overrides a setting, such as
+/// `SACN_NEEWER_SACN_PORT`.
+pub const ENV_PREFIX: &str = "SACN_NEEWER_";
+
+// Looked for in each config directory in this order; only the first found is used.
+const CONFIG_FILE_NAMES: [&str; 4] = ["config.toml", "config.yaml", "config.yml", "config.json"];
+
+// Past this, a long light setting pushes its source out rather than every other one.
+const MAX_SOURCE_COLUMN: usize = 56;
+
+// Stands in for a profile's settings while they're merged, until each gets its own source.
+const PROFILE_SOURCE: &str = "profile";
+
+/// A config file as read, to be merged over the ones before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    pub path: String,
+    pub text: String,
+}
+
+/// Config files merged into one document, remembering which file each value came from.
+#[derive(Debug, Clone, Default)]
+pub struct Layered {
+    pub document: Value,
+    /// The file each value that isn't a table came from, by path such as `sacn.port` or
+    /// `lights[0].id`.
+    pub sources: BTreeMap<String, String>,
+}
+
+/// A setting in effect, and where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Setting {
+    pub path: String,
+    pub value: Value,
+    pub source: String,
+}
+
+/// The user's config directory: `$XDG_CONFIG_HOME/sacn-neewer-lite`, or under `~/.config`.
+pub fn user_config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("sacn-neewer-lite"))
+}
+
+/// The directories searched for layers under the local config, lowest first.
+pub fn config_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(SYSTEM_CONFIG_DIR)];
+    dirs.extend(user_config_dir());
+    dirs
+}
+
+/// The files to merge, lowest first: the first config found in each of `dirs`, then `local`,
+/// which wins. `local` is left out when it doesn't exist but another layer does, so a host can
+/// run on the shared files alone.
+pub fn find_layer_paths(dirs: &[PathBuf], local: &str) -> Vec<String> {
+    let mut paths: Vec<String> = dirs
+        .iter()
+        .filter_map(|dir| find_config_in(dir))
+        // --config may name one of the shared files itself
+        .filter(|path| !same_file(path, local))
+        .collect();
+    if paths.is_empty() || Path::new(local).is_file() {
+        paths.push(local.to_string());
+    }
+    paths
+}
+
+fn find_config_in(dir: &Path) -> Option<String> {
+    CONFIG_FILE_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+fn same_file(a: &str, b: &str) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// The layers' paths as one name, for reports about the merged config.
+pub fn describe_paths(paths: &[String]) -> String {
+    paths.join(" + ")
+}
+
+pub async fn read_layers(paths: &[String]) -> Result<Vec<Layer>, String> {
+    let mut layers = vec![];
+    for path in paths {
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("{}: {}", path, e))?;
+        layers.push(Layer {
+            path: path.clone(),
+            text,
+        });
+    }
+    Ok(layers)
+}
+
+/// Merges layers, each over the ones before it. Tables merge key by key, so a layer only
+/// needs the settings it changes, such as a group's adapter; anything else, the light list
+/// included, is replaced whole. Every layer that doesn't parse is reported.
+pub fn merge(layers: &[Layer]) -> Result<Layered, Vec<String>> {
+    let mut layered = Layered::default();
+    let mut problems = vec![];
+    for layer in layers {
+        let document = match ConfigFormat::from_path(&layer.path).parse(&layer.text) {
+            Ok(document) => document,
+            Err(e) => {
+                let problem = Problem {
+                    line: e.line,
+                    message: e.message,
+                };
+                problems.push(problem.describe(&layer.path));
+                continue;
+            }
+        };
+        // a legacy list has nothing to merge key by key
+        if layers.len() > 1 && !document.is_object() {
+            problems.push(format!(
+                "{}: Only a table of settings can be layered with other files, run `migrate` first",
+                layer.path
+            ));
+            continue;
+        }
+        merge_value(
+            &mut layered.document,
+            document,
+            "",
+            &layer.path,
+            &mut layered.sources,
+        );
+    }
+
+    if problems.is_empty() {
+        Ok(layered)
+    } else {
+        Err(problems)
+    }
+}
+
+fn merge_value(
+    base: &mut Value,
+    layer: Value,
+    path: &str,
+    source: &str,
+    sources: &mut BTreeMap<String, String>,
+) {
+    match (base, layer) {
+        (Value::Object(base), Value::Object(layer)) => {
+            for (key, value) in layer {
+                let child = join(path, &key);
+                match base.get_mut(&key) {
+                    Some(existing) if existing.is_object() && value.is_object() => {
+                        merge_value(existing, value, &child, source, sources)
+                    }
+                    _ => {
+                        forget(sources, &child);
+                        record(sources, &child, &value, source);
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, layer) => {
+            forget(sources, path);
+            record(sources, path, &layer, source);
+            *base = layer;
+        }
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        toml_key(key)
+    } else {
+        format!("{}.{}", path, toml_key(key))
+    }
+}
+
+/// Marks every value within `value` as coming from `source`.
+fn record(sources: &mut BTreeMap<String, String>, path: &str, value: &Value, source: &str) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (key, value) in fields {
+                record(sources, &join(path, key), value, source);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, item) in items.iter().enumerate() {
+                record(sources, &format!("{}[{}]", path, i), item, source);
+            }
+        }
+        _ => {
+            sources.insert(path.to_string(), source.to_string());
+        }
+    }
+}
+
+/// Drops the sources of `path` and everything within it, for a value being replaced.
+fn forget(sources: &mut BTreeMap<String, String>, path: &str) {
+    sources.retain(|key, _| {
+        !(path.is_empty()
+            || key == path
+            || key
+                .strip_prefix(path)
+                .is_some_and(|rest| rest.starts_with('.') || rest.starts_with('[')))
+    });
+}
+
+impl Layered {
+    /// The config the layers make, with `profile` in use or the document's own, and the
+    /// overrides applied.
+    pub fn to_config(
+        &self,
+        profile: Option<&str>,
+        overrides: &Overrides,
+    ) -> Result<Config, Box<dyn Error>> {
+        let config = Config::from_value_with_profile(self.document.clone(), profile)?;
+        Ok(overrides.apply(config))
+    }
+
+    /// Every setting in effect, in document order, with the file, variable or flag it came
+    /// from. The profile is picked as it would be when running, and the others left out;
+    /// settings nothing sets show their defaults.
+    pub fn effective(
+        &self,
+        profile: Option<&str>,
+        overrides: &Overrides,
+    ) -> Result<Vec<Setting>, String> {
+        let mut sources = self.sources.clone();
+        let mut document = match &self.document {
+            // a legacy list of lights runs with the default settings
+            Value::Array(lights) => {
+                sources = sources
+                    .into_iter()
+                    .map(|(path, source)| (format!("lights{}", path), source))
+                    .collect();
+                json!({ "lights": lights })
+            }
+            Value::Object(_) => self.document.clone(),
+            _ => return Err("A config must be a table".to_string()),
+        };
+
+        let chosen = profile.map(str::to_string).or(document
+            .get("profile")
+            .and_then(Value::as_str)
+            .map(str::to_string));
+        if let Some(chosen) = &chosen {
+            let settings = document["profiles"][chosen.as_str()].clone();
+            // merged the same way as the document below, to drop the sources it replaces
+            let mut unprofiled = document.clone();
+            merge_value(&mut unprofiled, settings, "", PROFILE_SOURCE, &mut sources);
+            config::select_profile(&mut document, chosen)?;
+
+            let prefix = format!("{}.", join("profiles", chosen));
+            let from_profile: BTreeMap<String, String> = sources
+                .iter()
+                .filter_map(|(path, source)| {
+                    let path = path.strip_prefix(&prefix)?;
+                    Some((path.to_string(), format!("{}, profile {}", source, chosen)))
+                })
+                .collect();
+            for (path, source) in sources.iter_mut() {
+                if source == PROFILE_SOURCE {
+                    if let Some(from) = from_profile.get(path) {
+                        *source = from.clone();
+                    }
+                }
+            }
+            if profile.is_some() {
+                sources.insert("profile".to_string(), "--profile".to_string());
+            }
+        }
+
+        let Value::Object(fields) = &mut document else {
+            return Err("A config must be a table".to_string());
+        };
+        fields.remove("profiles");
+        forget(&mut sources, "profiles");
+        for (section, defaults) in config::default_settings() {
+            let Value::Object(defaults) = defaults else {
+                continue;
+            };
+            let current = fields
+                .entry(section)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(current) = current {
+                for (key, value) in defaults {
+                    current.entry(key).or_insert(value);
+                }
+            }
+        }
+
+        let mut set = |section: &str, key: &str, value: Value, source: String| {
+            fields[section][key] = value;
+            sources.insert(join(&toml_key(section), key), source);
+        };
+        for (section, overridden) in overrides.settings.iter() {
+            for (key, value) in overridden.as_object().into_iter().flatten() {
+                set(section, key, value.clone(), env_var_name(section, key));
+            }
+        }
+        if let Some(interface) = overrides.interface {
+            set(
+                "sacn",
+                "interface",
+                json!(interface.to_string()),
+                "--interface".to_string(),
+            );
+        }
+        if overrides.headless {
+            set("ui", "headless", json!(true), "--headless".to_string());
+        }
+
+        let mut settings = vec![];
+        collect_settings(&document, "", &sources, &mut settings);
+        Ok(settings)
+    }
+}
+
+fn collect_settings(
+    value: &Value,
+    path: &str,
+    sources: &BTreeMap<String, String>,
+    settings: &mut Vec<Setting>,
+) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (key, value) in fields {
+                collect_settings(value, &join(path, key), sources, settings);
+            }
+        }
+        Value::Array(items) if !items.is_empty() => {
+            for (i, item) in items.iter().enumerate() {
+                collect_settings(item, &format!("{}[{}]", path, i), sources, settings);
+            }
+        }
+        _ => settings.push(Setting {
+            path: path.to_string(),
+            value: value.clone(),
+            source: sources.get(path).cloned().unwrap_or("default".to_string()),
+        }),
+    }
+}
+
+/// Every problem with the merged layers. A single file is checked as written, so problems
+/// come with its lines; merged files can only say which light or setting is wrong.
+pub fn check(layers: &[Layer]) -> Vec<String> {
+    if let [layer] = layers {
+        return validate::validate(&layer.text, ConfigFormat::from_path(&layer.path))
+            .iter()
+            .map(|problem| problem.describe(&layer.path))
+            .collect();
+    }
+
+    let paths: Vec<String> = layers.iter().map(|layer| layer.path.clone()).collect();
+    match merge(layers) {
+        Ok(layered) => validate::validate_document(&layered.document)
+            .iter()
+            .map(|problem| problem.describe(&describe_paths(&paths)))
+            .collect(),
+        Err(problems) => problems,
+    }
+}
+
+/// Fails with a readable report if the layers at `paths` have problems once merged.
+pub async fn check_layers(paths: &[String]) -> Result<(), String> {
+    let layers = read_layers(paths).await?;
+    let mut report = check(&layers);
+    if report.is_empty() {
+        return Ok(());
+    }
+
+    report.push(format!(
+        "{} problem(s) in {}",
+        report.len(),
+        describe_paths(paths)
+    ));
+    Err(report.join("\n"))
+}
+
+/// Reads and merges the layers at `paths` into a config, reporting the first problem found.
+/// Run `check_layers` first for all of them.
+pub async fn load(
+    paths: &[String],
+    profile: Option<&str>,
+    overrides: &Overrides,
+) -> Result<Config, Box<dyn Error>> {
+    let layers = read_layers(paths).await?;
+    let layered = merge(&layers).map_err(|problems| problems.join("\n"))?;
+    layered.to_config(profile, overrides)
+}
+
+/// The variable that overrides a setting, such as `SACN_NEEWER_SACN_PORT` for `sacn.port`.
+pub fn env_var_name(section: &str, key: &str) -> String {
+    format!("{}{}_{}", ENV_PREFIX, section, key).to_ascii_uppercase()
+}
+
+/// Reads overrides of the settings sections' values from the variables `env_var_name` gives
+/// them, through `lookup`. Each is read as the type of its default and checked the way a
+/// config file's would be, so a typo stops the bridge rather than being ignored.
+pub fn env_settings(lookup: impl Fn(&str) -> Option<String>) -> Result<Map<String, Value>, String> {
+    let mut settings = Map::new();
+    for (section, defaults) in config::default_settings() {
+        let mut fields = Map::new();
+        for (key, default) in defaults.as_object().into_iter().flatten() {
+            let name = env_var_name(&section, key);
+            let Some(text) = lookup(&name) else {
+                continue;
+            };
+            let value = parse_env_value(&name, text.trim(), default)?;
+            let field = Map::from_iter([(key.clone(), value.clone())]);
+            Config::default()
+                .set_section_fields(&section, &field)
+                .map_err(|e| format!("{} is not a valid {}: {}", name, key, e))?;
+            fields.insert(key.clone(), value);
+        }
+        if !fields.is_empty() {
+            settings.insert(section, Value::Object(fields));
+        }
+    }
+    Ok(settings)
+}
+
+fn parse_env_value(name: &str, text: &str, default: &Value) -> Result<Value, String> {
+    match default {
+        Value::Bool(_) => match text.to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(json!(true)),
+            "0" | "false" | "no" | "off" => Ok(json!(false)),
+            _ => Err(format!("{} must be true or false, not {}", name, text)),
+        },
+        Value::Number(_) => text
+            .parse::<u64>()
+            .map(Value::from)
+            .map_err(|_| format!("{} must be a whole number, not {}", name, text)),
+        _ => Ok(json!(text)),
+    }
+}
+
+/// `path = value  # source` for each setting, with the sources lined up.
+pub fn format_settings(settings: &[Setting]) -> String {
+    let assignments: Vec<String> = settings
+        .iter()
+        .map(|setting| format!("{} = {}", setting.path, setting.value))
+        .collect();
+    let width = assignments
+        .iter()
+        .map(|assignment| assignment.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(MAX_SOURCE_COLUMN);
+    settings
+        .iter()
+        .zip(assignments)
+        .map(|(setting, assignment)| format!("{:<width$}  # {}\n", assignment, setting.source))
+        .collect()
+}
+
+/// Where each layer is looked for, lowest first, and what was found, for `config show`.
+pub fn describe_lookup(dirs: &[PathBuf], local: &str, env: &Map<String, Value>) -> String {
+    let mut rows: Vec<(String, String)> = dirs
+        .iter()
+        .map(|dir| match find_config_in(dir) {
+            Some(path) => (path, "found".to_string()),
+            None => (
+                dir.join("config.{toml,yaml,yml,json}")
+                    .to_string_lossy()
+                    .to_string(),
+                "not found".to_string(),
+            ),
+        })
+        .collect();
+    let local_found = if Path::new(local).is_file() {
+        "found"
+    } else {
+        "not found"
+    };
+    rows.push((local.to_string(), local_found.to_string()));
+    let variables: Vec<String> = env
+        .iter()
+        .flat_map(|(section, fields)| {
+            fields
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(key, _)| env_var_name(section, key))
+        })
+        .collect();
+    let variables = if variables.is_empty() {
+        format!("no {}* settings set", ENV_PREFIX)
+    } else {
+        variables.join(", ")
+    };
+    rows.push(("environment".to_string(), variables));
+
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let mut out = "Config layers, lowest first; each wins over the ones above it:\n".to_string();
+    for (name, status) in rows {
+        out.push_str(&format!("  {:<width$}  {}\n", name, status));
+    }
+    out
+}
+
+/// The merged settings with their sources, for `config show --effective`.
+pub async fn show_effective(
+    paths: &[String],
+    profile: Option<&str>,
+    overrides: &Overrides,
+) -> Result<String, Box<dyn Error>> {
+    let layers = read_layers(paths).await?;
+    let layered = merge(&layers).map_err(|problems| problems.join("\n"))?;
+    let settings = layered.effective(profile, overrides)?;
+    Ok(format!(
+        "# Merged from {}\n{}",
+        describe_paths(paths),
+        format_settings(&settings)
+    ))
+}
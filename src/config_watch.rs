@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use ratatui::style::Color;
+use tokio::{sync::RwLock, time};
+
+use crate::config::Overrides;
+use crate::config_layers::{self, Layer};
+use crate::light_controller::LightController;
+use crate::terminal_ui::TerminalUi;
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// The app status pane grows to fit; past this many problems the rest are only counted.
+const MAX_SHOWN_PROBLEMS: usize = 5;
+
+/// Polls the config files and applies them to the running controller whenever any of their
+/// contents change, keeping to the profile that's running. A config that fails validation is
+/// reported in the app status and otherwise ignored, so the previous patch keeps running.
+pub async fn watch(
+    paths: &[String],
+    overrides: &Overrides,
+    interval: Duration,
+    controller: &LightController,
+    terminal: &RwLock<TerminalUi>,
+) {
+    let mut last = config_layers::read_layers(paths).await.ok();
+    loop {
+        time::sleep(interval).await;
+
+        // editors may briefly remove a file while saving; try again next time
+        let Ok(layers) = config_layers::read_layers(paths).await else {
+            continue;
+        };
+        if last.as_ref() == Some(&layers) {
+            continue;
+        }
+        last = Some(layers.clone());
+
+        let (status, color) = match reload(&layers, overrides, controller, terminal).await {
+            Ok(message) => (message, Color::Green),
+            Err(message) => (message, Color::Red),
+        };
+        terminal
+            .write()
+            .await
+            .set_app_status(status.as_str(), color);
+    }
+}
+
+async fn reload(
+    layers: &[Layer],
+    overrides: &Overrides,
+    controller: &LightController,
+    terminal: &RwLock<TerminalUi>,
+) -> Result<String, String> {
+    let problems = config_layers::check(layers);
+    if !problems.is_empty() {
+        let paths: Vec<String> = layers.iter().map(|layer| layer.path.clone()).collect();
+        let mut report = vec![format!(
+            "Reload of {} failed, still running the previous config:",
+            config_layers::describe_paths(&paths)
+        )];
+        report.extend(problems.iter().take(MAX_SHOWN_PROBLEMS).cloned());
+        if problems.len() > MAX_SHOWN_PROBLEMS {
+            report.push(format!(
+                "... and {} more",
+                problems.len() - MAX_SHOWN_PROBLEMS
+            ));
+        }
+        return Err(report.join("\n"));
+    }
+
+    let layered = config_layers::merge(layers).map_err(|problems| problems.join("\n"))?;
+    let config = layered
+        .to_config(controller.get_profile().as_deref(), overrides)
+        .map_err(|e| e.to_string())?;
+    let summary = controller.reload(&config, terminal).await?;
+    Ok(summary.to_string())
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+use std::time::Duration;
+
+use rand::Rng;
+use ratatui::style::Color;
+
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    Idle,
+    Scanning,
+    Connecting,
+    Discovering,
+    Ready,
+    Backoff { delay: Duration, reason: String },
+}
+
+impl ConnectionState {
+    pub fn color(&self) -> Color {
+        match self {
+            ConnectionState::Idle => Color::Reset,
+            ConnectionState::Scanning
+            | ConnectionState::Connecting
+            | ConnectionState::Discovering => Color::Yellow,
+            ConnectionState::Ready => Color::Green,
+            ConnectionState::Backoff { .. } => Color::Red,
+        }
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionState::Idle => write!(f, "Idle"),
+            ConnectionState::Scanning => write!(f, "Scanning"),
+            ConnectionState::Connecting => write!(f, "Connecting"),
+            ConnectionState::Discovering => write!(f, "Discovering services"),
+            ConnectionState::Ready => write!(f, "Ready"),
+            ConnectionState::Backoff { delay, reason } => {
+                write!(
+                    f,
+                    "Retrying in {:.1}s after {}",
+                    delay.as_secs_f32(),
+                    reason
+                )
+            }
+        }
+    }
+}
+
+/// Exponential backoff with equal jitter: each delay is a random point in the upper half of
+/// the current window, and the window doubles per failure up to a ceiling.
+pub struct Backoff {
+    failures: u32,
+    base: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self::with_limits(BACKOFF_BASE, BACKOFF_MAX)
+    }
+
+    pub fn with_limits(base: Duration, max: Duration) -> Self {
+        Self {
+            failures: 0,
+            base,
+            max,
+        }
+    }
+
+    pub fn next_delay(&mut self, rng: &mut impl Rng) -> Duration {
+        let window = self
+            .base
+            .saturating_mul(2u32.saturating_pow(self.failures))
+            .min(self.max);
+        self.failures = self.failures.saturating_add(1);
+
+        let half = window / 2;
+        half + half.mul_f64(rng.gen::<f64>())
+    }
+
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+
+    pub fn get_failures(&self) -> u32 {
+        self.failures
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
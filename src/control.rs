@@ -0,0 +1,204 @@
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use ratatui::style::Color;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, RwLock};
+
+use crate::cli::parse_seconds;
+use crate::identify::DEFAULT_IDENTIFY_DURATION;
+use crate::light_controller::LightController;
+use crate::terminal_ui::TerminalUi;
+
+/// Where a running instance listens for control commands. Loopback only.
+pub const DEFAULT_CONTROL_ADDRESS: &str = "127.0.0.1:7170";
+
+// A client that connects and goes quiet shouldn't hold up the next one.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Something another process or the TUI asks a running instance to do.
+///
+/// On the wire each command is one line, answered by one line starting `ok` or `error`. A
+/// word with spaces in it, such as a label, goes in double quotes, with `\"` and `\\` for a
+/// quote or backslash inside them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCommand {
+    /// `identify <light or group> [seconds]`: flash a light, or every light in a group, so it
+    /// can be found in the rig.
+    Identify { target: String, duration: Duration },
+    /// `profile [name]`: switch to another profile from the config, keeping the connections
+    /// of lights it leaves alone, or say which one is running.
+    Profile { name: Option<String> },
+}
+
+impl ControlCommand {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        Self::from_words(&split_words(line)?)
+    }
+
+    /// Reads a command already split into words, such as the arguments to `send`.
+    pub fn from_words(words: &[String]) -> Result<Self, String> {
+        let mut words = words.iter().map(String::as_str);
+        match words.next() {
+            Some("identify") => {
+                let target = words.next().ok_or("identify needs a light")?.to_string();
+                let duration = match words.next() {
+                    Some(seconds) => parse_seconds(seconds)
+                        .map_err(|_| format!("Invalid duration {}", seconds))?,
+                    None => DEFAULT_IDENTIFY_DURATION,
+                };
+                Ok(ControlCommand::Identify { target, duration })
+            }
+            Some("profile") => Ok(ControlCommand::Profile {
+                name: words.next().map(str::to_string),
+            }),
+            Some(command) => Err(format!("Unknown command {}", command)),
+            None => Err("Empty command".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlCommand::Identify { target, duration } => {
+                write!(f, "identify {} {}", quote(target), duration.as_secs_f32())
+            }
+            ControlCommand::Profile { name: Some(name) } => write!(f, "profile {}", quote(name)),
+            ControlCommand::Profile { name: None } => write!(f, "profile"),
+        }
+    }
+}
+
+/// Splits a command line into words, keeping quoted words whole.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(first) = chars.next() else {
+            return Ok(words);
+        };
+
+        let mut word = String::new();
+        if first == '"' {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => word.push(chars.next().ok_or("Unfinished quote")?),
+                    Some(c) => word.push(c),
+                    None => return Err("Unfinished quote".to_string()),
+                }
+            }
+        } else {
+            word.push(first);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+        }
+        words.push(word);
+    }
+}
+
+// quotes a word that wouldn't survive `split_words` as it is
+fn quote(word: &str) -> String {
+    let plain =
+        !word.is_empty() && !word.starts_with('"') && !word.chars().any(|c| c.is_whitespace());
+    if plain {
+        return word.to_string();
+    }
+    let escaped = word.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", escaped)
+}
+
+/// Answers control commands on `address` until the app exits.
+pub async fn serve(address: &str, controller: &LightController, terminal: &RwLock<TerminalUi>) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            terminal.write().await.set_app_status(
+                format!("Control API unavailable on {}: {}", address, e).as_str(),
+                Color::Red,
+            );
+            return std::future::pending().await;
+        }
+    };
+
+    serve_listener(listener, controller, terminal).await
+}
+
+pub async fn serve_listener(
+    listener: TcpListener,
+    controller: &LightController,
+    terminal: &RwLock<TerminalUi>,
+) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        // a misbehaving client only loses its own connection
+        let _ =
+            tokio::time::timeout(CLIENT_TIMEOUT, handle_client(stream, controller, terminal)).await;
+    }
+}
+
+async fn handle_client(
+    stream: TcpStream,
+    controller: &LightController,
+    terminal: &RwLock<TerminalUi>,
+) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let response = match ControlCommand::parse(&line) {
+            Ok(command) => controller.execute(&command, terminal).await,
+            Err(e) => Err(e),
+        };
+        let reply = match response {
+            Ok(message) => format!("ok {}\n", message),
+            Err(message) => format!("error {}\n", message),
+        };
+        writer.write_all(reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Runs commands queued by the TUI, reporting failures in the app status.
+pub async fn command_loop(
+    mut receiver: mpsc::UnboundedReceiver<ControlCommand>,
+    controller: &LightController,
+    terminal: &RwLock<TerminalUi>,
+) {
+    while let Some(command) = receiver.recv().await {
+        if let Err(message) = controller.execute(&command, terminal).await {
+            terminal
+                .write()
+                .await
+                .set_app_status(message.as_str(), Color::Red);
+        }
+    }
+    std::future::pending().await
+}
+
+/// Sends one command to a running instance and returns its reply.
+pub async fn send(address: &str, command: &ControlCommand) -> Result<String, Box<dyn Error>> {
+    let stream = TcpStream::connect(address)
+        .await
+        .map_err(|e| format!("No running instance on {}: {}", address, e))?;
+    let (reader, mut writer) = stream.into_split();
+    writer
+        .write_all(format!("{}\n", command).as_bytes())
+        .await?;
+
+    let reply = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or("Connection closed without a reply")?;
+    match reply.split_once(' ').unwrap_or((reply.as_str(), "")) {
+        ("ok", message) => Ok(message.to_string()),
+        (_, message) => Err(message.into()),
+    }
+}
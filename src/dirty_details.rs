@@ -1,5 +1,10 @@
+use std::time::Duration;
+
 use tokio::time::Instant;
 
+// Lights that miss a write (or were power cycled) pick the state back up within this long.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
 pub struct DirtyDetails {
     dirty: bool,
     last_clean_time: Instant,
@@ -19,10 +24,24 @@ impl DirtyDetails {
     }
 
     pub fn is_dirty(&self) -> bool {
-        return self.dirty || self.last_clean_time.elapsed().as_secs() > 10;
+        self.dirty
     }
 
     pub fn dirty(&mut self) {
         self.dirty = true;
     }
+
+    pub fn last_clean_time(&self) -> Instant {
+        self.last_clean_time
+    }
+
+    pub fn keepalive_due(&self) -> Instant {
+        self.last_clean_time + KEEPALIVE_INTERVAL
+    }
+}
+
+impl Default for DirtyDetails {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,418 @@
+use std::error::Error;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use btleplug::api::BDAddr;
+
+use crate::config::{Config, LightMatch, Overrides, UNRESOLVED_NAME_ORDER};
+use crate::config_layers;
+use crate::light_state::DEFAULT_CCT_RANGE;
+use crate::model::ModelInfo;
+use crate::personality::Personality;
+use crate::scan::{self, ScanOptions, ScanResult};
+use crate::transport::BleTransport;
+
+const MANUFACTURER: &str = "Neewer";
+const MODEL: &str = "Bluetooth Light";
+// Kept fixed so a console sees a re-export as the same fixture type.
+const GDTF_FIXTURE_TYPE_ID: &str = "0BA123B9-8CFC-4674-955F-C0C9C256A583";
+
+pub const QXF_FILE_NAME: &str = "Neewer-Bluetooth-Light.qxf";
+pub const GDTF_FILE_NAME: &str = "Neewer@Bluetooth_Light.gdtf";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SheetFormat {
+    Csv,
+    Html,
+}
+
+impl SheetFormat {
+    /// Goes by the extension of the file being written, falling back to CSV.
+    fn from_output(output: Option<&str>) -> Self {
+        let is_html = output
+            .and_then(|output| Path::new(output).extension())
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+            });
+        if is_html {
+            SheetFormat::Html
+        } else {
+            SheetFormat::Csv
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, clap::Subcommand)]
+pub enum ExportKind {
+    /// The patch as a sheet: label, MAC, model, universe, address, footprint, personality.
+    Patch {
+        /// CSV or HTML; goes by the output's extension when absent.
+        #[arg(long, value_enum)]
+        format: Option<SheetFormat>,
+        /// Where to write it; standard output when absent.
+        #[arg(long, short)]
+        output: Option<String>,
+        /// Scan for this many seconds first, to fill in the model of lights matched by address.
+        #[arg(long, value_parser = crate::cli::parse_seconds)]
+        scan: Option<Duration>,
+    },
+    /// A QLC+ fixture definition with a mode per personality.
+    Qxf {
+        #[arg(long, short, default_value = QXF_FILE_NAME)]
+        output: String,
+    },
+    /// A GDTF fixture type with a DMX mode per personality.
+    Gdtf {
+        #[arg(long, short, default_value = GDTF_FILE_NAME)]
+        output: String,
+    },
+}
+
+impl ExportKind {
+    pub fn needs_bluetooth(&self) -> bool {
+        matches!(self, ExportKind::Patch { scan: Some(_), .. })
+    }
+}
+
+/// One light on the patch sheet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchRow {
+    pub label: String,
+    /// Known from the config for lights matched by address. None for name and index rules,
+    /// whose device depends on the order the running bridge first hears them.
+    pub mac: Option<BDAddr>,
+    pub model: Option<String>,
+    pub universe: u16,
+    pub address: u16,
+    pub footprint: u16,
+    pub personality: Personality,
+}
+
+/// The patch in universe and address order, filled in from `scanned` where it can be.
+pub fn patch_rows(config: &Config, scanned: &[ScanResult]) -> Vec<PatchRow> {
+    let mut rows: Vec<PatchRow> = config
+        .lights
+        .iter()
+        .map(|light| {
+            // a name rule's device depends on what the running bridge heard first
+            let mac = light.matcher.get_address();
+            let found = scanned.iter().find(|result| Some(result.address) == mac);
+            let model = found.and_then(|result| result.model.clone()).or_else(|| {
+                let LightMatch::Name { name, .. } = &light.matcher else {
+                    return None;
+                };
+                ModelInfo::lookup(name).map(|model| model.name.to_string())
+            });
+
+            PatchRow {
+                label: light.get_label(),
+                mac,
+                model,
+                universe: light.universe,
+                address: light.address,
+                footprint: light.personality.footprint(),
+                personality: light.personality,
+            }
+        })
+        .collect();
+    rows.sort_by_key(|row| (row.universe, row.address));
+    rows
+}
+
+const SHEET_COLUMNS: [&str; 7] = [
+    "Label",
+    "MAC",
+    "Model",
+    "Universe",
+    "Address",
+    "Footprint",
+    "Personality",
+];
+
+fn sheet_fields(row: &PatchRow) -> [String; 7] {
+    [
+        row.label.clone(),
+        row.mac
+            .map_or(UNRESOLVED_NAME_ORDER.to_string(), |mac| mac.to_string()),
+        row.model.clone().unwrap_or_default(),
+        row.universe.to_string(),
+        row.address.to_string(),
+        row.footprint.to_string(),
+        row.personality.name().to_string(),
+    ]
+}
+
+pub fn to_csv(rows: &[PatchRow]) -> String {
+    let mut out = SHEET_COLUMNS.join(",") + "\n";
+    for row in rows {
+        let fields: Vec<String> = sheet_fields(row).iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// A standalone page with the patch as a table, for printing.
+pub fn to_html(rows: &[PatchRow], title: &str) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; }}\n\
+         table {{ border-collapse: collapse; }}\n\
+         th, td {{ border: 1px solid #999; padding: 4px 8px; text-align: left; }}\n\
+         </style>\n\
+         </head>\n\
+         <body>\n\
+         <h1>{title}</h1>\n\
+         <table>\n\
+         <tr>{header}</tr>\n",
+        title = escape_xml(title),
+        header = SHEET_COLUMNS
+            .iter()
+            .map(|column| format!("<th>{}</th>", column))
+            .collect::<String>()
+    );
+    for row in rows {
+        out.push_str("<tr>");
+        for field in sheet_fields(row) {
+            out.push_str(&format!("<td>{}</td>", escape_xml(&field)));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n</body>\n</html>\n");
+    out
+}
+
+/// Escapes text for an element or a double-quoted attribute.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A QLC+ fixture definition with one mode per personality.
+pub fn to_qxf() -> String {
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE FixtureDefinition>\n\
+         <FixtureDefinition xmlns=\"http://www.qlcplus.org/FixtureDefinition\">\n \
+         <Creator>\n  \
+         <Name>sacn-neewer-lite</Name>\n  \
+         <Version>{}</Version>\n  \
+         <Author>sacn-neewer-lite</Author>\n \
+         </Creator>\n \
+         <Manufacturer>{}</Manufacturer>\n \
+         <Model>{}</Model>\n \
+         <Type>Color Changer</Type>\n",
+        env!("CARGO_PKG_VERSION"),
+        MANUFACTURER,
+        MODEL
+    );
+    for personality in Personality::ALL {
+        for channel in personality.channel_names() {
+            out.push_str(&qxf_channel(channel));
+        }
+    }
+    for personality in Personality::ALL {
+        out.push_str(&format!(" <Mode Name=\"{}\">\n", personality.name()));
+        for (number, channel) in personality.channel_names().iter().enumerate() {
+            out.push_str(&format!(
+                "  <Channel Number=\"{}\">{}</Channel>\n",
+                number, channel
+            ));
+        }
+        out.push_str(" </Mode>\n");
+    }
+    out.push_str("</FixtureDefinition>\n");
+    out
+}
+
+fn qxf_channel(name: &str) -> String {
+    match name {
+        "Red" => " <Channel Name=\"Red\" Preset=\"IntensityRed\"/>\n".to_string(),
+        "Green" => " <Channel Name=\"Green\" Preset=\"IntensityGreen\"/>\n".to_string(),
+        "Blue" => " <Channel Name=\"Blue\" Preset=\"IntensityBlue\"/>\n".to_string(),
+        "Dimmer" => " <Channel Name=\"Dimmer\" Preset=\"IntensityMasterDimmer\"/>\n".to_string(),
+        _ => format!(
+            " <Channel Name=\"{}\">\n  \
+             <Group Byte=\"0\">Colour</Group>\n  \
+             <Capability Min=\"0\" Max=\"255\">Warmest to coolest the light supports</Capability>\n \
+             </Channel>\n",
+            name
+        ),
+    }
+}
+
+/// The GDTF attribute a channel maps to, and its physical range.
+fn gdtf_attribute(name: &str) -> (&'static str, u16, u16) {
+    match name {
+        "Red" => ("ColorAdd_R", 0, 1),
+        "Green" => ("ColorAdd_G", 0, 1),
+        "Blue" => ("ColorAdd_B", 0, 1),
+        "Dimmer" => ("Dimmer", 0, 1),
+        _ => ("CTC", DEFAULT_CCT_RANGE.0, DEFAULT_CCT_RANGE.1),
+    }
+}
+
+/// The `description.xml` of a GDTF fixture type with one DMX mode per personality.
+pub fn gdtf_description() -> String {
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\" ?>\n\
+         <GDTF DataVersion=\"1.2\">\n  \
+         <FixtureType Name=\"{model}\" ShortName=\"{model}\" LongName=\"{manufacturer} {model}\" \
+         Manufacturer=\"{manufacturer}\" \
+         Description=\"A Neewer light driven over Bluetooth by sacn-neewer-lite.\" \
+         FixtureTypeID=\"{id}\" Thumbnail=\"\" RefFT=\"\" CanHaveChildren=\"No\">\n    \
+         <AttributeDefinitions>\n      \
+         <ActivationGroups>\n        \
+         <ActivationGroup Name=\"ColorRGB\"/>\n      \
+         </ActivationGroups>\n      \
+         <FeatureGroups>\n        \
+         <FeatureGroup Name=\"Dimmer\" Pretty=\"Dimmer\">\n          \
+         <Feature Name=\"Dimmer\"/>\n        \
+         </FeatureGroup>\n        \
+         <FeatureGroup Name=\"Color\" Pretty=\"Color\">\n          \
+         <Feature Name=\"RGB\"/>\n          \
+         <Feature Name=\"Color\"/>\n        \
+         </FeatureGroup>\n      \
+         </FeatureGroups>\n      \
+         <Attributes>\n        \
+         <Attribute Name=\"Dimmer\" Pretty=\"Dim\" Feature=\"Dimmer.Dimmer\" PhysicalUnit=\"LuminousIntensity\"/>\n        \
+         <Attribute Name=\"ColorAdd_R\" Pretty=\"R\" ActivationGroup=\"ColorRGB\" Feature=\"Color.RGB\" PhysicalUnit=\"ColorComponent\" Color=\"0.7347,0.2653,100\"/>\n        \
+         <Attribute Name=\"ColorAdd_G\" Pretty=\"G\" ActivationGroup=\"ColorRGB\" Feature=\"Color.RGB\" PhysicalUnit=\"ColorComponent\" Color=\"0.1596,0.8404,100\"/>\n        \
+         <Attribute Name=\"ColorAdd_B\" Pretty=\"B\" ActivationGroup=\"ColorRGB\" Feature=\"Color.RGB\" PhysicalUnit=\"ColorComponent\" Color=\"0.0366,0.0001,100\"/>\n        \
+         <Attribute Name=\"CTC\" Pretty=\"CTC\" Feature=\"Color.Color\" PhysicalUnit=\"Temperature\"/>\n      \
+         </Attributes>\n    \
+         </AttributeDefinitions>\n    \
+         <Wheels/>\n    \
+         <PhysicalDescriptions/>\n    \
+         <Models>\n      \
+         <Model Name=\"Body\" Length=\"0.2\" Width=\"0.2\" Height=\"0.05\" PrimitiveType=\"Cube\"/>\n    \
+         </Models>\n    \
+         <Geometries>\n      \
+         <Geometry Name=\"Body\" Model=\"Body\" Position=\"{{1,0,0,0}}{{0,1,0,0}}{{0,0,1,0}}{{0,0,0,1}}\"/>\n    \
+         </Geometries>\n    \
+         <DMXModes>\n",
+        model = MODEL,
+        manufacturer = MANUFACTURER,
+        id = GDTF_FIXTURE_TYPE_ID
+    );
+    for personality in Personality::ALL {
+        out.push_str(&format!(
+            "      <DMXMode Name=\"{}\" Geometry=\"Body\">\n        <DMXChannels>\n",
+            personality.name()
+        ));
+        for (offset, channel) in personality.channel_names().iter().enumerate() {
+            let (attribute, from, to) = gdtf_attribute(channel);
+            out.push_str(&format!(
+                "          <DMXChannel DMXBreak=\"1\" Offset=\"{offset}\" \
+                 InitialFunction=\"Body_{attribute}.{attribute}.{attribute} 1\" Highlight=\"None\" Geometry=\"Body\">\n            \
+                 <LogicalChannel Attribute=\"{attribute}\" Snap=\"No\" Master=\"None\" MibFade=\"0\" DMXChangeTimeLimit=\"0\">\n              \
+                 <ChannelFunction Name=\"{attribute} 1\" Attribute=\"{attribute}\" OriginalAttribute=\"\" DMXFrom=\"0/1\" Default=\"0/1\" \
+                 PhysicalFrom=\"{from}\" PhysicalTo=\"{to}\" RealFade=\"0\" RealAcceleration=\"0\"/>\n            \
+                 </LogicalChannel>\n          \
+                 </DMXChannel>\n",
+                offset = offset + 1,
+                attribute = attribute,
+                from = from,
+                to = to
+            ));
+        }
+        out.push_str(
+            "        </DMXChannels>\n        <Relations/>\n        <FTMacros/>\n      </DMXMode>\n",
+        );
+    }
+    out.push_str(&format!(
+        "    </DMXModes>\n    \
+         <Revisions>\n      \
+         <Revision Text=\"Exported by sacn-neewer-lite {}\" UserID=\"0\"/>\n    \
+         </Revisions>\n    \
+         <FTPresets/>\n    \
+         <Protocols/>\n  \
+         </FixtureType>\n\
+         </GDTF>\n",
+        env!("CARGO_PKG_VERSION")
+    ));
+    out
+}
+
+/// A .gdtf file: a zip holding the description.
+pub fn gdtf_archive() -> zip::result::ZipResult<Vec<u8>> {
+    let mut archive = zip::ZipWriter::new(Cursor::new(vec![]));
+    let options =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    archive.start_file("description.xml", options)?;
+    archive.write_all(gdtf_description().as_bytes())?;
+    Ok(archive.finish()?.into_inner())
+}
+
+/// Writes what `kind` asks for. Only a patch export with a scan uses `transports`.
+pub async fn run(
+    kind: &ExportKind,
+    config_paths: &[String],
+    profile: Option<&str>,
+    overrides: &Overrides,
+    transports: &[Arc<dyn BleTransport>],
+) -> Result<(), Box<dyn Error>> {
+    match kind {
+        ExportKind::Patch {
+            format,
+            output,
+            scan,
+        } => {
+            let config = config_layers::load(config_paths, profile, overrides).await?;
+            let config_name = config_layers::describe_paths(config_paths);
+            let scanned = match scan {
+                Some(duration) => {
+                    let options = ScanOptions {
+                        duration: *duration,
+                        ..Default::default()
+                    };
+                    scan::scan(transports, &options, Some(&config)).await?
+                }
+                None => vec![],
+            };
+            let rows = patch_rows(&config, &scanned);
+            let title = match config.profile.as_deref() {
+                Some(profile) => format!("Patch: {} ({})", config_name, profile),
+                None => format!("Patch: {}", config_name),
+            };
+            let sheet = match format.unwrap_or(SheetFormat::from_output(output.as_deref())) {
+                SheetFormat::Csv => to_csv(&rows),
+                SheetFormat::Html => to_html(&rows, &title),
+            };
+            match output {
+                Some(output) => {
+                    tokio::fs::write(output, sheet).await?;
+                    println!("Wrote {}", output);
+                }
+                None => print!("{}", sheet),
+            }
+        }
+        ExportKind::Qxf { output } => {
+            tokio::fs::write(output, to_qxf()).await?;
+            println!("Wrote {}", output);
+        }
+        ExportKind::Gdtf { output } => {
+            tokio::fs::write(output, gdtf_archive()?).await?;
+            println!("Wrote {}", output);
+        }
+    }
+    Ok(())
+}
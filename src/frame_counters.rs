@@ -0,0 +1,43 @@
+/// Per-light tally of sACN frames against BLE writes, for tuning update rates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameCounters {
+    /// Frames addressed to the light, changed or not.
+    pub received: u64,
+    pub sent: u64,
+    /// Changes replaced by a newer one before they could be written.
+    pub coalesced: u64,
+    /// Write attempts that failed and were tried again.
+    pub retried: u64,
+    /// Writes that still failed once the retries ran out.
+    pub failed: u64,
+    unsent_change: bool,
+}
+
+impl FrameCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_received(&mut self, changed: bool) {
+        self.received += 1;
+        if changed {
+            if self.unsent_change {
+                self.coalesced += 1;
+            }
+            self.unsent_change = true;
+        }
+    }
+
+    pub fn record_sent(&mut self) {
+        self.sent += 1;
+        self.unsent_change = false;
+    }
+
+    pub fn record_retry(&mut self) {
+        self.retried += 1;
+    }
+
+    pub fn record_failed(&mut self) {
+        self.failed += 1;
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// How long a light flashes when identified without an explicit duration.
+pub const DEFAULT_IDENTIFY_DURATION: Duration = Duration::from_secs(5);
+
+// Half of one on/off cycle. Slow enough that every model keeps up.
+const FLASH_PERIOD: Duration = Duration::from_millis(250);
+
+/// A light flashing full on and off to show where it is, taking over from sACN until it ends.
+#[derive(Debug, Clone, Copy)]
+pub struct Identify {
+    started: Instant,
+    duration: Duration,
+    last_written: Option<Instant>,
+}
+
+impl Identify {
+    pub fn new(started: Instant, duration: Duration) -> Self {
+        Self {
+            started,
+            duration,
+            last_written: None,
+        }
+    }
+
+    pub fn record_write(&mut self, now: Instant) {
+        self.last_written = Some(now);
+    }
+
+    /// When the light next needs a write: straight away, then each time the flash changes.
+    pub fn next_write(&self) -> Instant {
+        match self.last_written {
+            Some(last_written) => self.next_change(last_written),
+            None => self.started,
+        }
+    }
+
+    pub fn is_over(&self, now: Instant) -> bool {
+        now >= self.started + self.duration
+    }
+
+    /// Whether the light should be on at `now`. Starts lit.
+    pub fn is_lit(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.started);
+        (elapsed.as_millis() / FLASH_PERIOD.as_millis()).is_multiple_of(2)
+    }
+
+    /// When the flash next changes after `after`, counting the end as a change.
+    pub fn next_change(&self, after: Instant) -> Instant {
+        let end = self.started + self.duration;
+        if after < self.started {
+            return self.started;
+        }
+
+        let periods = after.duration_since(self.started).as_millis() / FLASH_PERIOD.as_millis();
+        let next = self.started + FLASH_PERIOD * (periods as u32 + 1);
+        next.min(end)
+    }
+}
@@ -0,0 +1,177 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::time::Instant;
+
+use crate::color::Color;
+use crate::light_state::LightState;
+
+// Output closer than this to the target on every channel counts as arrived.
+const SETTLED_EPSILON: f32 = 0.5;
+
+/// How a light moves between the states sACN asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Interpolation {
+    /// Jump straight to each new state.
+    #[default]
+    Snap,
+    /// Follow the estimated target with an exponential lag of `time_constant_ms`.
+    #[serde(alias = "linear")]
+    Smooth { time_constant_ms: u64 },
+    /// As `Smooth`, but jump straight there when any channel moves by more than `threshold`.
+    SnapAbove {
+        threshold: u8,
+        time_constant_ms: u64,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    channels: [f32; 3],
+    at: Instant,
+}
+
+/// Estimates where the desk is heading from the last two frames and eases the output
+/// towards it, so rate-limited writes still show a smooth fade.
+pub struct Interpolator {
+    mode: Interpolation,
+    kind: LightState,
+    target: Option<Sample>,
+    previous: Option<Sample>,
+    output: Option<[f32; 3]>,
+    last_sample: Option<Instant>,
+}
+
+impl Interpolator {
+    pub fn new(mode: Interpolation, initial: LightState) -> Self {
+        Self {
+            mode,
+            kind: initial,
+            target: None,
+            previous: None,
+            output: Some(Self::channels(&initial)),
+            last_sample: None,
+        }
+    }
+
+    /// Jumps straight to `state`, forgetting any movement in progress.
+    pub fn reset(&mut self, state: LightState) {
+        *self = Self::new(self.mode, state);
+    }
+
+    pub fn set_target(&mut self, state: LightState, now: Instant) {
+        if self.is_settled() {
+            // a new movement starts now, not at whenever we last sent
+            self.last_sample = Some(now);
+        }
+
+        let channels = Self::channels(&state);
+        let same_kind = std::mem::discriminant(&state) == std::mem::discriminant(&self.kind);
+        self.kind = state;
+
+        let snap = match self.mode {
+            Interpolation::Snap => true,
+            Interpolation::Smooth { .. } => false,
+            Interpolation::SnapAbove { threshold, .. } => self.output.is_some_and(|output| {
+                output
+                    .iter()
+                    .zip(channels.iter())
+                    .any(|(o, c)| (o - c).abs() > threshold as f32)
+            }),
+        };
+
+        if snap || !same_kind {
+            self.output = Some(channels);
+            self.previous = None;
+        } else {
+            self.previous = self.target;
+        }
+        self.target = Some(Sample { channels, at: now });
+    }
+
+    /// The state to send right now.
+    pub fn sample(&mut self, now: Instant) -> LightState {
+        let Some(target) = self.target else {
+            return self.kind;
+        };
+        let Some(time_constant) = self.time_constant() else {
+            return self.kind;
+        };
+
+        let predicted = self.predict(target, now);
+        let output = self.output.unwrap_or(predicted);
+        let elapsed = self
+            .last_sample
+            .map_or(Duration::ZERO, |last| now.saturating_duration_since(last));
+        let keep = (-elapsed.as_secs_f32() / time_constant.as_secs_f32()).exp();
+
+        let next: [f32; 3] =
+            std::array::from_fn(|i| predicted[i] + (output[i] - predicted[i]) * keep);
+        self.output = Some(next);
+        self.last_sample = Some(now);
+        Self::state_from(&self.kind, next)
+    }
+
+    /// Whether the output has caught up with the latest target.
+    pub fn is_settled(&self) -> bool {
+        match (self.output, self.target) {
+            (Some(output), Some(target)) => output
+                .iter()
+                .zip(target.channels.iter())
+                .all(|(o, t)| (o - t).abs() < SETTLED_EPSILON),
+            _ => true,
+        }
+    }
+
+    fn time_constant(&self) -> Option<Duration> {
+        match self.mode {
+            Interpolation::Snap => None,
+            Interpolation::Smooth { time_constant_ms }
+            | Interpolation::SnapAbove {
+                time_constant_ms, ..
+            } => Some(Duration::from_millis(time_constant_ms.max(1))),
+        }
+    }
+
+    // Carries the last frame-to-frame velocity forward until the next frame is due. Unchanged
+    // frames never arrive, so once that time has passed the desk has stopped and the target
+    // itself is the estimate.
+    fn predict(&self, target: Sample, now: Instant) -> [f32; 3] {
+        let Some(previous) = self.previous else {
+            return target.channels;
+        };
+        let interval = target.at.saturating_duration_since(previous.at);
+        let ahead = now.saturating_duration_since(target.at);
+        if interval.is_zero() || ahead >= interval {
+            return target.channels;
+        }
+
+        let fraction = ahead.as_secs_f32() / interval.as_secs_f32();
+        std::array::from_fn(|i| {
+            let step = target.channels[i] - previous.channels[i];
+            (target.channels[i] + step * fraction).clamp(0.0, 255.0)
+        })
+    }
+
+    fn channels(state: &LightState) -> [f32; 3] {
+        match state {
+            LightState::Rgb(color) => [color.red as f32, color.green as f32, color.blue as f32],
+            LightState::Cct {
+                dimmer,
+                temperature,
+            } => [*dimmer as f32, *temperature as f32, 0.0],
+        }
+    }
+
+    fn state_from(kind: &LightState, channels: [f32; 3]) -> LightState {
+        let [a, b, c] = channels.map(|value| value.round().clamp(0.0, 255.0) as u8);
+        match kind {
+            LightState::Rgb(_) => LightState::Rgb(Color::new(a, b, c)),
+            LightState::Cct { .. } => LightState::Cct {
+                dimmer: a,
+                temperature: b,
+            },
+        }
+    }
+}
@@ -1,203 +1,580 @@
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
-use btleplug::api::{BDAddr, Central, Manager as _, Peripheral as _, WriteType};
-use btleplug::platform::{Adapter, Manager, Peripheral};
+use btleplug::api::{BDAddr, WriteType};
+use futures::StreamExt;
 use lazy_static::lazy_static;
-use tokio::sync::RwLock;
+use tokio::sync::{watch, Mutex, Notify, RwLock, Semaphore};
 use uuid::Uuid;
 
-use crate::color::Color;
+use crate::config::{LightConfig, LightMatch};
+use crate::connection_state::{Backoff, ConnectionState};
 use crate::dirty_details::DirtyDetails;
+use crate::frame_counters::FrameCounters;
+use crate::identify::Identify;
+use crate::interpolation::Interpolator;
+use crate::light_state::LightState;
+use crate::model::ModelInfo;
+use crate::personality::Personality;
+use crate::protocol::Protocol;
+use crate::readback::Readback;
 use crate::terminal_ui::TerminalUi;
+use crate::transport::LightLink;
+use crate::write_policy::WriteTracker;
 
 const UUID_STR: &str = "69400002-B5A3-F393-E0A9-E50E24DCCA99";
+const NOTIFY_UUID_STR: &str = "69400003-B5A3-F393-E0A9-E50E24DCCA99";
 lazy_static! {
     static ref write_uuid: Uuid = Uuid::parse_str(UUID_STR).unwrap();
+    static ref notify_uuid: Uuid = Uuid::parse_str(NOTIFY_UUID_STR).unwrap();
 }
 
+// Used when neither the config nor the model table gives an update rate.
+const DEFAULT_MAX_UPDATE_HZ: u16 = 20;
+
+const READBACK_QUERY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+// Each write gets this many attempts, spaced by a short backoff, before it counts as failed.
+const MAX_WRITE_ATTEMPTS: u32 = 3;
+const WRITE_RETRY_BASE: Duration = Duration::from_millis(20);
+const WRITE_RETRY_MAX: Duration = Duration::from_millis(200);
+
 pub struct Light {
-    id: BDAddr,
-    universe: u16,
-    address: u16,
-    peripheral: RwLock<Option<Peripheral>>,
-    color: RwLock<Color>,
+    matcher: LightMatch,
+    // the patch can change under a running light when the config is reloaded
+    label: std::sync::RwLock<String>,
+    universe: AtomicU16,
+    address: AtomicU16,
+    protocol: Option<Protocol>,
+    personality: std::sync::RwLock<Personality>,
+    adapter: Option<String>,
+    active_protocol: RwLock<Protocol>,
+    model: RwLock<Option<&'static ModelInfo>>,
+    peripheral: RwLock<Option<Arc<dyn LightLink>>>,
+    discovered: watch::Sender<Option<Arc<dyn LightLink>>>,
+    state: RwLock<LightState>,
+    /// Whether `state` came from sACN or a saved state, rather than the blackout it starts at.
+    state_known: AtomicBool,
+    /// Last state actually written, which trails `state` while interpolating.
+    sent_state: RwLock<LightState>,
+    /// Whether the light has been sent a commanded or saved state, rather than only the
+    /// blackout it starts at or identify flashes.
+    state_sent: AtomicBool,
+    /// A state saved by an earlier run, sent when the light first connects.
+    saved_state: Mutex<Option<LightState>>,
+    /// Whether a saved state has been offered, so later offers after a reload are ignored.
+    saved_state_offered: AtomicBool,
     dirty_details: RwLock<DirtyDetails>,
+    readback: RwLock<Readback>,
+    connection_state: watch::Sender<ConnectionState>,
+    backoff: Mutex<Backoff>,
+    min_write_interval: Option<Duration>,
+    max_update_hz: Option<u16>,
+    counters: Mutex<FrameCounters>,
+    interpolator: Mutex<Interpolator>,
+    write_tracker: Mutex<WriteTracker>,
+    identify: Mutex<Option<Identify>>,
+    /// Woken when the commanded state changes.
+    changed: Notify,
+    /// Woken when the adapter reports this light disconnected.
+    lost: Notify,
+    /// Set once the light has been removed from the config, which ends `find_loop`.
+    retired: watch::Sender<bool>,
+    /// Set while `find_loop` runs, so retiring can wait for it to stop.
+    running: watch::Sender<bool>,
+}
+
+// Clears `running` however `find_loop` ends, including being dropped mid-await.
+struct RunningGuard<'a>(&'a watch::Sender<bool>);
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.0.send_replace(false);
+    }
 }
 
 impl Light {
-    pub fn new(id: BDAddr, universe: u16, address: u16) -> Self {
+    pub fn new(config: &LightConfig) -> Self {
         Self {
-            id,
-            universe,
-            address,
+            matcher: config.matcher.clone(),
+            label: std::sync::RwLock::new(config.get_label()),
+            universe: AtomicU16::new(config.universe),
+            address: AtomicU16::new(config.address),
+            protocol: config.protocol,
+            personality: std::sync::RwLock::new(config.personality),
+            adapter: config.adapter.clone(),
+            active_protocol: RwLock::new(config.protocol.unwrap_or(Protocol::Legacy)),
+            model: RwLock::new(None),
             peripheral: RwLock::new(None),
-            color: RwLock::new(Color::new(0, 0, 0)),
+            discovered: watch::channel(None).0,
+            state: RwLock::new(config.personality.decode(&[0, 0, 0])),
+            state_known: AtomicBool::new(false),
+            sent_state: RwLock::new(config.personality.decode(&[0, 0, 0])),
+            state_sent: AtomicBool::new(false),
+            saved_state: Mutex::new(None),
+            saved_state_offered: AtomicBool::new(false),
             dirty_details: RwLock::new(DirtyDetails::new()),
+            readback: RwLock::new(Readback::new()),
+            connection_state: watch::channel(ConnectionState::Idle).0,
+            backoff: Mutex::new(Backoff::new()),
+            min_write_interval: config.min_write_interval,
+            max_update_hz: config.max_update_hz,
+            counters: Mutex::new(FrameCounters::new()),
+            interpolator: Mutex::new(Interpolator::new(
+                config.interpolation,
+                config.personality.decode(&[0, 0, 0]),
+            )),
+            write_tracker: Mutex::new(WriteTracker::new(config.write_policy)),
+            identify: Mutex::new(None),
+            changed: Notify::new(),
+            lost: Notify::new(),
+            retired: watch::channel(false).0,
+            running: watch::channel(false).0,
         }
     }
 
-    fn get_checksum(send_value: &[u8]) -> u8 {
-        let mut check_sum: u8 = 0;
+    async fn send_color(&self) -> Result<bool, btleplug::Error> {
+        let model = *self.model.read().await;
+        if model.is_some_and(|m| !m.supports(self.get_personality())) {
+            self.dirty_details.write().await.clean();
+            return Ok(false);
+        }
 
-        for value in send_value {
-            check_sum = check_sum.wrapping_add(*value);
+        if !self.dirty_details.read().await.is_dirty() {
+            return Ok(false);
         }
 
-        return check_sum;
+        let now = tokio::time::Instant::now();
+        let flash = self.identify_frame(now).await;
+        let (state, settled) = match flash {
+            Some(flash) => (flash, false),
+            None => {
+                let mut interpolator = self.interpolator.lock().await;
+                let state = interpolator.sample(now);
+                (state, interpolator.is_settled())
+            }
+        };
+        *self.sent_state.write().await = state;
+
+        let protocol = *self.active_protocol.read().await;
+        let mac = self.get_bound_address().await.unwrap_or_default();
+        let color_cmd = state.to_command(protocol, mac, model);
+
+        let send_result = self.write_with_retry(&color_cmd).await;
+        let mut dirty_details = self.dirty_details.write().await;
+        dirty_details.clean();
+        if !settled {
+            // still easing towards the target, so go again after the write gap
+            dirty_details.dirty();
+        }
+        drop(dirty_details);
+        if send_result.is_ok() {
+            self.counters.lock().await.record_sent();
+            if flash.is_none() && self.state_known.load(Ordering::Relaxed) {
+                self.state_sent.store(true, Ordering::Relaxed);
+            }
+        }
+        send_result.map(|_| true)
     }
 
-    async fn send_color(&self) -> Result<bool, impl Error> {
-        let color = self.color.read().await;
-        let (hue, saturation, brightness) = color.to_hsv();
-        drop(color);
-
-        let hue_lsb = (hue & 0xFF) as u8;
-        let hue_msb = ((hue >> 8) & 0xFF) as u8;
+    /// The flash state to send instead of live data, while identifying.
+    async fn identify_frame(&self, now: tokio::time::Instant) -> Option<LightState> {
+        let mut lock = self.identify.lock().await;
+        let identify = lock.as_mut()?;
+        if identify.is_over(now) {
+            lock.take();
+            return None;
+        }
+        identify.record_write(now);
 
-        let mut color_cmd = vec![120, 134, 4, hue_lsb, hue_msb, saturation, brightness];
-        color_cmd.push(Light::get_checksum(&color_cmd));
+        let level = if identify.is_lit(now) { 255 } else { 0 };
+        Some(self.get_personality().decode(&[level; 3]))
+    }
 
-        let lock = self.peripheral.read().await;
+    /// Flashes the light for `duration`, ignoring sACN until it's done.
+    pub async fn identify(&self, duration: Duration) {
+        *self.identify.lock().await = Some(Identify::new(tokio::time::Instant::now(), duration));
+        self.dirty_details.write().await.dirty();
+        self.changed.notify_one();
+    }
 
-        // find the characteristic we want
-        if lock.is_some() {
-            let peripheral = lock.as_ref().unwrap();
-            let chars = peripheral.characteristics();
-            let maybe_cmd_char = chars.iter().find(|c| c.uuid == *write_uuid);
-
-            match maybe_cmd_char {
-                Some(cmd_char) => {
-                    let details_read_lock = self.dirty_details.read().await;
-                    if details_read_lock.is_dirty() {
-                        drop(details_read_lock);
-                        let send_result = peripheral
-                            .write(cmd_char, &color_cmd, WriteType::WithoutResponse)
-                            .await;
-                        let mut details_write_lock = self.dirty_details.write().await;
-                        details_write_lock.clean();
-                        match send_result {
-                            Ok(_) => {
-                                return Ok(true);
-                            }
-                            Err(e) => {
-                                return Err(e);
-                            }
-                        }
-                    } else {
-                        return Ok(false);
+    /// Writes `cmd` using the light's write policy, retrying a failed write a few times
+    /// before giving up on it.
+    async fn write_with_retry(&self, cmd: &[u8]) -> Result<(), btleplug::Error> {
+        let mut backoff = Backoff::with_limits(WRITE_RETRY_BASE, WRITE_RETRY_MAX);
+        let mut attempt = 1;
+        loop {
+            let write_type = self.write_tracker.lock().await.write_type();
+            let result = self.write_command(cmd, write_type).await;
+            if result.is_err() && !self.is_connected().await.unwrap_or(false) {
+                // a lost link is the search loop's problem, not a failed write
+                return result;
+            }
+            match result {
+                Ok(()) => {
+                    // only an acknowledged write shows it arrived; readback judges the rest
+                    if write_type == WriteType::WithResponse {
+                        self.write_tracker.lock().await.record_success();
                     }
+                    return Ok(());
+                }
+                Err(_) if attempt < MAX_WRITE_ATTEMPTS => {
+                    self.counters.lock().await.record_retry();
+                    let delay = backoff.next_delay(&mut rand::thread_rng());
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
                 }
-                None => {
-                    return Err(btleplug::Error::NoSuchCharacteristic);
+                Err(e) => {
+                    self.counters.lock().await.record_failed();
+                    self.write_tracker.lock().await.record_failure();
+                    return Err(e);
                 }
             }
-        } else {
-            return Err(btleplug::Error::NoSuchCharacteristic);
         }
     }
 
-    pub async fn set_color_rgb(&self, red: u8, green: u8, blue: u8) {
-        let read_lock = self.color.read().await;
-        if read_lock.red == red && read_lock.green == green && read_lock.blue == blue {
-            return;
+    async fn write_command(
+        &self,
+        cmd: &[u8],
+        write_type: WriteType,
+    ) -> Result<(), btleplug::Error> {
+        let lock = self.peripheral.read().await;
+
+        match lock.as_ref() {
+            Some(peripheral) => peripheral.write(*write_uuid, cmd, write_type).await,
+            None => Err(btleplug::Error::NoSuchCharacteristic),
+        }
+    }
+
+    /// Sends the status queries with a plain write. They aren't color writes, so they stay
+    /// out of the counters and the adaptive write tracker.
+    async fn query_status(&self, queries: &[Vec<u8>]) -> Result<(), btleplug::Error> {
+        let write_type = self.write_tracker.lock().await.write_type();
+        for query in queries {
+            self.write_command(query, write_type).await?;
         }
+        Ok(())
+    }
+
+    /// Hands the light a peripheral advertising its address, to connect to when it next searches.
+    pub fn offer(&self, link: Arc<dyn LightLink>) {
+        self.discovered.send_replace(Some(link));
+    }
+
+    pub async fn set_state(&self, state: LightState) {
+        self.state_known.store(true, Ordering::Relaxed);
+        let read_lock = self.state.read().await;
+        let changed = *read_lock != state;
         drop(read_lock);
+        self.counters.lock().await.record_received(changed);
+        if !changed {
+            return;
+        }
 
-        let mut lock = self.color.write().await;
-        lock.red = red;
-        lock.green = green;
-        lock.blue = blue;
+        *self.state.write().await = state;
+        self.interpolator
+            .lock()
+            .await
+            .set_target(state, tokio::time::Instant::now());
         self.dirty_details.write().await.dirty();
+        self.changed.notify_one();
     }
 
-    pub async fn connect(&self, peripheral: Peripheral, terminal: &RwLock<TerminalUi>) {
-        terminal.write().await.set_light_status(
-            self.id.to_string().as_str(),
-            "Connecting",
-            ratatui::style::Color::Yellow,
-        );
+    /// The state last commanded, or None if nothing has been yet.
+    pub async fn get_known_state(&self) -> Option<LightState> {
+        if !self.state_known.load(Ordering::Relaxed) {
+            return None;
+        }
+        Some(*self.state.read().await)
+    }
+
+    /// The state last written to the light, or None if it hasn't been sent one. Identify
+    /// flashes don't count.
+    pub async fn get_sent_state(&self) -> Option<LightState> {
+        if self.identify.lock().await.is_some() || !self.state_sent.load(Ordering::Relaxed) {
+            return None;
+        }
+        Some(*self.sent_state.read().await)
+    }
+
+    /// Hands the light a state saved by an earlier run, to start at when it first connects
+    /// unless sACN has moved it on by then. Only the first offer counts. Returns false if
+    /// it's ignored, including when it was saved under another personality and doesn't fit.
+    pub async fn offer_saved_state(&self, state: LightState) -> bool {
+        if std::mem::discriminant(&state) != std::mem::discriminant(&*self.state.read().await) {
+            return false;
+        }
+        if self.saved_state_offered.swap(true, Ordering::Relaxed) {
+            return false;
+        }
+        *self.saved_state.lock().await = Some(state);
+        true
+    }
+
+    /// Starts the light at a state saved by an earlier run, without fading to it. Returns
+    /// false if the state was saved under another personality and doesn't fit.
+    async fn restore_state(&self, state: LightState) -> bool {
+        if std::mem::discriminant(&state) != std::mem::discriminant(&*self.state.read().await) {
+            return false;
+        }
+        self.state_known.store(true, Ordering::Relaxed);
+        *self.state.write().await = state;
+        self.interpolator.lock().await.reset(state);
+        self.dirty_details.write().await.dirty();
+        self.changed.notify_one();
+        true
+    }
+
+    /// Moves the light to the universe, address, personality and label in `config` without
+    /// touching its connection. Returns whether anything changed; moving the light's TUI
+    /// status to a new label is left to the caller.
+    pub async fn repatch(&self, config: &LightConfig) -> bool {
+        let label = config.get_label();
+        let changed = self.get_universe() != config.universe
+            || self.get_address() != config.address
+            || self.get_personality() != config.personality
+            || self.get_label() != label;
+        if !changed {
+            return false;
+        }
+
+        self.universe.store(config.universe, Ordering::Relaxed);
+        self.address.store(config.address, Ordering::Relaxed);
+        *self.personality.write().unwrap() = config.personality;
+        *self.label.write().unwrap() = label;
+        // whatever arrives at the new address is sent on the next frame
+        self.dirty_details.write().await.dirty();
+        self.changed.notify_one();
+        true
+    }
+
+    /// Stops `find_loop` and waits for it to finish, so nothing reconnects the light once
+    /// it's dropped.
+    pub async fn retire(&self) {
+        self.retired.send_replace(true);
+        let _ = self.running.subscribe().wait_for(|running| !*running).await;
+    }
+
+    /// Shortest time between writes: the update rate cap, or the minimum interval if one is
+    /// set and stricter.
+    pub fn get_write_gap(&self, model: Option<&ModelInfo>) -> Duration {
+        let max_update_hz = self
+            .max_update_hz
+            .or(model.map(|m| m.max_update_hz))
+            .unwrap_or(DEFAULT_MAX_UPDATE_HZ)
+            .max(1);
+        let rate_gap = Duration::from_secs(1) / max_update_hz as u32;
+        self.min_write_interval
+            .map_or(rate_gap, |interval| interval.max(rate_gap))
+    }
+
+    pub async fn get_counters(&self) -> FrameCounters {
+        *self.counters.lock().await
+    }
+
+    /// How writes are currently being sent, for the TUI.
+    pub async fn get_write_mode(&self) -> String {
+        self.write_tracker.lock().await.to_string()
+    }
+
+    pub fn link_lost(&self) {
+        self.lost.notify_one();
+    }
+
+    /// Connects and discovers services, returning the reason on failure.
+    pub async fn connect(
+        &self,
+        peripheral: Arc<dyn LightLink>,
+        terminal: &RwLock<TerminalUi>,
+    ) -> Result<(), String> {
+        self.set_connection_state(terminal, ConnectionState::Connecting)
+            .await;
 
         let mut peripheral_lock = self.peripheral.write().await;
         peripheral_lock.replace(peripheral);
 
         if let Err(e) = peripheral_lock.as_ref().unwrap().connect().await {
             peripheral_lock.take();
-            self.set_error_status(terminal, "Failed to connect", e)
-                .await;
-            return;
+            return Err(format!("Failed to connect: {:?}", e));
         }
+
+        drop(peripheral_lock);
+        self.set_connection_state(terminal, ConnectionState::Discovering)
+            .await;
+        let mut peripheral_lock = self.peripheral.write().await;
+
         if let Err(e) = peripheral_lock.as_ref().unwrap().discover_services().await {
+            // don't leave a half-open link behind for the next attempt
+            let _ = peripheral_lock.as_ref().unwrap().disconnect().await;
             peripheral_lock.take();
-            self.set_error_status(terminal, "Failed to discover services", e)
-                .await;
-            return;
+            return Err(format!("Failed to discover services: {:?}", e));
         }
 
         drop(peripheral_lock);
+        let name = self.get_name().await;
+        let model = name.as_deref().and_then(ModelInfo::lookup);
+        let protocol = match self.protocol {
+            Some(protocol) => protocol,
+            None => name.as_deref().map_or(Protocol::Legacy, Protocol::detect),
+        };
+        *self.model.write().await = model;
+        *self.active_protocol.write().await = protocol;
+        if let Some(saved) = self.saved_state.lock().await.take() {
+            if !self.state_known.load(Ordering::Relaxed) {
+                self.restore_state(saved).await;
+            }
+        }
+        self.dirty_details.write().await.dirty();
 
-        terminal.write().await.set_light_status(
-            self.id.to_string().as_str(),
-            "Connected",
-            ratatui::style::Color::Green,
+        // Readback is best-effort; not every model exposes the notify characteristic.
+        let readback_status = if protocol.status_queries().is_none() {
+            format!("Not supported for {:?} lights", protocol)
+        } else {
+            let lock = self.peripheral.read().await;
+            match lock.as_ref().unwrap().subscribe(*notify_uuid).await {
+                Ok(()) => "Waiting".to_string(),
+                Err(_) => "Unavailable".to_string(),
+            }
+        };
+        *self.readback.write().await = Readback::new();
+        terminal.write().await.set_light_readback(
+            self.get_label().as_str(),
+            readback_status.as_str(),
+            true,
+        );
+
+        self.set_connection_state(terminal, ConnectionState::Ready)
+            .await;
+
+        let device = self.get_bound_address().await.unwrap_or_default();
+        let mut terminal_lock = terminal.write().await;
+        terminal_lock.set_light_device(self.get_label().as_str(), device.to_string().as_str());
+        terminal_lock.set_light_model(
+            self.get_label().as_str(),
+            model
+                .map_or("Unknown model".to_string(), |m| m.to_string())
+                .as_str(),
         );
+        if let Some(m) = model.filter(|m| !m.supports(self.get_personality())) {
+            terminal_lock.set_light_status(
+                self.get_label().as_str(),
+                format!(
+                    "{:?} personality not supported by {}",
+                    self.get_personality(),
+                    m.name
+                )
+                .as_str(),
+                ratatui::style::Color::Red,
+            );
+        }
+        Ok(())
     }
 
     pub async fn disconnect(&self, terminal: &RwLock<TerminalUi>) -> Result<(), btleplug::Error> {
-        terminal.write().await.set_light_status(
-            self.id.to_string().as_str(),
-            "Disconnected",
-            ratatui::style::Color::Red,
-        );
+        self.set_connection_state(terminal, ConnectionState::Idle)
+            .await;
 
         let lock = self.peripheral.read().await;
-        if lock.as_ref().is_some() {
-            lock.as_ref().unwrap().disconnect().await?;
+        if let Some(peripheral) = lock.as_ref() {
+            if let Err(e) = peripheral.disconnect().await {
+                log::warn!("Failed to disconnect {}: {:?}", self.get_label(), e);
+                self.set_error_status(terminal, "Failed to disconnect", &e)
+                    .await;
+                return Err(e);
+            }
         }
-        return Ok(());
+        Ok(())
     }
 
     pub async fn get_name(&self) -> Option<String> {
         let lock = self.peripheral.read().await;
         match lock.as_ref() {
-            Some(p) => {
-                let props = p.properties().await.unwrap().unwrap();
-                return props.local_name;
-            }
-            None => return None,
+            Some(p) => p
+                .properties()
+                .await
+                .ok()
+                .flatten()
+                .and_then(|props| props.local_name),
+            None => None,
         }
     }
 
     pub fn get_address(&self) -> u16 {
-        return self.address;
+        self.address.load(Ordering::Relaxed)
+    }
+
+    pub fn get_personality(&self) -> Personality {
+        *self.personality.read().unwrap()
+    }
+
+    pub fn get_adapter(&self) -> Option<&str> {
+        self.adapter.as_deref()
     }
 
     pub fn get_universe(&self) -> u16 {
-        return self.universe;
+        self.universe.load(Ordering::Relaxed)
     }
 
     pub async fn is_connected(&self) -> Result<bool, btleplug::Error> {
         let lock = self.peripheral.read().await;
         match lock.as_ref() {
-            Some(ref p) => return p.is_connected().await,
-            None => return Ok(false),
+            Some(p) => p.is_connected().await,
+            None => Ok(false),
         }
     }
 
-    pub async fn find_loop(&self, terminal: &RwLock<TerminalUi>) {
-        let manager = Manager::new().await.unwrap();
-        let adapters = manager.adapters().await.unwrap();
-        let central = adapters.into_iter().nth(0).unwrap();
+    /// Connects and keeps the light up to date until it is retired.
+    pub async fn find_loop(&self, connect_limit: &Semaphore, terminal: &RwLock<TerminalUi>) {
+        self.running.send_replace(true);
+        let _running = RunningGuard(&self.running);
+        let mut retired = self.retired.subscribe();
+        tokio::select! {
+            _ = futures::future::join(
+                self.send_loop(connect_limit, terminal),
+                self.readback_loop(terminal),
+            ) => {}
+            _ = retired.wait_for(|retired| *retired) => {}
+        }
+    }
 
+    async fn send_loop(&self, connect_limit: &Semaphore, terminal: &RwLock<TerminalUi>) {
         loop {
-            self.search(&central, terminal).await;
+            self.search(connect_limit, terminal).await;
+
+            if !self.dirty_details.read().await.is_dirty() {
+                let keepalive_due = self.dirty_details.read().await.keepalive_due();
+                tokio::select! {
+                    _ = self.changed.notified() => {}
+                    _ = self.lost.notified() => continue,
+                    _ = tokio::time::sleep_until(keepalive_due) => {
+                        self.dirty_details.write().await.dirty();
+                    }
+                }
+            }
+
+            // changes arriving inside the gap are folded into the next write
+            let mut next_write = self.dirty_details.read().await.last_clean_time()
+                + self.get_write_gap(*self.model.read().await);
+            if let Some(identify) = self.identify.lock().await.as_ref() {
+                // only write when the flash actually changes
+                next_write = next_write.max(identify.next_write());
+            }
+            tokio::time::sleep_until(next_write).await;
 
             match self.send_color().await {
                 Ok(sent) => {
                     if sent {
-                        terminal
-                            .write()
-                            .await
-                            .add_light_event(self.id.to_string().as_str());
+                        let state = *self.sent_state.read().await;
+                        let in_step = self.readback.read().await.matches(&state);
+                        let mut terminal_lock = terminal.write().await;
+                        terminal_lock.add_light_event(self.get_label().as_str());
+                        terminal_lock.set_light_commanded(
+                            self.get_label().as_str(),
+                            state.to_string().as_str(),
+                            in_step,
+                        );
                     }
                 }
                 Err(e) => {
@@ -205,40 +582,153 @@ impl Light {
                         .await;
                 }
             }
-            tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
         }
     }
 
-    async fn search(&self, central: &Adapter, terminal: &RwLock<TerminalUi>) {
-        if !self.is_connected().await.unwrap() {
-            terminal.write().await.set_light_status(
-                self.id.to_string().as_str(),
-                "Searching",
-                ratatui::style::Color::Yellow,
-            );
-        }
+    async fn readback_loop(&self, terminal: &RwLock<TerminalUi>) {
+        let mut connection_state = self.connection_state.subscribe();
+        loop {
+            let _ = connection_state
+                .wait_for(|state| *state == ConnectionState::Ready)
+                .await;
 
-        while !self.is_connected().await.unwrap() {
-            for p in central.peripherals().await.unwrap() {
-                let props_result = p.properties().await;
+            let queries = self.active_protocol.read().await.status_queries();
+            let lock = self.peripheral.read().await;
+            let stream = match (lock.as_ref(), &queries) {
+                (Some(p), Some(_)) if p.is_connected().await.unwrap_or(false) => {
+                    p.notifications().await.ok()
+                }
+                _ => None,
+            };
+            drop(lock);
+
+            if let (Some(mut stream), Some(queries)) = (stream, queries) {
+                let mut query_interval = tokio::time::interval(READBACK_QUERY_INTERVAL);
+                // each query round answers in several notifications, but only counts once
+                let mut answered = false;
+                loop {
+                    tokio::select! {
+                        notification = stream.next() => {
+                            let Some(notification) = notification else {
+                                break;
+                            };
+                            if notification.uuid != *notify_uuid {
+                                continue;
+                            }
 
-                if let Ok(Some(props)) = props_result {
-                    if props.address == self.id {
-                        self.connect(p, terminal).await;
+                            let mut readback = self.readback.write().await;
+                            if readback.apply(&notification.value) {
+                                let in_step = readback.matches(&*self.sent_state.read().await);
+                                terminal.write().await.set_light_readback(
+                                    self.get_label().as_str(),
+                                    readback.to_string().as_str(),
+                                    in_step,
+                                );
+                                answered = true;
+                            }
+                        }
+                        _ = query_interval.tick() => {
+                            if std::mem::take(&mut answered) {
+                                let readback = *self.readback.read().await;
+                                self.check_readback(readback.matches(&*self.sent_state.read().await))
+                                    .await;
+                            }
+                            // a failed query shows up as stale readback, sends report their own errors
+                            let _ = self.query_status(&queries).await;
+                        }
+                        _ = connection_state.wait_for(|state| *state != ConnectionState::Ready) => {
+                            break;
+                        }
                     }
-                } else {
-                    self.set_error_status(
-                        terminal,
-                        "Failed to get properties",
-                        props_result.err().unwrap(),
-                    )
+                }
+            }
+
+            // wait for the next connection rather than re-reading this one
+            let _ = connection_state
+                .wait_for(|state| *state != ConnectionState::Ready)
+                .await;
+        }
+    }
+
+    /// Treats a settled light reporting something other than what we wrote as a silently
+    /// lost write, so adaptive lights can fall back and resend.
+    async fn check_readback(&self, in_step: bool) {
+        if self.dirty_details.read().await.is_dirty() {
+            return;
+        }
+
+        let mut write_tracker = self.write_tracker.lock().await;
+        if in_step {
+            write_tracker.record_success();
+            return;
+        }
+        if write_tracker.record_failure() {
+            drop(write_tracker);
+            self.dirty_details.write().await.dirty();
+            self.changed.notify_one();
+        }
+    }
+
+    /// Drives the connection state machine until the light is ready:
+    /// Scanning -> Connecting -> Discovering -> Ready, dropping into Backoff on any failure.
+    async fn search(&self, connect_limit: &Semaphore, terminal: &RwLock<TerminalUi>) {
+        let mut discovered = self.discovered.subscribe();
+        while !self.check_connected(terminal).await {
+            let link = discovered.borrow_and_update().clone();
+            let Some(link) = link else {
+                self.set_connection_state(terminal, ConnectionState::Scanning)
                     .await;
+                let _ = discovered.changed().await;
+                continue;
+            };
+
+            let permit = connect_limit.acquire().await.unwrap();
+            let result = self.connect(link, terminal).await;
+            drop(permit);
+
+            match result {
+                Ok(()) => self.backoff.lock().await.reset(),
+                Err(reason) => {
+                    let delay = self
+                        .backoff
+                        .lock()
+                        .await
+                        .next_delay(&mut rand::thread_rng());
+                    self.set_connection_state(terminal, ConnectionState::Backoff { delay, reason })
+                        .await;
+                    tokio::time::sleep(delay).await;
                 }
             }
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
         }
     }
 
+    // an adapter error counts as disconnected, so the light reconnects rather than the task
+    // giving up
+    async fn check_connected(&self, terminal: &RwLock<TerminalUi>) -> bool {
+        match self.is_connected().await {
+            Ok(connected) => connected,
+            Err(e) => {
+                log::warn!("Failed to check {} is connected: {:?}", self.get_label(), e);
+                self.set_error_status(terminal, "Failed to check connection", e)
+                    .await;
+                false
+            }
+        }
+    }
+
+    async fn set_connection_state(&self, terminal: &RwLock<TerminalUi>, state: ConnectionState) {
+        terminal.write().await.set_light_status(
+            self.get_label().as_str(),
+            state.to_string().as_str(),
+            state.color(),
+        );
+        self.connection_state.send_replace(state);
+    }
+
+    pub fn get_connection_state(&self) -> ConnectionState {
+        self.connection_state.borrow().clone()
+    }
+
     async fn set_error_status(
         &self,
         terminal: &RwLock<TerminalUi>,
@@ -247,13 +737,26 @@ impl Light {
     ) {
         let err = format!("{}: {:?}", status, error);
         terminal.write().await.set_light_status(
-            self.id.to_string().as_str(),
+            self.get_label().as_str(),
             err.as_str(),
             ratatui::style::Color::Red,
         );
     }
 
-    pub async fn get_id(&self) -> BDAddr {
-        return self.id;
+    pub fn get_label(&self) -> String {
+        self.label.read().unwrap().clone()
+    }
+
+    pub fn get_matcher(&self) -> &LightMatch {
+        &self.matcher
+    }
+
+    /// The address of the peripheral this light is using, or the configured one if it
+    /// hasn't found it yet.
+    pub async fn get_bound_address(&self) -> Option<BDAddr> {
+        match self.peripheral.read().await.as_ref() {
+            Some(peripheral) => Some(peripheral.address()),
+            None => self.matcher.get_address(),
+        }
     }
 }
@@ -1,44 +1,449 @@
+use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
-use btleplug::{
-    api::{Central, Peripheral as _},
-    platform::Adapter,
-};
+use btleplug::api::BDAddr;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
 use ratatui::style::Color;
-use tokio::{sync::RwLock, time};
+use tokio::{
+    sync::{watch, RwLock, Semaphore},
+    time,
+};
 
 use crate::{
-    config::Config, light::Light, sacn_client::SacnClient, sacn_packet::SacnDmxPacket,
+    config::{
+        BluetoothConfig, Config, LightConfig, LightMatch, NameSlots, SacnConfig, StateConfig,
+        UiConfig,
+    },
+    control::ControlCommand,
+    light::Light,
+    sacn_client::SacnClient,
+    sacn_packet::SacnDmxPacket,
+    state_file::StateStore,
     terminal_ui::TerminalUi,
+    transport::{BleTransport, LightLink, TransportEvent},
 };
 
+/// A light with the config it was built from and the adapter it was given.
+#[derive(Clone)]
+struct PatchedLight {
+    light: Arc<Light>,
+    config: LightConfig,
+    /// Index into `transports`, or None if its configured adapter is missing.
+    assignment: Option<usize>,
+}
+
+/// How long an address can go unheard before name and index rules stop counting it.
+/// Connected lights don't advertise, so they are kept regardless.
+const ADVERTISEMENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Every address heard advertising each name, slotted by `NameSlots` for name and index rules.
+#[derive(Default)]
+struct SeenNames {
+    slots: NameSlots<SeenLink>,
+}
+
+struct SeenLink {
+    link: Arc<dyn LightLink>,
+    heard: time::Instant,
+}
+
+impl SeenNames {
+    fn heard(&mut self, name: &str, link: Arc<dyn LightLink>, now: time::Instant) {
+        self.slots
+            .heard(name, link.address(), SeenLink { link, heard: now });
+    }
+
+    fn pick(&self, name: &str, index: usize) -> Option<Arc<dyn LightLink>> {
+        Some(self.slots.pick(name, index)?.link.clone())
+    }
+
+    fn forget(&mut self, address: BDAddr) {
+        self.slots.forget_where(|heard, _| heard == address);
+    }
+
+    /// Forgets addresses not heard for `ADVERTISEMENT_TIMEOUT`, other than those in `connected`.
+    fn forget_stale(&mut self, now: time::Instant, connected: &[BDAddr]) {
+        self.slots.forget_where(|address, seen| {
+            now.saturating_duration_since(seen.heard) > ADVERTISEMENT_TIMEOUT
+                && !connected.contains(&address)
+        });
+    }
+}
+
+/// What a reload changed, for the app status.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReloadSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub repatched: usize,
+    /// The sacn, bluetooth, ui or state sections changed, which only take effect on restart.
+    pub restart_needed: bool,
+}
+
+impl fmt::Display for ReloadSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Reloaded: {} added, {} removed, {} repatched",
+            self.added, self.removed, self.repatched
+        )?;
+        if self.restart_needed {
+            write!(
+                f,
+                " (restart to apply sacn, bluetooth, ui and state changes)"
+            )?;
+        }
+        Ok(())
+    }
+}
+
 pub struct LightController {
     sacn_client: Option<SacnClient>,
-    lights: Vec<Light>,
+    transports: Vec<Arc<dyn BleTransport>>,
+    lights: std::sync::RwLock<Vec<PatchedLight>>,
+    /// Bumped whenever `lights` changes, so the running loops pick up new lights.
+    generation: watch::Sender<u64>,
+    /// Caps simultaneous connection attempts; BlueZ rejects overlapping ones.
+    connect_limit: Semaphore,
+    sacn_timeout: Duration,
+    /// The config last applied, to pick another profile from.
+    config: std::sync::RwLock<Config>,
+    // the settings sections as started, to tell when a reload needs a restart
+    sacn: SacnConfig,
+    bluetooth: BluetoothConfig,
+    ui: UiConfig,
+    state: StateConfig,
+    /// Saved states for lights that reloads add, once restoring is turned on.
+    state_store: std::sync::RwLock<Option<Arc<StateStore>>>,
 }
 
 impl LightController {
-    pub async fn new(config: &Config) -> Self {
-        let mut lights = vec![];
+    pub async fn new(config: &Config, transports: Vec<Arc<dyn BleTransport>>) -> Self {
+        let sacn_client = SacnClient::new(config.get_universes(), &config.sacn)
+            .await
+            .unwrap();
+        Self::with_sacn_client(config, transports, Some(sacn_client))
+    }
+
+    pub fn with_sacn_client(
+        config: &Config,
+        transports: Vec<Arc<dyn BleTransport>>,
+        sacn_client: Option<SacnClient>,
+    ) -> Self {
+        let mut lights: Vec<PatchedLight> = config
+            .lights
+            .iter()
+            .map(|light_config| PatchedLight {
+                light: Arc::new(Light::new(light_config)),
+                config: light_config.clone(),
+                assignment: None,
+            })
+            .collect();
+        Self::assign_adapters(&mut lights, &[], &transports);
+
+        Self {
+            sacn_client,
+            transports,
+            lights: std::sync::RwLock::new(lights),
+            generation: watch::channel(0).0,
+            connect_limit: Semaphore::new(config.bluetooth.max_concurrent_connects.max(1)),
+            sacn_timeout: config.sacn.get_timeout(),
+            config: std::sync::RwLock::new(config.clone()),
+            sacn: config.sacn.clone(),
+            bluetooth: config.bluetooth.clone(),
+            ui: config.ui.clone(),
+            state: config.state.clone(),
+            state_store: std::sync::RwLock::new(None),
+        }
+    }
+
+    /// Pins lights to their configured adapter, then spreads the rest onto whichever adapter
+    /// has the fewest lights so far. The first `kept` lights stay where they already are.
+    fn assign_adapters(
+        lights: &mut [PatchedLight],
+        kept: &[bool],
+        transports: &[Arc<dyn BleTransport>],
+    ) {
+        let is_kept = |i: usize| kept.get(i).copied().unwrap_or(false);
+        let mut loads = vec![0; transports.len()];
+        for (i, patched) in lights.iter_mut().enumerate() {
+            if !is_kept(i) {
+                patched.assignment = patched
+                    .config
+                    .adapter
+                    .as_deref()
+                    .and_then(|selector| transports.iter().position(|t| t.matches(selector)));
+            }
+            if let Some(index) = patched.assignment {
+                loads[index] += 1;
+            }
+        }
+
+        for (i, patched) in lights.iter_mut().enumerate() {
+            if !is_kept(i) && patched.config.adapter.is_none() {
+                patched.assignment = (0..transports.len()).min_by_key(|&i| loads[i]);
+                if let Some(index) = patched.assignment {
+                    loads[index] += 1;
+                }
+            }
+        }
+    }
+
+    /// Has each light send its state from `store` when it first connects, including lights
+    /// that later reloads and profile switches add. Returns how many of the current lights
+    /// had one.
+    pub async fn restore_states(&self, store: Arc<StateStore>) -> usize {
+        *self.state_store.write().unwrap() = Some(store.clone());
+        store.restore(&self.get_lights()).await
+    }
+
+    pub fn get_lights(&self) -> Vec<Arc<Light>> {
+        self.lights
+            .read()
+            .unwrap()
+            .iter()
+            .map(|patched| patched.light.clone())
+            .collect()
+    }
+
+    pub fn get_adapter_name(&self, light_index: usize) -> Option<&str> {
+        let assignment = self.lights.read().unwrap().get(light_index)?.assignment;
+        assignment.map(|index| self.transports[index].name())
+    }
+
+    /// The profile in use, if the config has any.
+    pub fn get_profile(&self) -> Option<String> {
+        self.config.read().unwrap().profile.clone()
+    }
+
+    /// The lights assigned to one adapter.
+    fn lights_on(&self, transport_index: usize) -> Vec<Arc<Light>> {
+        self.lights
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|patched| patched.assignment == Some(transport_index))
+            .map(|patched| patched.light.clone())
+            .collect()
+    }
+
+    /// Finds a light by its label or address, as given on the command line or in the TUI.
+    pub async fn find_light(&self, target: &str) -> Option<Arc<Light>> {
+        let lights = self.get_lights();
+        if let Some(light) = lights
+            .iter()
+            .find(|light| light.get_label().eq_ignore_ascii_case(target))
+        {
+            return Some(light.clone());
+        }
+
+        let address: BDAddr = target.parse().ok()?;
+        for light in lights {
+            if light.get_bound_address().await == Some(address) {
+                return Some(light);
+            }
+        }
+        None
+    }
+
+    /// Finds the lights a command is aimed at: every member of a group named `target`, or
+    /// the one light `find_light` picks out.
+    pub async fn find_lights(&self, target: &str) -> Vec<Arc<Light>> {
+        let members: Vec<Arc<Light>> = self
+            .lights
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|patched| {
+                patched
+                    .config
+                    .group
+                    .as_ref()
+                    .is_some_and(|group| group.eq_ignore_ascii_case(target))
+            })
+            .map(|patched| patched.light.clone())
+            .collect();
+        if !members.is_empty() {
+            return members;
+        }
+        self.find_light(target).await.into_iter().collect()
+    }
+
+    /// Swaps in the lights from a reloaded config. Lights whose match rule and connection
+    /// settings are unchanged keep their connection and only move to their new patch; the
+    /// rest are disconnected or started from scratch.
+    pub async fn reload(
+        &self,
+        config: &Config,
+        terminal: &RwLock<TerminalUi>,
+    ) -> Result<ReloadSummary, String> {
+        let mut remaining: Vec<Option<PatchedLight>> = self
+            .lights
+            .read()
+            .unwrap()
+            .iter()
+            .cloned()
+            .map(Some)
+            .collect();
+
+        let mut kept = vec![];
+        let mut added = vec![];
         for light_config in config.lights.iter() {
-            let light = Light::new(light_config.id, light_config.universe, light_config.address);
-            lights.push(light);
+            let previous = remaining
+                .iter_mut()
+                .find(|slot| {
+                    slot.as_ref()
+                        .is_some_and(|patched| patched.config.is_repatch_of(light_config))
+                })
+                .and_then(Option::take);
+            match previous {
+                Some(patched) => kept.push((patched, light_config)),
+                None => added.push(light_config),
+            }
         }
-        let sacn_client = SacnClient::new(config.get_universes()).await.unwrap();
 
-        Self {
-            sacn_client: Some(sacn_client),
-            lights,
+        let mut summary = ReloadSummary {
+            added: added.len(),
+            restart_needed: config.sacn != self.sacn
+                || config.bluetooth != self.bluetooth
+                || config.ui != self.ui
+                || config.state != self.state,
+            ..Default::default()
+        };
+
+        // removed lights go first, so their labels are free for whatever replaces them
+        for patched in remaining.into_iter().flatten() {
+            summary.removed += 1;
+            // stopped first, or its send loop could reconnect it after the disconnect
+            patched.light.retire().await;
+            let _ = patched.light.disconnect(terminal).await;
+            terminal
+                .write()
+                .await
+                .remove_light(&patched.light.get_label());
         }
+
+        let mut lights = vec![];
+        let mut renames = vec![];
+        for (patched, light_config) in kept {
+            let old_label = patched.light.get_label();
+            if patched.light.repatch(light_config).await {
+                summary.repatched += 1;
+            }
+            if patched.light.get_label() != old_label {
+                renames.push((old_label, patched.light.get_label()));
+            }
+            lights.push(PatchedLight {
+                config: light_config.clone(),
+                ..patched
+            });
+        }
+        terminal.write().await.rename_lights(&renames);
+        let kept = vec![true; lights.len()];
+        for light_config in added {
+            lights.push(PatchedLight {
+                light: Arc::new(Light::new(light_config)),
+                config: light_config.clone(),
+                assignment: None,
+            });
+        }
+        Self::assign_adapters(&mut lights, &kept, &self.transports);
+        // before the new lights start, so none can connect ahead of its saved state
+        let state_store = self.state_store.read().unwrap().clone();
+        if let Some(state_store) = state_store {
+            let new_lights: Vec<Arc<Light>> = lights[kept.len()..]
+                .iter()
+                .map(|patched| patched.light.clone())
+                .collect();
+            state_store.restore(&new_lights).await;
+        }
+
+        *self.lights.write().unwrap() = lights;
+        *self.config.write().unwrap() = config.clone();
+        self.generation.send_modify(|generation| *generation += 1);
+        terminal
+            .write()
+            .await
+            .set_profiles(config.get_profile_names(), config.profile.as_deref());
+
+        if let Some(sacn_client) = self.sacn_client.as_ref() {
+            sacn_client
+                .set_universes(config.get_universes())
+                .map_err(|e| format!("{}, but failed to update sACN universes: {}", summary, e))?;
+        }
+        Ok(summary)
     }
 
-    async fn handle_packet(&self, packet: &SacnDmxPacket) -> Result<(), btleplug::Error> {
-        for light in self.lights.iter() {
+    /// Carries out a command from the TUI or control API, returning a message for whoever
+    /// asked.
+    pub async fn execute(
+        &self,
+        command: &ControlCommand,
+        terminal: &RwLock<TerminalUi>,
+    ) -> Result<String, String> {
+        match command {
+            ControlCommand::Identify { target, duration } => {
+                let lights = self.find_lights(target).await;
+                let name = match lights.as_slice() {
+                    [] => return Err(format!("No light matching {}", target)),
+                    [light] => light.get_label(),
+                    _ => format!("group {} ({} lights)", target, lights.len()),
+                };
+                for light in lights.iter() {
+                    light.identify(*duration).await;
+                }
+                let message = format!("Identifying {} for {:.1}s", name, duration.as_secs_f32());
+                terminal
+                    .write()
+                    .await
+                    .set_app_status(message.as_str(), Color::Green);
+                Ok(message)
+            }
+            ControlCommand::Profile { name: None } => {
+                let config = self.config.read().unwrap().clone();
+                match config.profile.as_deref() {
+                    Some(profile) => Ok(format!(
+                        "Running profile {} of {}",
+                        profile,
+                        config.get_profile_names().join(", ")
+                    )),
+                    None => Err("The config has no profiles".to_string()),
+                }
+            }
+            ControlCommand::Profile { name: Some(name) } => {
+                let config = self
+                    .config
+                    .read()
+                    .unwrap()
+                    .with_profile(name)
+                    .map_err(|e| e.to_string())?;
+                let summary = self.reload(&config, terminal).await?;
+                let message = format!("Switched to profile {}. {}", name, summary);
+                terminal
+                    .write()
+                    .await
+                    .set_app_status(message.as_str(), Color::Green);
+                Ok(message)
+            }
+        }
+    }
+
+    pub(crate) async fn handle_packet(
+        &self,
+        packet: &SacnDmxPacket,
+    ) -> Result<(), btleplug::Error> {
+        for light in self.get_lights() {
             if light.get_universe() == packet.universe {
-                let red = packet.dmx_data[light.get_address() as usize];
-                let green = packet.dmx_data[light.get_address() as usize + 1];
-                let blue = packet.dmx_data[light.get_address() as usize + 2];
-                light.set_color_rgb(red, green, blue).await;
+                let start = light.get_address() as usize;
+                let end = start + light.get_personality().footprint() as usize;
+                // packets may carry fewer than 512 slots
+                let Some(channels) = packet.dmx_data.get(start..end) else {
+                    continue;
+                };
+                let state = light.get_personality().decode(channels);
+                light.set_state(state).await;
             }
         }
         Ok(())
@@ -46,7 +451,7 @@ impl LightController {
 
     pub async fn listen(&self, terminal: &RwLock<TerminalUi>) {
         loop {
-            let _result = tokio::select! {
+            tokio::select! {
                 packet = self.sacn_client.as_ref().unwrap().receive() => {
                     let mut lock = terminal.write().await;
                     lock.set_sacn_status("Received Sacn Packet", Color::Green);
@@ -54,55 +459,254 @@ impl LightController {
                     drop(lock);
 
                     if let Err(e) = self.handle_packet(&packet.unwrap()).await {
-                        eprintln!("Error handling packet: {:?}", e);
+                        log::error!("Error handling packet: {:?}", e);
                     }
                 }
-                _timeout = time::sleep(Duration::from_secs(1)) => {
+                _timeout = time::sleep(self.sacn_timeout) => {
                     let mut lock = terminal.write().await;
                     lock.set_sacn_status("Timeout", Color::Red);
                     drop(lock);
                 }
-            };
+            }
         }
     }
 
     pub async fn find_light_loop(&self, terminal: &RwLock<TerminalUi>) {
-        // start scanning for devices
-        let futures: Vec<_> = self
-            .lights
-            .iter()
-            .map(|light| light.find_loop(terminal))
+        let scan_futures: Vec<_> = (0..self.transports.len())
+            .map(|i| self.scan_loop(i, terminal))
             .collect();
-        futures::future::join_all(futures).await;
+        futures::future::join3(
+            futures::future::join_all(scan_futures),
+            self.light_loops(terminal),
+            self.status_loop(terminal),
+        )
+        .await;
     }
 
-    pub async fn disconnect(&self, terminal: &RwLock<TerminalUi>) {
-        for light in self.lights.iter() {
-            light.disconnect(terminal).await.unwrap();
+    /// Runs each light's `find_loop`, starting lights as reloads add them. Removed lights are
+    /// retired, which ends their loop.
+    async fn light_loops(&self, terminal: &RwLock<TerminalUi>) {
+        let mut generation = self.generation.subscribe();
+        let mut started: Vec<Arc<Light>> = vec![];
+        let mut running = FuturesUnordered::new();
+
+        loop {
+            generation.borrow_and_update();
+            let lights = self.lights.read().unwrap().clone();
+            started.retain(|light| {
+                lights
+                    .iter()
+                    .any(|patched| Arc::ptr_eq(&patched.light, light))
+            });
+
+            for patched in lights {
+                // reloads can move kept lights between groups too
+                terminal
+                    .write()
+                    .await
+                    .set_light_group(&patched.light.get_label(), patched.config.group.as_deref());
+                if started
+                    .iter()
+                    .any(|light| Arc::ptr_eq(light, &patched.light))
+                {
+                    continue;
+                }
+                started.push(patched.light.clone());
+
+                let light = patched.light;
+                let id = light.get_label();
+                match patched.assignment {
+                    Some(index) => {
+                        terminal
+                            .write()
+                            .await
+                            .set_light_adapter(id.as_str(), self.transports[index].name());
+                        running.push(async move {
+                            light.find_loop(&self.connect_limit, terminal).await;
+                        });
+                    }
+                    None => {
+                        // unpinned lights only go unassigned when there are no adapters at all
+                        let status = match light.get_adapter() {
+                            Some(adapter) => format!("Adapter {} not found", adapter),
+                            None => "No Bluetooth adapter".to_string(),
+                        };
+                        terminal.write().await.set_light_status(
+                            id.as_str(),
+                            status.as_str(),
+                            Color::Red,
+                        );
+                    }
+                }
+            }
+
+            tokio::select! {
+                Some(()) = running.next(), if !running.is_empty() => {}
+                _ = generation.changed() => {}
+            }
         }
+    }
 
-        self.sacn_client
-            .as_ref()
-            .unwrap()
-            .disconnect(terminal)
-            .await
-            .unwrap();
+    /// Keeps the TUI's per-adapter loads and per-light counters and write modes current.
+    async fn status_loop(&self, terminal: &RwLock<TerminalUi>) {
+        loop {
+            let lights = self.lights.read().unwrap().clone();
+            let mut counters = vec![];
+            for PatchedLight { light, .. } in lights.iter() {
+                counters.push((
+                    light.get_label(),
+                    light.get_counters().await,
+                    light.get_write_mode().await,
+                ));
+            }
+
+            let mut loads = vec![(0, 0); self.transports.len()];
+            for PatchedLight {
+                light, assignment, ..
+            } in lights.iter()
+            {
+                if let Some(index) = *assignment {
+                    loads[index].1 += 1;
+                    if light.is_connected().await.unwrap_or(false) {
+                        loads[index].0 += 1;
+                    }
+                }
+            }
+
+            let mut lock = terminal.write().await;
+            for (transport, (connected, assigned)) in self.transports.iter().zip(loads) {
+                lock.set_adapter_load(transport.name(), connected, assigned);
+            }
+            for (id, light_counters, write_mode) in counters {
+                lock.set_light_counters(id.as_str(), light_counters);
+                lock.set_light_write_mode(id.as_str(), write_mode.as_str());
+            }
+            drop(lock);
+
+            time::sleep(Duration::from_secs(1)).await;
+        }
     }
 
-    pub async fn scan(adapter: Adapter) -> Result<(), btleplug::Error> {
-        for p in adapter.peripherals().await? {
-            let props = p.properties().await?;
-            if let Some(properties) = props {
-                if properties.local_name.is_some() {
-                    println!(
-                        "{:?} -> {:?}",
-                        properties.local_name.unwrap(),
-                        properties.address
+    /// Watches one adapter for advertisements and hands each one to the light assigned to that
+    /// adapter whose match rule selects it.
+    async fn scan_loop(&self, transport_index: usize, terminal: &RwLock<TerminalUi>) {
+        let transport = &self.transports[transport_index];
+        let mut generation = self.generation.subscribe();
+        let mut seen_names = SeenNames::default();
+
+        loop {
+            let mut events = match transport.events().await {
+                Ok(events) => events,
+                Err(e) => {
+                    terminal.write().await.set_app_status(
+                        format!(
+                            "Failed to watch for lights on {}: {:?}",
+                            transport.name(),
+                            e
+                        )
+                        .as_str(),
+                        Color::Red,
                     );
+                    time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+
+            // anything seen before we subscribed won't be announced again
+            generation.borrow_and_update();
+            self.offer_known(transport_index, &mut seen_names).await;
+
+            loop {
+                let event = tokio::select! {
+                    event = events.next() => event,
+                    // lights added by a reload may already be advertising
+                    _ = generation.changed() => {
+                        self.offer_known(transport_index, &mut seen_names).await;
+                        continue;
+                    }
+                };
+                let Some(event) = event else {
+                    break;
+                };
+                match event {
+                    TransportEvent::Discovered(link) => {
+                        let lights = self.lights_on(transport_index);
+                        Self::offer(&lights, link, &mut seen_names).await;
+                    }
+                    TransportEvent::Disconnected(address) => {
+                        seen_names.forget(address);
+                        for light in self.lights_on(transport_index) {
+                            if light.get_bound_address().await == Some(address) {
+                                light.link_lost();
+                            }
+                        }
+                    }
                 }
             }
+            time::sleep(Duration::from_secs(1)).await;
         }
+    }
 
-        Ok(())
+    /// Offers everything the adapter already knows about to the lights assigned to it.
+    async fn offer_known(&self, transport_index: usize, seen_names: &mut SeenNames) {
+        if let Ok(peripherals) = self.transports[transport_index].peripherals().await {
+            let lights = self.lights_on(transport_index);
+            for link in peripherals {
+                Self::offer(&lights, link, seen_names).await;
+            }
+        }
+    }
+
+    /// Hands `link` to whichever lights it belongs to. A new address can fill a slot that
+    /// name and index rules count in, so those lights are offered their pick again.
+    async fn offer(lights: &[Arc<Light>], link: Arc<dyn LightLink>, seen_names: &mut SeenNames) {
+        let address = link.address();
+        let local_name = link
+            .properties()
+            .await
+            .ok()
+            .flatten()
+            .and_then(|properties| properties.local_name);
+        if let Some(name) = local_name.as_ref() {
+            let mut connected = vec![];
+            for light in lights {
+                if light.is_connected().await.unwrap_or(false) {
+                    connected.extend(light.get_bound_address().await);
+                }
+            }
+            let now = time::Instant::now();
+            seen_names.forget_stale(now, &connected);
+            seen_names.heard(name, link.clone(), now);
+        }
+
+        for light in lights {
+            if !light
+                .get_matcher()
+                .may_select(address, local_name.as_deref())
+            {
+                continue;
+            }
+            match light.get_matcher() {
+                LightMatch::Address(_) => light.offer(link.clone()),
+                LightMatch::Name { name, index } => {
+                    if let Some(pick) = seen_names.pick(name, *index) {
+                        light.offer(pick);
+                    }
+                }
+            }
+        }
+    }
+
+    pub async fn disconnect(&self, terminal: &RwLock<TerminalUi>) {
+        for light in self.get_lights() {
+            // the light reports its own failure, and the rest still need disconnecting
+            let _ = light.disconnect(terminal).await;
+        }
+
+        if let Some(sacn_client) = self.sacn_client.as_ref() {
+            if let Err(e) = sacn_client.disconnect(terminal).await {
+                log::warn!("Failed to disconnect from sACN: {:?}", e);
+            }
+        }
     }
 }
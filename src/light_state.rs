@@ -0,0 +1,78 @@
+use std::fmt;
+
+use btleplug::api::BDAddr;
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+use crate::model::ModelInfo;
+use crate::protocol::Protocol;
+
+// Used when the advertised name doesn't match anything in the model table.
+pub const DEFAULT_CCT_RANGE: (u16, u16) = (3200, 5600);
+const DEFAULT_BRIGHTNESS_STEPS: u8 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum LightState {
+    Rgb(Color),
+    Cct { dimmer: u8, temperature: u8 },
+}
+
+impl LightState {
+    /// Whether the light is giving out any light at all.
+    pub fn is_on(&self) -> bool {
+        match self {
+            LightState::Rgb(color) => *color != Color::new(0, 0, 0),
+            LightState::Cct { dimmer, .. } => *dimmer > 0,
+        }
+    }
+
+    pub fn to_command(
+        &self,
+        protocol: Protocol,
+        mac: BDAddr,
+        model: Option<&ModelInfo>,
+    ) -> Vec<u8> {
+        let steps = model.map_or(DEFAULT_BRIGHTNESS_STEPS, |m| m.brightness_steps);
+
+        match self {
+            LightState::Rgb(color) => {
+                let (hue, saturation, brightness) = color.to_hsv();
+                protocol.hsi_command(mac, hue, saturation, Self::scale(brightness, 100, steps))
+            }
+            LightState::Cct {
+                dimmer,
+                temperature,
+            } => {
+                let (warmest, coolest) = model.map_or(DEFAULT_CCT_RANGE, |m| m.cct_range);
+                let kelvin =
+                    warmest as u32 + (coolest - warmest) as u32 * *temperature as u32 / 255;
+                protocol.cct_command(mac, Self::scale(*dimmer, 255, steps), (kelvin / 100) as u8)
+            }
+        }
+    }
+
+    fn scale(value: u8, from_max: u8, to_max: u8) -> u8 {
+        ((value as u32 * to_max as u32 + from_max as u32 / 2) / from_max as u32) as u8
+    }
+}
+
+impl fmt::Display for LightState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LightState::Rgb(color) => {
+                let (hue, saturation, brightness) = color.to_hsv();
+                write!(f, "HSI {} {}% {}%", hue, saturation, brightness)
+            }
+            LightState::Cct {
+                dimmer,
+                temperature,
+            } => write!(
+                f,
+                "CCT {}% temp {}",
+                Self::scale(*dimmer, 255, 100),
+                temperature
+            ),
+        }
+    }
+}
@@ -1,71 +1,298 @@
+pub mod cli;
 pub mod color;
 pub mod config;
+pub mod config_format;
+pub mod config_layers;
+pub mod config_watch;
+pub mod connection_state;
+pub mod control;
 pub mod dirty_details;
 pub mod event_counter;
+pub mod export;
+pub mod frame_counters;
+pub mod identify;
+pub mod interpolation;
 pub mod light;
 pub mod light_controller;
+pub mod light_state;
+pub mod model;
+pub mod patch;
+pub mod personality;
+pub mod protocol;
+pub mod readback;
 pub mod sacn_client;
 pub mod sacn_packet;
+pub mod scan;
+pub mod state_file;
 pub mod terminal_status;
 pub mod terminal_ui;
 pub mod tests;
+pub mod transport;
+pub mod validate;
+pub mod write_policy;
 
-use std::env;
 use std::error::Error;
 use std::sync::Arc;
 
-use btleplug::api::{Central, Manager as _, ScanFilter};
+use btleplug::api::Manager as _;
 use btleplug::platform::Manager;
-use config::Config;
+use clap::Parser;
+use cli::{Cli, Command, ConfigAction};
+use config::Overrides;
+use control::{ControlCommand, DEFAULT_CONTROL_ADDRESS};
+use identify::DEFAULT_IDENTIFY_DURATION;
 use light_controller::LightController;
+use state_file::StateStore;
 use terminal_ui::TerminalUi;
 use tokio::sync::RwLock;
+use transport::btleplug_transport::BtleplugTransport;
+use transport::BleTransport;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let cli = Cli::parse();
+    let config_path = cli.config.as_str();
+    let profile = cli.profile.as_deref();
+    let config_paths = config_layers::find_layer_paths(&config_layers::config_dirs(), config_path);
+    let mut overrides = cli.get_overrides();
+    overrides.settings = config_layers::env_settings(|name| std::env::var(name).ok())?;
+    // the files may be missing or broken; `run` reports that properly below
+    let headless_in_config = config_layers::load(&config_paths, profile, &overrides)
+        .await
+        .is_ok_and(|config| config.ui.headless);
+    if !cli.shows_tui(headless_in_config) {
+        env_logger::Builder::new()
+            .filter_level(cli.log_level)
+            .init();
+    }
+
+    match cli.get_command() {
+        Command::Identify { target, duration } => {
+            let command = ControlCommand::Identify {
+                target: target.clone(),
+                duration: duration.unwrap_or(DEFAULT_IDENTIFY_DURATION),
+            };
+            return send_command(&config_paths, &overrides, &command).await;
+        }
+        Command::Profile { name } => {
+            let command = ControlCommand::Profile { name: name.clone() };
+            return send_command(&config_paths, &overrides, &command).await;
+        }
+        Command::Send { command } => {
+            let command = ControlCommand::from_words(command)?;
+            return send_command(&config_paths, &overrides, &command).await;
+        }
+        Command::Validate { path } => {
+            let checked = match path {
+                Some(path) => validate::check_file(path).await.map(|()| path.clone()),
+                None => config_layers::check_layers(&config_paths)
+                    .await
+                    .map(|()| config_layers::describe_paths(&config_paths)),
+            };
+            match checked {
+                Ok(name) => println!("{} is valid", name),
+                Err(report) => {
+                    eprintln!("{}", report);
+                    std::process::exit(1);
+                }
+            }
+            return Ok(());
+        }
+        Command::Migrate { path } => {
+            let path = path.as_deref().unwrap_or(config_path);
+            match config::migrate_file(path).await? {
+                Some(backup_path) => println!(
+                    "Migrated {} to version {}, original kept as {}",
+                    path,
+                    config::CONFIG_VERSION,
+                    backup_path
+                ),
+                None => println!("{} is already version {}", path, config::CONFIG_VERSION),
+            }
+            return Ok(());
+        }
+        Command::Convert { path, output } => {
+            let path = path.as_deref().unwrap_or(config_path);
+            let output = match output {
+                Some(output) => output.clone(),
+                None => std::path::Path::new(path)
+                    .with_extension("toml")
+                    .to_string_lossy()
+                    .to_string(),
+            };
+            config_format::convert_file(path, &output).await?;
+            println!("Wrote {}", output);
+            return Ok(());
+        }
+        Command::Config {
+            action: ConfigAction::Show { effective },
+        } => {
+            if *effective {
+                let shown =
+                    config_layers::show_effective(&config_paths, profile, &overrides).await?;
+                print!("{}", shown);
+            } else {
+                let dirs = config_layers::config_dirs();
+                let shown = config_layers::describe_lookup(&dirs, config_path, &overrides.settings);
+                print!("{}", shown);
+            }
+            return Ok(());
+        }
+        Command::Export { kind } if !kind.needs_bluetooth() => {
+            return export::run(kind, &config_paths, profile, &overrides, &[]).await;
+        }
+        _ => {}
+    }
+
+    let manager = Manager::new()
+        .await
+        .map_err(|e| format!("Can't reach Bluetooth: {}", e))?;
+    let adapters = manager
+        .adapters()
+        .await
+        .map_err(|e| format!("Can't list Bluetooth adapters: {}", e))?;
+    let mut transports: Vec<Arc<dyn BleTransport>> = vec![];
+    for adapter in adapters {
+        transports.push(Arc::new(BtleplugTransport::new(adapter).await));
+    }
+    if let Some(selector) = cli.adapter.as_deref() {
+        transports.retain(|transport| transport.matches(selector));
+        if transports.is_empty() {
+            return Err(format!("No Bluetooth adapter matching {}", selector).into());
+        }
+    }
+
+    match cli.get_command() {
+        Command::Scan(options) => {
+            // the scan is still useful without a config, it just can't say what's patched
+            let config = config_layers::load(&config_paths, profile, &overrides)
+                .await
+                .ok();
+            let results = scan::scan(&transports, options, config.as_ref()).await?;
+            if options.json {
+                println!("{}", serde_json::to_string_pretty(&results)?);
+            } else {
+                scan::print_table(&results);
+            }
+        }
+        Command::Patch => {
+            patch::run(&transports, &config_paths, config_path, profile, &overrides).await?;
+        }
+        Command::Export { kind } => {
+            export::run(kind, &config_paths, profile, &overrides, &transports).await?;
+        }
+        _ => {
+            // refuse to start on a bad config, before the TUI takes over the terminal
+            if let Err(report) = config_layers::check_layers(&config_paths).await {
+                eprintln!("{}", report);
+                std::process::exit(1);
+            }
+            let config = config_layers::load(&config_paths, profile, &overrides).await?;
+            // also before the TUI, so a powered-off adapter is reported on a usable terminal
+            for transport in transports.iter() {
+                transport
+                    .start_scan()
+                    .await
+                    .map_err(|e| format!("Can't scan for lights on {}: {}", transport.name(), e))?;
+            }
 
-    let manager = Manager::new().await.unwrap();
-    let adapters = manager.adapters().await?;
-    let central = adapters.into_iter().nth(0).unwrap();
+            let (command_sender, command_receiver) = tokio::sync::mpsc::unbounded_channel();
+            let mut termui = if config.ui.headless {
+                TerminalUi::headless()
+            } else {
+                TerminalUi::new()
+            };
+            termui.set_command_sender(command_sender);
+            termui.set_profiles(config.get_profile_names(), config.profile.as_deref());
+            let terminal_mutex = RwLock::new(termui);
 
-    if args.len() == 2 && args[1] == "scan" {
-        LightController::scan(central).await.unwrap();
-    } else {
-        let termui = TerminalUi::new();
-        let terminal_mutex = RwLock::new(termui);
+            terminal_mutex
+                .write()
+                .await
+                .set_app_status("Starting", ratatui::style::Color::Reset);
 
-        terminal_mutex
-            .write()
-            .await
-            .set_app_status("Starting", ratatui::style::Color::Reset);
+            let controller = LightController::new(&config, transports).await;
 
-        central.start_scan(ScanFilter::default()).await.unwrap();
+            let state_store = Arc::new(match StateStore::open(&config.state.path).await {
+                Ok(state_store) => state_store,
+                Err(e) => {
+                    // start afresh; the next save replaces the unreadable file
+                    log::warn!("{}", e);
+                    StateStore::new(&config.state.path)
+                }
+            });
+            if config.state.restore_on_connect {
+                let restored = controller.restore_states(state_store.clone()).await;
+                log::info!(
+                    "Restoring {} light state(s) from {} as the lights connect",
+                    restored,
+                    state_store.get_path()
+                );
+            }
 
-        let config = Config::from_file("data/config.json").await.unwrap();
-        let controller = LightController::new(&config).await;
+            let controller_arc = Arc::new(tokio::sync::RwLock::new(controller));
+            let controller_read_lock = controller_arc.read().await;
 
-        let controller_arc = Arc::new(tokio::sync::RwLock::new(controller));
-        let controller_read_lock = controller_arc.read().await;
+            if config.is_legacy() {
+                terminal_mutex.write().await.set_app_status(
+                    "Running with a legacy config, run `migrate` to upgrade it",
+                    ratatui::style::Color::Yellow,
+                );
+            } else {
+                terminal_mutex
+                    .write()
+                    .await
+                    .set_app_status("Running", ratatui::style::Color::Green);
+            }
 
-        terminal_mutex
-            .write()
-            .await
-            .set_app_status("Running", ratatui::style::Color::Green);
+            tokio::select! {
+                _ = controller_read_lock.listen(&terminal_mutex) => {},
+                _ = controller_read_lock.find_light_loop(&terminal_mutex) => {},
+                _ = async {
+                    if config.ui.headless {
+                        let _ = tokio::signal::ctrl_c().await;
+                    } else {
+                        TerminalUi::ui_loop(&terminal_mutex).await;
+                    }
+                } => {},
+                _ = control::serve(&config.ui.control_address, &controller_read_lock, &terminal_mutex) => {},
+                _ = control::command_loop(command_receiver, &controller_read_lock, &terminal_mutex) => {},
+                _ = config_watch::watch(&config_paths, &overrides, config_watch::POLL_INTERVAL, &controller_read_lock, &terminal_mutex) => {},
+                _ = state_store.save_loop(config.state.get_save_interval(), &controller_read_lock, &terminal_mutex) => {},
+            };
 
-        tokio::select! {
-            _ = controller_read_lock.listen(&terminal_mutex) => {},
-            _ = controller_read_lock.find_light_loop(&terminal_mutex) => {},
-            _ = TerminalUi::ui_loop(&terminal_mutex) => {},
-        };
+            let saved = state_store.save(&controller_read_lock).await;
+            controller_read_lock.disconnect(&terminal_mutex).await;
 
-        controller_read_lock.disconnect(&terminal_mutex).await;
+            let mut terminal_lock = terminal_mutex.write().await;
+            let _result = terminal_lock.restore_terminal().await;
 
-        let mut terminal_lock = terminal_mutex.write().await;
-        let _result = terminal_lock.restore_terminal().await;
+            if let Err(e) = saved {
+                eprintln!(
+                    "Failed to save light states to {}: {}",
+                    state_store.get_path(),
+                    e
+                );
+            }
 
-        println!("Exiting");
+            println!("Exiting");
+        }
     }
 
     Ok(())
 }
+
+/// Hands a command to the running bridge over the control API and prints its reply.
+async fn send_command(
+    config_paths: &[String],
+    overrides: &Overrides,
+    command: &ControlCommand,
+) -> Result<(), Box<dyn Error>> {
+    let control_address = match config_layers::load(config_paths, None, overrides).await {
+        Ok(config) => config.ui.control_address,
+        Err(_) => DEFAULT_CONTROL_ADDRESS.to_string(),
+    };
+    let reply = control::send(&control_address, command).await?;
+    println!("{}", reply);
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+
+use crate::personality::Personality;
+use crate::protocol::Protocol;
+
+// Prefixes Neewer puts in front of the model name when advertising.
+const NAME_PREFIXES: [&str; 3] = ["NEEWER-", "NWR-", "NW-"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    /// Full colour via HSI, plus CCT.
+    Rgb,
+    /// Warm/cool white only.
+    BiColor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelInfo {
+    pub name: &'static str,
+    pub color_support: ColorSupport,
+    /// Lowest and highest colour temperature in kelvin.
+    pub cct_range: (u16, u16),
+    /// Number of brightness steps above zero the light accepts.
+    pub brightness_steps: u8,
+    /// Fastest rate the light keeps up with before it starts dropping writes.
+    pub max_update_hz: u16,
+    pub protocol: Protocol,
+}
+
+const fn model(
+    name: &'static str,
+    color_support: ColorSupport,
+    cct_range: (u16, u16),
+    protocol: Protocol,
+) -> ModelInfo {
+    ModelInfo {
+        name,
+        color_support,
+        cct_range,
+        brightness_steps: 100,
+        max_update_hz: match protocol {
+            Protocol::Legacy => 20,
+            Protocol::Infinity => 30,
+        },
+        protocol,
+    }
+}
+
+static MODELS: [ModelInfo; 16] = [
+    model(
+        "RGB660 PRO",
+        ColorSupport::Rgb,
+        (3200, 5600),
+        Protocol::Legacy,
+    ),
+    model("RGB660", ColorSupport::Rgb, (3200, 5600), Protocol::Legacy),
+    model(
+        "RGB530 PRO",
+        ColorSupport::Rgb,
+        (3200, 5600),
+        Protocol::Legacy,
+    ),
+    model("RGB530", ColorSupport::Rgb, (3200, 5600), Protocol::Legacy),
+    model("RGB480", ColorSupport::Rgb, (3200, 5600), Protocol::Legacy),
+    model("RGB960", ColorSupport::Rgb, (3200, 5600), Protocol::Legacy),
+    model("RGB176", ColorSupport::Rgb, (3200, 5600), Protocol::Legacy),
+    model(
+        "SNL660",
+        ColorSupport::BiColor,
+        (3200, 5600),
+        Protocol::Legacy,
+    ),
+    model(
+        "SNL530",
+        ColorSupport::BiColor,
+        (3200, 5600),
+        Protocol::Legacy,
+    ),
+    model("GL1", ColorSupport::BiColor, (2900, 7000), Protocol::Legacy),
+    model("RGB62", ColorSupport::Rgb, (3200, 5600), Protocol::Infinity),
+    model("TL60", ColorSupport::Rgb, (2500, 10000), Protocol::Infinity),
+    model("TL90", ColorSupport::Rgb, (2500, 10000), Protocol::Infinity),
+    model(
+        "TL120",
+        ColorSupport::Rgb,
+        (2500, 10000),
+        Protocol::Infinity,
+    ),
+    model("CB60", ColorSupport::Rgb, (2500, 6500), Protocol::Infinity),
+    model("SL90", ColorSupport::Rgb, (2500, 10000), Protocol::Infinity),
+];
+
+impl ModelInfo {
+    /// Finds the model for an advertised local name such as "NEEWER-RGB660" or "NW-RGB176".
+    pub fn lookup(local_name: &str) -> Option<&'static ModelInfo> {
+        let upper = local_name.trim().to_uppercase();
+        let name = NAME_PREFIXES
+            .iter()
+            .find_map(|prefix| upper.strip_prefix(prefix))
+            .unwrap_or(&upper);
+
+        MODELS
+            .iter()
+            .filter(|model| name.starts_with(model.name))
+            .max_by_key(|model| model.name.len())
+    }
+
+    pub fn supports(&self, personality: Personality) -> bool {
+        match personality {
+            Personality::Rgb => self.color_support == ColorSupport::Rgb,
+            Personality::Cct => true,
+        }
+    }
+}
+
+impl fmt::Display for ModelInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let color = match self.color_support {
+            ColorSupport::Rgb => "RGB",
+            ColorSupport::BiColor => "bi-colour",
+        };
+        write!(
+            f,
+            "{} ({}, {}-{}K, {} steps, {}Hz, {:?})",
+            self.name,
+            color,
+            self.cct_range.0,
+            self.cct_range.1,
+            self.brightness_steps,
+            self.max_update_hz,
+            self.protocol
+        )
+    }
+}
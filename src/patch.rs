@@ -0,0 +1,447 @@
+use std::error::Error;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use btleplug::api::BDAddr;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, BufReader, Lines, Stdin};
+use tokio::sync::RwLock;
+
+use crate::config::{self, Config, LightConfig, Overrides, UNRESOLVED_NAME_ORDER};
+use crate::config_format::ConfigFormat;
+use crate::config_layers::{self, Layer};
+use crate::control::ControlCommand;
+use crate::light_controller::LightController;
+use crate::model::{ColorSupport, ModelInfo};
+use crate::personality::Personality;
+use crate::scan::{self, ScanOptions, ScanResult};
+use crate::terminal_ui::TerminalUi;
+use crate::transport::BleTransport;
+use crate::validate::{MAX_UNIVERSE, MIN_UNIVERSE};
+
+const DMX_CHANNELS: u16 = 512;
+
+// Long enough to outlast any answer; the flash stops as soon as the prompt is answered.
+const IDENTIFY_WHILE_PROMPTING: Duration = Duration::from_secs(600);
+
+/// A light the wizard is adding to the config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchEntry {
+    pub id: BDAddr,
+    pub label: String,
+    pub personality: Personality,
+    pub universe: u16,
+    pub address: u16,
+}
+
+impl PatchEntry {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id.to_string(),
+            "label": self.label,
+            "universe": self.universe,
+            "address": self.address,
+            "personality": self.personality,
+        })
+    }
+}
+
+/// Hands out consecutive DMX addresses, stepping around anything already patched and moving
+/// on to the next universe when one fills up.
+pub struct AddressAllocator {
+    universe: u16,
+    next_address: u16,
+    /// Universe and inclusive channel range of everything patched so far.
+    used: Vec<(u16, u16, u16)>,
+}
+
+impl AddressAllocator {
+    pub fn new(universe: u16, start_address: u16, existing: &[LightConfig]) -> Self {
+        Self {
+            universe,
+            next_address: start_address.max(1),
+            used: existing
+                .iter()
+                .map(|light| {
+                    let end = light.address + light.personality.footprint() - 1;
+                    (light.universe, light.address, end)
+                })
+                .collect(),
+        }
+    }
+
+    /// The first free address after every light on `universe`, for use as a default.
+    pub fn first_free(universe: u16, existing: &[LightConfig]) -> u16 {
+        existing
+            .iter()
+            .filter(|light| light.universe == universe)
+            .map(|light| light.address + light.personality.footprint())
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// The universe and address for a light of `footprint` channels, or an error once every
+    /// universe is full.
+    pub fn allocate(&mut self, footprint: u16) -> Result<(u16, u16), String> {
+        loop {
+            let end = self
+                .next_address
+                .checked_add(footprint.max(1) - 1)
+                .filter(|end| *end <= DMX_CHANNELS);
+            let Some(end) = end else {
+                if self.universe >= MAX_UNIVERSE {
+                    return Err("No universe has room for another light".to_string());
+                }
+                self.universe += 1;
+                self.next_address = 1;
+                continue;
+            };
+
+            let clash = self.used.iter().find(|(universe, start, used_end)| {
+                *universe == self.universe && *start <= end && self.next_address <= *used_end
+            });
+            match clash {
+                Some((_, _, used_end)) => self.next_address = used_end + 1,
+                None => {
+                    let address = (self.universe, self.next_address);
+                    self.used.push((self.universe, self.next_address, end));
+                    self.next_address = end + 1;
+                    return Ok(address);
+                }
+            }
+        }
+    }
+}
+
+/// Adds `entries` to a config document, leaving every existing entry exactly as it was.
+/// Entries for lights that are already in the document are skipped. Legacy documents stay
+/// legacy; a missing one is started in the current format. Documents with a default profile
+/// that sets its own lights get the entries added to that profile.
+pub fn merge(existing: Option<Value>, entries: &[PatchEntry]) -> Result<Value, String> {
+    let mut document = match existing {
+        Some(document) => document,
+        None => config::migrate(Value::Array(vec![]))?,
+    };
+    let profile = document
+        .get("profile")
+        .and_then(Value::as_str)
+        .filter(|profile| document["profiles"][profile].get("lights").is_some())
+        .map(str::to_string);
+    let lights = match &mut document {
+        Value::Array(lights) => lights,
+        Value::Object(fields) => {
+            let lights = match profile {
+                Some(profile) => fields["profiles"][profile.as_str()].get_mut("lights"),
+                None => fields.get_mut("lights"),
+            };
+            match lights {
+                Some(Value::Array(lights)) => lights,
+                _ => return Err("Expected the config to have a list of lights".to_string()),
+            }
+        }
+        _ => return Err("Expected the config to be a document or a list of lights".to_string()),
+    };
+
+    for entry in entries {
+        // a range entry lists its devices under ids
+        let already_patched = lights
+            .iter()
+            .flat_map(|light| {
+                std::iter::once(&light["id"]).chain(light["ids"].as_array().into_iter().flatten())
+            })
+            .any(|id| id.as_str().and_then(|id| id.parse::<BDAddr>().ok()) == Some(entry.id));
+        if !already_patched {
+            lights.push(entry.to_json());
+        }
+    }
+    Ok(document)
+}
+
+/// The lights patched in other layers that `document`, written as the local layer at `path`,
+/// would hide. A layer's light list replaces the ones under it whole, so a local list
+/// started from scratch drops every light the shared files patch.
+pub fn hidden_lights(
+    layers: &[Layer],
+    path: &str,
+    document: &Value,
+    profile: Option<&str>,
+    overrides: &Overrides,
+) -> Result<Vec<LightConfig>, Box<dyn Error>> {
+    let before = effective_config(layers, profile, overrides)?;
+    let mut written: Vec<Layer> = layers
+        .iter()
+        .filter(|layer| layer.path != path)
+        .cloned()
+        .collect();
+    written.push(Layer {
+        path: path.to_string(),
+        text: serde_json::to_string_pretty(document)?,
+    });
+    let after = effective_config(&written, profile, overrides)?;
+
+    Ok(before
+        .lights
+        .into_iter()
+        .filter(|light| {
+            !after
+                .lights
+                .iter()
+                .any(|kept| kept.matcher == light.matcher)
+        })
+        .collect())
+}
+
+// the config the layers make, or the defaults when there are none yet
+fn effective_config(
+    layers: &[Layer],
+    profile: Option<&str>,
+    overrides: &Overrides,
+) -> Result<Config, Box<dyn Error>> {
+    if layers.is_empty() {
+        return Ok(Config::default());
+    }
+    let layered = config_layers::merge(layers).map_err(|problems| problems.join("\n"))?;
+    layered.to_config(profile, overrides)
+}
+
+/// Walks the user through patching every light in range that no layer of the config
+/// patches, flashing each one so they can tell which is which, then merges the result into
+/// the local layer at `path`.
+pub async fn run(
+    transports: &[Arc<dyn BleTransport>],
+    config_paths: &[String],
+    path: &str,
+    profile: Option<&str>,
+    overrides: &Overrides,
+) -> Result<(), Box<dyn Error>> {
+    let format = ConfigFormat::from_path(path);
+    if format != ConfigFormat::Json {
+        // rewriting it would lose the crew's comments
+        return Err(format!(
+            "The patch wizard only writes JSON configs and {} is {}; add the lights by hand",
+            path, format
+        )
+        .into());
+    }
+
+    let existing_document: Option<Value> = match tokio::fs::read_to_string(path).await {
+        Ok(data) => Some(serde_json::from_str(&data)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    // a missing local config is only in the paths when there's nothing else to read
+    let paths: Vec<String> = config_paths
+        .iter()
+        .filter(|layer_path| existing_document.is_some() || *layer_path != path)
+        .cloned()
+        .collect();
+    let layers = config_layers::read_layers(&paths).await?;
+    let shared: Vec<&str> = layers
+        .iter()
+        .map(|layer| layer.path.as_str())
+        .filter(|layer_path| *layer_path != path)
+        .collect();
+    let config = effective_config(&layers, profile, overrides)?;
+
+    // checked up front, rather than after the user has named every light
+    let unchanged = merge(existing_document.clone(), &[])?;
+    let hidden = hidden_lights(&layers, path, &unchanged, profile, overrides)?;
+    if !hidden.is_empty() {
+        return Err(format!(
+            "Writing {} would hide the {} light(s) patched in {}, since its light list \
+             replaces theirs; copy their lights into {} first, or add the new lights to {} by hand",
+            path,
+            hidden.len(),
+            shared.join(" + "),
+            path,
+            shared.join(" or ")
+        )
+        .into());
+    }
+
+    println!("Scanning for lights...");
+    let (by_name, found): (Vec<ScanResult>, Vec<ScanResult>) =
+        scan::scan(transports, &ScanOptions::default(), Some(&config))
+            .await?
+            .into_iter()
+            .filter(|result| !result.configured)
+            .partition(|result| result.by_name);
+    if !by_name.is_empty() {
+        // patching one by address as well could bind it twice
+        println!(
+            "Leaving out {} light(s) a name rule may pick ({})",
+            by_name.len(),
+            UNRESOLVED_NAME_ORDER
+        );
+    }
+    if found.is_empty() {
+        println!("No unpatched lights found");
+        return Ok(());
+    }
+    println!("Found {} unpatched light(s)", found.len());
+
+    let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+    let universe: u16 = prompt_parsed(&mut stdin, "Universe", "1", |answer| {
+        answer
+            .parse()
+            .ok()
+            .filter(|universe| (MIN_UNIVERSE..=MAX_UNIVERSE).contains(universe))
+            .ok_or("Expected a universe from 1 to 63999")
+    })
+    .await?;
+    let first_free = AddressAllocator::first_free(universe, &config.lights).to_string();
+    let start_address: u16 = prompt_parsed(&mut stdin, "Start address", &first_free, |answer| {
+        answer
+            .parse()
+            .ok()
+            .filter(|address| (1..=DMX_CHANNELS).contains(address))
+            .ok_or("Expected an address from 1 to 512")
+    })
+    .await?;
+    let mut allocator = AddressAllocator::new(universe, start_address, &config.lights);
+
+    let mut entries = vec![];
+    for result in found.iter() {
+        println!(
+            "\n{} ({}, RSSI {}) should now be flashing",
+            result.address,
+            result.model.as_deref().unwrap_or("unknown model"),
+            result.rssi.map_or("?".to_string(), |rssi| rssi.to_string())
+        );
+        let label = identify_while(transports, result.address, async {
+            prompt(&mut stdin, "Name (blank to skip)", "").await
+        })
+        .await??;
+        if label.is_empty() {
+            continue;
+        }
+
+        let default_personality = match result.model.as_deref().and_then(ModelInfo::lookup) {
+            Some(model) if model.color_support == ColorSupport::BiColor => "cct",
+            _ => "rgb",
+        };
+        let personality: Personality = prompt_parsed(
+            &mut stdin,
+            "Personality (rgb/cct)",
+            default_personality,
+            |answer| {
+                serde_json::from_value(Value::String(answer.to_string()))
+                    .map_err(|_| "Expected rgb or cct")
+            },
+        )
+        .await?;
+
+        let (universe, address) = match allocator.allocate(personality.footprint()) {
+            Ok(address) => address,
+            Err(e) => {
+                println!("{}; stopping with the lights named so far", e);
+                break;
+            }
+        };
+        entries.push(PatchEntry {
+            id: result.address,
+            label,
+            personality,
+            universe,
+            address,
+        });
+    }
+
+    if entries.is_empty() {
+        println!("Nothing to patch");
+        return Ok(());
+    }
+
+    println!();
+    for entry in entries.iter() {
+        println!(
+            "{:<20} {} -> {}.{} ({:?})",
+            entry.label, entry.id, entry.universe, entry.address, entry.personality
+        );
+    }
+    let confirm = prompt(&mut stdin, &format!("Write to {}? (y/n)", path), "n").await?;
+    if !confirm.eq_ignore_ascii_case("y") {
+        println!("Nothing written");
+        return Ok(());
+    }
+
+    let merged = merge(existing_document, &entries)?;
+    // write alongside and rename, so an interrupted write can't leave a half-written config
+    let temp_path = format!("{}.tmp", path);
+    tokio::fs::write(&temp_path, serde_json::to_string_pretty(&merged)? + "\n").await?;
+    tokio::fs::rename(&temp_path, path).await?;
+    println!("Patched {} light(s) into {}", entries.len(), path);
+    Ok(())
+}
+
+/// Flashes one light until `until` finishes, connecting to it just for the purpose.
+async fn identify_while<T>(
+    transports: &[Arc<dyn BleTransport>],
+    id: BDAddr,
+    until: impl std::future::Future<Output = T>,
+) -> Result<T, Box<dyn Error>> {
+    let light: LightConfig = serde_json::from_value(json!({
+        "id": id.to_string(),
+        "universe": 1,
+        "address": 1,
+    }))?;
+    let config = Config {
+        lights: vec![light],
+        ..Default::default()
+    };
+    let controller = LightController::with_sacn_client(&config, transports.to_vec(), None);
+    let terminal = RwLock::new(TerminalUi::headless());
+    let identify = ControlCommand::Identify {
+        target: id.to_string(),
+        duration: IDENTIFY_WHILE_PROMPTING,
+    };
+    let _ = controller.execute(&identify, &terminal).await;
+
+    let result = tokio::select! {
+        _ = controller.find_light_loop(&terminal) => {
+            Err(format!("Stopped looking for {} before the answer", id).into())
+        }
+        result = until => Ok(result),
+    };
+    controller.disconnect(&terminal).await;
+    result
+}
+
+async fn prompt(
+    stdin: &mut Lines<BufReader<Stdin>>,
+    question: &str,
+    default: &str,
+) -> Result<String, Box<dyn Error>> {
+    if default.is_empty() {
+        print!("{}: ", question);
+    } else {
+        print!("{} [{}]: ", question, default);
+    }
+    std::io::stdout().flush()?;
+
+    let answer = stdin.next_line().await?.ok_or("Input closed")?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer.to_string()
+    })
+}
+
+/// Asks `question` until `parse` accepts the answer, so a typo doesn't lose the lights
+/// already named.
+async fn prompt_parsed<T>(
+    stdin: &mut Lines<BufReader<Stdin>>,
+    question: &str,
+    default: &str,
+    parse: impl Fn(&str) -> Result<T, &'static str>,
+) -> Result<T, Box<dyn Error>> {
+    loop {
+        let answer = prompt(stdin, question, default).await?;
+        match parse(&answer) {
+            Ok(value) => return Ok(value),
+            Err(e) => println!("{}", e),
+        }
+    }
+}
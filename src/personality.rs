@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use crate::color::Color;
+use crate::light_state::LightState;
+
+/// How a light's DMX footprint is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Personality {
+    /// Red, green, blue.
+    #[default]
+    Rgb,
+    /// Dimmer, colour temperature (warmest to coolest the model supports).
+    Cct,
+}
+
+impl Personality {
+    pub const ALL: [Personality; 2] = [Personality::Rgb, Personality::Cct];
+
+    /// What consoles and patch sheets call it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Personality::Rgb => "RGB",
+            Personality::Cct => "CCT",
+        }
+    }
+
+    /// What each channel of the footprint does, in order.
+    pub fn channel_names(&self) -> &'static [&'static str] {
+        match self {
+            Personality::Rgb => &["Red", "Green", "Blue"],
+            Personality::Cct => &["Dimmer", "Colour Temperature"],
+        }
+    }
+
+    pub fn footprint(&self) -> u16 {
+        match self {
+            Personality::Rgb => 3,
+            Personality::Cct => 2,
+        }
+    }
+
+    pub fn decode(&self, channels: &[u8]) -> LightState {
+        match self {
+            Personality::Rgb => LightState::Rgb(Color::new(channels[0], channels[1], channels[2])),
+            Personality::Cct => LightState::Cct {
+                dimmer: channels[0],
+                temperature: channels[1],
+            },
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use btleplug::api::BDAddr;
+use serde::Deserialize;
+
+use crate::model::ModelInfo;
+
+const COMMAND_PREFIX: u8 = 0x78;
+
+const LEGACY_HSI_TAG: u8 = 0x86;
+const LEGACY_CCT_TAG: u8 = 0x87;
+
+// Ask the light to report on the notify characteristic.
+const LEGACY_POWER_QUERY_TAG: u8 = 0x85;
+const LEGACY_CHANNEL_QUERY_TAG: u8 = 0x84;
+const LEGACY_BATTERY_QUERY_TAG: u8 = 0x95;
+
+const INFINITY_HSI_TAG: u8 = 0x8F;
+const INFINITY_HSI_SUBTAG: u8 = 0x86;
+const INFINITY_CCT_TAG: u8 = 0x90;
+const INFINITY_CCT_SUBTAG: u8 = 0x87;
+
+// Infinity-series lights not in the model table still carry this in their name.
+const INFINITY_MARKER: &str = "INFINITY";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// `0x78 <tag> <len> <payload> <checksum>`, used by the older RGB panels.
+    Legacy,
+    /// `0x78 <tag> <len> <mac> <subtag> <payload> <checksum>`, used by the RGB62, TL60 and
+    /// Infinity-series lights.
+    Infinity,
+}
+
+impl Protocol {
+    pub fn detect(local_name: &str) -> Self {
+        if let Some(model) = ModelInfo::lookup(local_name) {
+            model.protocol
+        } else if local_name.to_uppercase().contains(INFINITY_MARKER) {
+            Protocol::Infinity
+        } else {
+            Protocol::Legacy
+        }
+    }
+
+    pub fn hsi_command(&self, mac: BDAddr, hue: u16, saturation: u8, brightness: u8) -> Vec<u8> {
+        let hue_lsb = (hue & 0xFF) as u8;
+        let hue_msb = ((hue >> 8) & 0xFF) as u8;
+        let params = [hue_lsb, hue_msb, saturation, brightness];
+
+        match self {
+            Protocol::Legacy => Self::frame(LEGACY_HSI_TAG, &params),
+            Protocol::Infinity => {
+                Self::infinity_frame(INFINITY_HSI_TAG, mac, INFINITY_HSI_SUBTAG, &params)
+            }
+        }
+    }
+
+    /// `temperature` is in units of 100K.
+    pub fn cct_command(&self, mac: BDAddr, brightness: u8, temperature: u8) -> Vec<u8> {
+        let params = [brightness, temperature];
+
+        match self {
+            Protocol::Legacy => Self::frame(LEGACY_CCT_TAG, &params),
+            Protocol::Infinity => {
+                Self::infinity_frame(INFINITY_CCT_TAG, mac, INFINITY_CCT_SUBTAG, &params)
+            }
+        }
+    }
+
+    /// The commands asking the light to report its power, channel and battery, or None if we
+    /// can't read it back. Infinity lights answer in a framing `Readback` doesn't parse yet.
+    pub fn status_queries(&self) -> Option<[Vec<u8>; 3]> {
+        match self {
+            Protocol::Legacy => Some([
+                Self::frame(LEGACY_POWER_QUERY_TAG, &[]),
+                Self::frame(LEGACY_CHANNEL_QUERY_TAG, &[]),
+                Self::frame(LEGACY_BATTERY_QUERY_TAG, &[]),
+            ]),
+            Protocol::Infinity => None,
+        }
+    }
+
+    fn frame(tag: u8, params: &[u8]) -> Vec<u8> {
+        let mut cmd = vec![COMMAND_PREFIX, tag, params.len() as u8];
+        cmd.extend_from_slice(params);
+        cmd.push(Self::checksum(&cmd));
+        cmd
+    }
+
+    fn infinity_frame(tag: u8, mac: BDAddr, subtag: u8, params: &[u8]) -> Vec<u8> {
+        let mac = mac.into_inner();
+        let mut payload = Vec::with_capacity(mac.len() + 1 + params.len());
+        payload.extend_from_slice(&mac);
+        payload.push(subtag);
+        payload.extend_from_slice(params);
+        Self::frame(tag, &payload)
+    }
+
+    pub fn checksum(send_value: &[u8]) -> u8 {
+        let mut check_sum: u8 = 0;
+
+        for value in send_value {
+            check_sum = check_sum.wrapping_add(*value);
+        }
+
+        check_sum
+    }
+}
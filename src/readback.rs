@@ -0,0 +1,107 @@
+use std::fmt;
+
+use crate::light_state::LightState;
+use crate::protocol::Protocol;
+
+const RESPONSE_PREFIX: u8 = 0x78;
+
+const CHANNEL_TAG: u8 = 0x01;
+const POWER_TAG: u8 = 0x02;
+const BATTERY_TAG: u8 = 0x95;
+
+const POWER_ON: u8 = 0x01;
+
+const MODE_CCT: u8 = 0x01;
+const MODE_HSI: u8 = 0x02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadbackMode {
+    Cct,
+    Hsi,
+    Scene,
+}
+
+/// What the light last reported about itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Readback {
+    pub power: Option<bool>,
+    pub mode: Option<ReadbackMode>,
+    pub brightness: Option<u8>,
+    pub battery: Option<u8>,
+}
+
+impl Readback {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies one notification, returning false if it wasn't a frame we understand.
+    pub fn apply(&mut self, bytes: &[u8]) -> bool {
+        if bytes.len() < 4 || bytes[0] != RESPONSE_PREFIX {
+            return false;
+        }
+
+        let len = bytes[2] as usize;
+        if bytes.len() != len + 4 {
+            return false;
+        }
+
+        let (frame, checksum) = bytes.split_at(bytes.len() - 1);
+        if Protocol::checksum(frame) != checksum[0] {
+            return false;
+        }
+
+        let payload = &frame[3..];
+        match (bytes[1], payload) {
+            (POWER_TAG, [power]) => self.power = Some(*power == POWER_ON),
+            (CHANNEL_TAG, [mode, brightness, ..]) => {
+                self.mode = Some(match *mode {
+                    MODE_CCT => ReadbackMode::Cct,
+                    MODE_HSI => ReadbackMode::Hsi,
+                    _ => ReadbackMode::Scene,
+                });
+                self.brightness = Some(*brightness);
+            }
+            (BATTERY_TAG, [battery]) => self.battery = Some(*battery),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Whether the reported state agrees with what we last sent, ignoring anything not reported.
+    pub fn matches(&self, state: &LightState) -> bool {
+        let (mode, brightness) = match state {
+            LightState::Rgb(color) => (ReadbackMode::Hsi, color.to_hsv().2),
+            LightState::Cct { dimmer, .. } => (
+                ReadbackMode::Cct,
+                ((*dimmer as u32 * 100 + 127) / 255) as u8,
+            ),
+        };
+
+        let power_ok = self.power.is_none_or(|on| on || brightness == 0);
+        let mode_ok = self.mode.is_none_or(|m| m == mode);
+        let brightness_ok = self.brightness.is_none_or(|b| b.abs_diff(brightness) <= 1);
+        power_ok && mode_ok && brightness_ok
+    }
+}
+
+impl fmt::Display for Readback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let power = match self.power {
+            Some(true) => "On",
+            Some(false) => "Off",
+            None => "?",
+        };
+        write!(f, "{}", power)?;
+        if let Some(mode) = self.mode {
+            write!(f, " {:?}", mode)?;
+        }
+        if let Some(brightness) = self.brightness {
+            write!(f, " {}%", brightness)?;
+        }
+        if let Some(battery) = self.battery {
+            write!(f, " battery {}%", battery)?;
+        }
+        Ok(())
+    }
+}
@@ -2,29 +2,64 @@ use async_std::net::UdpSocket;
 use ratatui::style::Color;
 use std::io;
 use std::net::{Ipv4Addr, SocketAddrV4};
+use std::sync::Mutex;
 use tokio::sync::RwLock;
 
+use crate::config::SacnConfig;
 use crate::sacn_packet::SacnDmxPacket;
 use crate::terminal_ui::TerminalUi;
 
-const SACN_PORT: u16 = 5568;
-
 pub struct SacnClient {
     socket: UdpSocket,
-    universes: Vec<u16>,
+    universes: Mutex<Vec<u16>>,
+    interface: Ipv4Addr,
+}
+
+fn multicast_address(universe: u16) -> Ipv4Addr {
+    Ipv4Addr::new(239, 255, (universe >> 8) as u8, (universe & 0xFF) as u8)
 }
 
 impl SacnClient {
-    pub async fn new(universes: Vec<u16>) -> io::Result<Self> {
-        let socket_addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, SACN_PORT);
+    pub async fn new(universes: Vec<u16>, config: &SacnConfig) -> io::Result<Self> {
+        let socket_addr = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, config.port);
         let socket = UdpSocket::bind(socket_addr).await?;
         for universe in &universes {
-            let multicast_addr =
-                Ipv4Addr::new(239, 255, (*universe >> 8) as u8, (*universe & 0xFF) as u8);
-            socket.join_multicast_v4(multicast_addr, Ipv4Addr::UNSPECIFIED)?;
+            socket.join_multicast_v4(multicast_address(*universe), config.interface)?;
+        }
+
+        Ok(SacnClient {
+            socket,
+            universes: Mutex::new(universes),
+            interface: config.interface,
+        })
+    }
+
+    /// Joins universes that are newly patched and leaves ones nothing listens to any more.
+    pub fn set_universes(&self, universes: Vec<u16>) -> io::Result<()> {
+        let mut current = self.universes.lock().unwrap();
+        // kept in step as we go, so a failure part way leaves an accurate list behind
+        for universe in universes.iter() {
+            if !current.contains(universe) {
+                self.socket
+                    .join_multicast_v4(multicast_address(*universe), self.interface)?;
+                current.push(*universe);
+            }
+        }
+        let stale: Vec<u16> = current
+            .iter()
+            .copied()
+            .filter(|universe| !universes.contains(universe))
+            .collect();
+        for universe in stale {
+            self.socket
+                .leave_multicast_v4(multicast_address(universe), self.interface)?;
+            current.retain(|u| *u != universe);
         }
+        Ok(())
+    }
 
-        Ok(SacnClient { socket, universes })
+    pub fn get_universes(&self) -> Vec<u16> {
+        self.universes.lock().unwrap().clone()
     }
 
     pub async fn disconnect(&self, terminal: &RwLock<TerminalUi>) -> Result<(), btleplug::Error> {
@@ -33,12 +68,9 @@ impl SacnClient {
             .await
             .set_sacn_status("Disconnected", Color::Red);
 
-        for universe in &self.universes {
+        for universe in self.universes.lock().unwrap().iter() {
             self.socket
-                .leave_multicast_v4(
-                    Ipv4Addr::new(239, 255, (*universe >> 8) as u8, (*universe & 0xFF) as u8),
-                    Ipv4Addr::UNSPECIFIED,
-                )
+                .leave_multicast_v4(multicast_address(*universe), self.interface)
                 .unwrap();
         }
         Ok(())
@@ -57,6 +89,6 @@ impl SacnClient {
     }
 
     pub fn get_socket(&self) -> &UdpSocket {
-        return &self.socket;
+        &self.socket
     }
 }
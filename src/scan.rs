@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use btleplug::api::{BDAddr, PeripheralProperties};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::config::{Config, UNRESOLVED_NAME_ORDER};
+use crate::model::ModelInfo;
+use crate::transport::BleTransport;
+
+const DEFAULT_SCAN_DURATION: Duration = Duration::from_secs(5);
+
+// Advertised by every Neewer light we've seen, including ones with unhelpful names.
+const NEEWER_SERVICE_UUID_STR: &str = "69400001-B5A3-F393-E0A9-E50E24DCCA99";
+const NEEWER_NAME_PREFIXES: [&str; 3] = ["NEEWER", "NWR-", "NW-"];
+lazy_static! {
+    static ref neewer_service_uuid: Uuid = Uuid::parse_str(NEEWER_SERVICE_UUID_STR).unwrap();
+}
+
+#[derive(Debug, PartialEq, clap::Args)]
+pub struct ScanOptions {
+    /// How long to listen for, in seconds.
+    #[arg(long, default_value = "5", value_parser = crate::cli::parse_seconds)]
+    pub duration: Duration,
+    /// Lists every device, not just the ones that look like Neewer lights.
+    #[arg(long)]
+    pub all: bool,
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            duration: DEFAULT_SCAN_DURATION,
+            all: false,
+            json: false,
+        }
+    }
+}
+
+/// One device heard during a scan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScanResult {
+    #[serde(serialize_with = "serialize_address")]
+    pub address: BDAddr,
+    pub name: Option<String>,
+    pub rssi: Option<i16>,
+    pub model: Option<String>,
+    /// The adapter that heard it loudest.
+    pub adapter: String,
+    /// Whether a light in the config is patched to this device's address.
+    pub configured: bool,
+    /// Whether a name and index rule may pick this device instead. Which device it picks
+    /// depends on the order the running bridge first hears them, so a scan can't tell.
+    pub by_name: bool,
+}
+
+fn serialize_address<S: serde::Serializer>(address: &BDAddr, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&address.to_string())
+}
+
+/// Whether a device looks like a Neewer light, by name or advertised service.
+pub fn is_neewer(properties: &PeripheralProperties) -> bool {
+    let named_neewer = properties.local_name.as_deref().is_some_and(|name| {
+        let name = name.to_uppercase();
+        NEEWER_NAME_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+            || ModelInfo::lookup(&name).is_some()
+    });
+    named_neewer || properties.services.contains(&neewer_service_uuid)
+}
+
+/// Scans every adapter for `options.duration` and lists what was heard, strongest first.
+/// A device heard by several adapters is listed once, against the one with the best signal.
+pub async fn scan(
+    transports: &[Arc<dyn BleTransport>],
+    options: &ScanOptions,
+    config: Option<&Config>,
+) -> Result<Vec<ScanResult>, btleplug::Error> {
+    for transport in transports {
+        transport.start_scan().await?;
+    }
+    tokio::time::sleep(options.duration).await;
+
+    let mut results: BTreeMap<BDAddr, ScanResult> = BTreeMap::new();
+    for transport in transports {
+        for peripheral in transport.peripherals().await? {
+            let Some(properties) = peripheral.properties().await? else {
+                continue;
+            };
+            if !options.all && !is_neewer(&properties) {
+                continue;
+            }
+
+            let result = ScanResult {
+                address: properties.address,
+                model: properties
+                    .local_name
+                    .as_deref()
+                    .and_then(ModelInfo::lookup)
+                    .map(|model| model.name.to_string()),
+                name: properties.local_name,
+                rssi: properties.rssi,
+                adapter: transport.name().to_string(),
+                configured: false,
+                by_name: false,
+            };
+            match results.get(&result.address) {
+                Some(existing) if existing.rssi >= result.rssi => {}
+                _ => {
+                    results.insert(result.address, result);
+                }
+            }
+        }
+    }
+
+    let lights = config.map_or(&[][..], |config| config.lights.as_slice());
+    for result in results.values_mut() {
+        result.configured = lights
+            .iter()
+            .any(|light| light.matcher.get_address() == Some(result.address));
+        result.by_name = !result.configured
+            && lights.iter().any(|light| {
+                light
+                    .matcher
+                    .may_select(result.address, result.name.as_deref())
+            });
+    }
+
+    let mut results: Vec<ScanResult> = results.into_values().collect();
+    results.sort_by_key(|result| std::cmp::Reverse(result.rssi));
+    Ok(results)
+}
+
+pub fn print_table(results: &[ScanResult]) {
+    println!(
+        "{:<17}  {:>4}  {:<12}  {:<24}  {:<8}  Configured",
+        "Address", "RSSI", "Model", "Name", "Adapter"
+    );
+    for result in results {
+        println!(
+            "{:<17}  {:>4}  {:<12}  {:<24}  {:<8}  {}",
+            result.address.to_string(),
+            result.rssi.map_or("?".to_string(), |rssi| rssi.to_string()),
+            result.model.as_deref().unwrap_or("Unknown"),
+            result.name.as_deref().unwrap_or(""),
+            result.adapter,
+            match (result.configured, result.by_name) {
+                (true, _) => "yes".to_string(),
+                (false, true) => format!("by name ({})", UNRESOLVED_NAME_ORDER),
+                (false, false) => "no".to_string(),
+            }
+        );
+    }
+}
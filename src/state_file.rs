@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ratatui::style::Color;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time;
+
+use crate::light::Light;
+use crate::light_controller::LightController;
+use crate::light_state::LightState;
+use crate::terminal_ui::TerminalUi;
+
+pub const DEFAULT_STATE_PATH: &str = "data/state.json";
+
+struct States {
+    current: BTreeMap<String, LightState>,
+    /// What the file holds, to skip writing it when nothing moved.
+    written: BTreeMap<String, LightState>,
+}
+
+/// The last state of every light seen, keyed by match rule, kept in a file between runs.
+/// Lights missing from the running patch keep their entries, so switching profiles and back
+/// doesn't lose them.
+pub struct StateStore {
+    path: String,
+    states: Mutex<States>,
+}
+
+impl StateStore {
+    /// An empty store that will write to `path`.
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            states: Mutex::new(States {
+                current: BTreeMap::new(),
+                written: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Reads the states saved at `path`, starting empty if there's no file yet.
+    pub async fn open(path: &str) -> Result<Self, String> {
+        let saved: BTreeMap<String, LightState> = match tokio::fs::read_to_string(path).await {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| format!("Can't read light states from {}: {}", path, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(format!("Can't read light states from {}: {}", path, e)),
+        };
+        Ok(Self {
+            path: path.to_string(),
+            states: Mutex::new(States {
+                current: saved.clone(),
+                written: saved,
+            }),
+        })
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub async fn get(&self, key: &str) -> Option<LightState> {
+        self.states.lock().await.current.get(key).copied()
+    }
+
+    /// Offers each of `lights` its saved state, to be sent when it first connects. Lights
+    /// already offered one are skipped. Returns how many took theirs.
+    pub async fn restore(&self, lights: &[Arc<Light>]) -> usize {
+        let states = self.states.lock().await;
+        let mut restored = 0;
+        for light in lights {
+            if let Some(saved) = states.current.get(&light.get_matcher().to_string()) {
+                if light.offer_saved_state(*saved).await {
+                    restored += 1;
+                }
+            }
+        }
+        restored
+    }
+
+    /// Records what was last sent to every light that has been sent something and writes
+    /// the file if anything changed since it was last written.
+    pub async fn save(&self, controller: &LightController) -> io::Result<()> {
+        let mut states = self.states.lock().await;
+        for light in controller.get_lights() {
+            if let Some(state) = light.get_sent_state().await {
+                states
+                    .current
+                    .insert(light.get_matcher().to_string(), state);
+            }
+        }
+        if states.current == states.written {
+            return Ok(());
+        }
+
+        if let Some(parent) = Path::new(&self.path).parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // written aside and moved into place, so a crash mid-write can't leave half a file
+        let temp_path = format!("{}.tmp", self.path);
+        let text = serde_json::to_string_pretty(&states.current)? + "\n";
+        tokio::fs::write(&temp_path, text).await?;
+        tokio::fs::rename(&temp_path, &self.path).await?;
+        states.written = states.current.clone();
+        Ok(())
+    }
+
+    /// Saves every `interval`, reporting failures in the app status.
+    pub async fn save_loop(
+        &self,
+        interval: Duration,
+        controller: &LightController,
+        terminal: &RwLock<TerminalUi>,
+    ) {
+        loop {
+            time::sleep(interval).await;
+            if let Err(e) = self.save(controller).await {
+                terminal.write().await.set_app_status(
+                    format!("Failed to save light states to {}: {}", self.path, e).as_str(),
+                    Color::Red,
+                );
+            }
+        }
+    }
+}
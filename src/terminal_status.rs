@@ -3,10 +3,22 @@ use std::time::Duration;
 use ratatui::style::Color;
 
 use crate::event_counter::EventCounter;
+use crate::frame_counters::FrameCounters;
 
 pub struct TerminalStatus {
     pub status: String,
     pub color: Color,
+    /// Address of the peripheral the light is using, shown when it has a label.
+    pub device: String,
+    /// Config group the light belongs to, empty if none.
+    pub group: String,
+    pub model: String,
+    pub adapter: String,
+    pub commanded: String,
+    pub readback: String,
+    pub in_step: bool,
+    pub counters: FrameCounters,
+    pub write_mode: String,
     pub event_counter: EventCounter,
 }
 
@@ -15,7 +27,22 @@ impl TerminalStatus {
         Self {
             status: String::new(),
             color: Color::Reset,
+            device: String::new(),
+            group: String::new(),
+            model: String::new(),
+            adapter: String::new(),
+            commanded: String::new(),
+            readback: String::new(),
+            in_step: true,
+            counters: FrameCounters::new(),
+            write_mode: String::new(),
             event_counter: EventCounter::new(Duration::from_secs(1), 20),
         }
     }
 }
+
+impl Default for TerminalStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet},
     error::Error,
     io::{self, Stdout},
 };
@@ -7,7 +7,7 @@ use std::{
 use ratatui::{
     crossterm::event::{self, Event, KeyCode},
     layout::{Constraint, Direction, Layout},
-    style::Color,
+    style::{Color, Modifier, Style},
 };
 use ratatui::{
     crossterm::{
@@ -18,15 +18,36 @@ use ratatui::{
     widgets::{Block, Paragraph},
     Frame, Terminal,
 };
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 
+use crate::connection_state::ConnectionState;
+use crate::control::ControlCommand;
+use crate::frame_counters::FrameCounters;
+use crate::identify::DEFAULT_IDENTIFY_DURATION;
 use crate::terminal_status::TerminalStatus;
 
+/// One line of the light list: a group header or a light, by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Row {
+    Group(String),
+    Light(String),
+}
+
 pub struct TerminalUi {
     sacn_status: TerminalStatus,
-    light_status: HashMap<String, TerminalStatus>,
+    light_status: BTreeMap<String, TerminalStatus>,
+    /// Row the arrow keys have picked out, for commands like identify.
+    selected: Option<Row>,
+    /// Groups folded down to their header.
+    collapsed: BTreeSet<String>,
+    commands: Option<mpsc::UnboundedSender<ControlCommand>>,
+    /// The config's profiles, and the one running.
+    profiles: Vec<String>,
+    profile: Option<String>,
     app_status: TerminalStatus,
-    terminal: RwLock<Terminal<CrosstermBackend<Stdout>>>,
+    /// Connected and assigned light counts per adapter.
+    adapter_loads: BTreeMap<String, (usize, usize)>,
+    terminal: RwLock<Option<Terminal<CrosstermBackend<Stdout>>>>,
 }
 
 impl TerminalUi {
@@ -34,9 +55,31 @@ impl TerminalUi {
         let terminal = Self::setup_terminal().unwrap();
         Self {
             sacn_status: TerminalStatus::new(),
-            light_status: HashMap::new(),
+            light_status: BTreeMap::new(),
+            selected: None,
+            collapsed: BTreeSet::new(),
+            commands: None,
+            profiles: vec![],
+            profile: None,
+            app_status: TerminalStatus::new(),
+            adapter_loads: BTreeMap::new(),
+            terminal: RwLock::new(Some(terminal)),
+        }
+    }
+
+    /// Tracks status without taking over the terminal.
+    pub fn headless() -> Self {
+        Self {
+            sacn_status: TerminalStatus::new(),
+            light_status: BTreeMap::new(),
+            selected: None,
+            collapsed: BTreeSet::new(),
+            commands: None,
+            profiles: vec![],
+            profile: None,
             app_status: TerminalStatus::new(),
-            terminal: RwLock::new(terminal),
+            adapter_loads: BTreeMap::new(),
+            terminal: RwLock::new(None),
         }
     }
 
@@ -44,17 +87,84 @@ impl TerminalUi {
         let mut stdout = io::stdout();
         enable_raw_mode()?;
         execute!(stdout, EnterAlternateScreen)?;
+
+        // put the terminal back before a panic message is printed, or it's unreadable and the
+        // shell is left in raw mode
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = disable_raw_mode();
+            let _ = execute!(io::stdout(), LeaveAlternateScreen);
+            default_hook(info);
+        }));
+
         Ok(Terminal::new(CrosstermBackend::new(stdout))?)
     }
 
     pub async fn restore_terminal(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut lock = self.terminal.write().await;
+        let Some(term) = lock.as_mut() else {
+            return Ok(());
+        };
         disable_raw_mode()?;
-        let mut term = self.terminal.write().await;
         execute!(term.backend_mut(), LeaveAlternateScreen,)?;
         Ok(term.show_cursor()?)
     }
 
+    /// Where key presses that act on lights send their commands.
+    pub fn set_command_sender(&mut self, commands: mpsc::UnboundedSender<ControlCommand>) {
+        self.commands = Some(commands);
+    }
+
+    pub fn set_profiles(&mut self, profiles: Vec<String>, profile: Option<&str>) {
+        self.profiles = profiles;
+        self.profile = profile.map(str::to_string);
+    }
+
+    pub fn get_profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// The selected light's label or group's name, as a command target.
+    pub fn get_selected(&self) -> Option<&str> {
+        match self.selected.as_ref()? {
+            Row::Group(name) | Row::Light(name) => Some(name),
+        }
+    }
+
+    /// Lights without a group first, then each group's header followed by its lights unless
+    /// it's collapsed.
+    pub fn get_rows(&self) -> Vec<Row> {
+        let mut rows: Vec<Row> = self
+            .light_status
+            .iter()
+            .filter(|(_, status)| status.group.is_empty())
+            .map(|(id, _)| Row::Light(id.clone()))
+            .collect();
+
+        let groups: BTreeSet<&String> = self
+            .light_status
+            .values()
+            .map(|status| &status.group)
+            .filter(|group| !group.is_empty())
+            .collect();
+        for group in groups {
+            rows.push(Row::Group(group.clone()));
+            if !self.collapsed.contains(group) {
+                rows.extend(
+                    self.light_status
+                        .iter()
+                        .filter(|(_, status)| status.group == *group)
+                        .map(|(id, _)| Row::Light(id.clone())),
+                );
+            }
+        }
+        rows
+    }
+
     pub fn set_sacn_status(&mut self, status: &str, color: Color) {
+        if self.sacn_status.status != status {
+            log_status("sACN", status, color);
+        }
         self.sacn_status.color = color;
         self.sacn_status.status = status.to_string();
     }
@@ -64,29 +174,121 @@ impl TerminalUi {
     }
 
     pub fn set_light_status(&mut self, id: &str, status: &str, color: Color) {
-        let status_obj = self
-            .light_status
-            .entry(id.to_string())
-            .or_insert(TerminalStatus::new());
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
+        if status_obj.status != status {
+            log_status(id, status, color);
+        }
 
         status_obj.color = color;
         status_obj.status = status.to_string();
     }
 
+    pub fn set_light_device(&mut self, id: &str, device: &str) {
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
+
+        status_obj.device = device.to_string();
+    }
+
+    pub fn set_light_model(&mut self, id: &str, model: &str) {
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
+
+        status_obj.model = model.to_string();
+    }
+
+    pub fn set_light_group(&mut self, id: &str, group: Option<&str>) {
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
+
+        status_obj.group = group.unwrap_or_default().to_string();
+    }
+
+    pub fn set_light_adapter(&mut self, id: &str, adapter: &str) {
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
+
+        status_obj.adapter = adapter.to_string();
+    }
+
+    pub fn set_adapter_load(&mut self, adapter: &str, connected: usize, assigned: usize) {
+        self.adapter_loads
+            .insert(adapter.to_string(), (connected, assigned));
+    }
+
+    pub fn set_light_counters(&mut self, id: &str, counters: FrameCounters) {
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
+
+        status_obj.counters = counters;
+    }
+
+    pub fn set_light_write_mode(&mut self, id: &str, write_mode: &str) {
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
+
+        status_obj.write_mode = write_mode.to_string();
+    }
+
+    pub fn set_light_commanded(&mut self, id: &str, commanded: &str, in_step: bool) {
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
+
+        status_obj.commanded = commanded.to_string();
+        status_obj.in_step = in_step;
+    }
+
+    pub fn set_light_readback(&mut self, id: &str, readback: &str, in_step: bool) {
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
+
+        status_obj.readback = readback.to_string();
+        status_obj.in_step = in_step;
+    }
+
     pub fn add_light_event(&mut self, id: &str) {
-        let status_obj = self
-            .light_status
-            .entry(id.to_string())
-            .or_insert(TerminalStatus::new());
+        let status_obj = self.light_status.entry(id.to_string()).or_default();
 
         status_obj.event_counter.increment();
     }
 
+    /// Carries a light's status over to its new label after a reload.
+    /// Moves each light's status from its old id to its new one. Every entry is taken out
+    /// before any is put back, so lights can swap labels without losing either status.
+    pub fn rename_lights(&mut self, renames: &[(String, String)]) {
+        let moved: Vec<_> = renames
+            .iter()
+            .map(|(old_id, new_id)| (new_id, self.light_status.remove(old_id)))
+            .collect();
+        for (new_id, status_obj) in moved {
+            if let Some(status_obj) = status_obj {
+                self.light_status.insert(new_id.clone(), status_obj);
+            }
+        }
+
+        let selected = renames
+            .iter()
+            .find(|(old_id, _)| self.selected.as_ref() == Some(&Row::Light(old_id.clone())));
+        if let Some((_, new_id)) = selected {
+            self.selected = Some(Row::Light(new_id.clone()));
+        }
+    }
+
+    pub fn remove_light(&mut self, id: &str) {
+        self.light_status.remove(id);
+        if self.selected == Some(Row::Light(id.to_string())) {
+            self.selected = None;
+        }
+    }
+
+    pub fn get_light_status(&self, id: &str) -> Option<&TerminalStatus> {
+        self.light_status.get(id)
+    }
+
     pub fn set_app_status(&mut self, status: &str, color: Color) {
+        if self.app_status.status != status {
+            log_status("App", status, color);
+        }
         self.app_status.color = color;
         self.app_status.status = status.to_string();
     }
 
+    pub fn get_app_status(&self) -> &TerminalStatus {
+        &self.app_status
+    }
+
     pub async fn ui_loop(lock: &RwLock<Self>) {
         let mut should_exit = false;
         while !should_exit {
@@ -95,24 +297,109 @@ impl TerminalUi {
             let self_ref = lock.read().await;
             let mut terminal_ref = self_ref.terminal.write().await;
 
-            let _ = terminal_ref.draw(|f| {
-                self_ref.ui(f);
-            });
+            if let Some(terminal) = terminal_ref.as_mut() {
+                let _ = terminal.draw(|f| {
+                    self_ref.ui(f);
+                });
+            }
+
+            drop(terminal_ref);
+            drop(self_ref);
 
             tokio::time::sleep(tokio::time::Duration::from_millis(25)).await;
-            should_exit = TerminalUi::handle_events().unwrap();
+            if let Some(key) = TerminalUi::poll_key().unwrap() {
+                should_exit = lock.write().await.handle_key(key);
+            }
         }
     }
 
-    fn handle_events() -> io::Result<bool> {
+    fn poll_key() -> io::Result<Option<KeyCode>> {
         if event::poll(std::time::Duration::from_millis(25))? {
             if let Event::Key(key) = event::read()? {
-                if key.kind == event::KeyEventKind::Press && key.code == KeyCode::Char('q') {
-                    return Ok(true);
+                if key.kind == event::KeyEventKind::Press {
+                    return Ok(Some(key.code));
                 }
             }
         }
-        Ok(false)
+        Ok(None)
+    }
+
+    /// Acts on a key press, returning true if the app should exit.
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('q') => return true,
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Char('p') => {
+                if let (Some(name), Some(commands)) = (self.next_profile(), &self.commands) {
+                    let _ = commands.send(ControlCommand::Profile { name: Some(name) });
+                }
+            }
+            KeyCode::Left => self.set_collapsed(true),
+            KeyCode::Right => self.set_collapsed(false),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                if let Some(Row::Group(group)) = self.selected.as_ref() {
+                    let collapsed = self.collapsed.contains(group);
+                    self.set_collapsed(!collapsed);
+                }
+            }
+            KeyCode::Char('i') => {
+                if let (Some(target), Some(commands)) = (self.get_selected(), &self.commands) {
+                    let _ = commands.send(ControlCommand::Identify {
+                        target: target.to_string(),
+                        duration: DEFAULT_IDENTIFY_DURATION,
+                    });
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        let rows = self.get_rows();
+        if rows.is_empty() {
+            return;
+        }
+
+        let next = match self
+            .selected
+            .as_ref()
+            .and_then(|selected| rows.iter().position(|row| row == selected))
+        {
+            Some(current) => (current as isize + offset).clamp(0, rows.len() as isize - 1) as usize,
+            None => 0,
+        };
+        self.selected = Some(rows[next].clone());
+    }
+
+    /// The profile after the running one, wrapping around.
+    fn next_profile(&self) -> Option<String> {
+        let current = self
+            .profile
+            .as_ref()
+            .and_then(|profile| self.profiles.iter().position(|name| name == profile));
+        let next = current.map_or(0, |current| (current + 1) % self.profiles.len());
+        self.profiles.get(next).cloned()
+    }
+
+    /// Folds or unfolds the selected group, or the group of the selected light. Folding from
+    /// a light moves the selection up to its group's header so it stays visible.
+    fn set_collapsed(&mut self, collapsed: bool) {
+        let group = match self.selected.as_ref() {
+            Some(Row::Group(group)) => group.clone(),
+            Some(Row::Light(id)) => match self.light_status.get(id) {
+                Some(status) if !status.group.is_empty() => status.group.clone(),
+                _ => return,
+            },
+            None => return,
+        };
+        if collapsed {
+            self.collapsed.insert(group.clone());
+            self.selected = Some(Row::Group(group));
+        } else {
+            self.collapsed.remove(&group);
+        }
     }
 
     fn update_sparklines(&mut self) {
@@ -138,9 +425,11 @@ impl TerminalUi {
             .margin(1)
             .constraints(
                 [
-                    Constraint::Length(3),
+                    // reload errors can run to several lines
+                    Constraint::Length(self.app_status.status.lines().count().max(1) as u16 + 2),
                     Constraint::Length(5),
-                    Constraint::Min((self.light_status.len() * 2 + 2) as u16),
+                    Constraint::Length((self.adapter_loads.len() + 2) as u16),
+                    Constraint::Min(self.rows_height() + 2),
                 ]
                 .as_ref(),
             )
@@ -164,7 +453,7 @@ impl TerminalUi {
 
         // Adding sparkline for sacn status
         let sacn_sparkline = ratatui::widgets::Sparkline::default()
-            .data(&self.sacn_status.event_counter.get_history().as_slices().0)
+            .data(self.sacn_status.event_counter.get_history().as_slices().0)
             .style(self.sacn_status.color);
         frame.render_widget(
             sacn_sparkline,
@@ -174,40 +463,170 @@ impl TerminalUi {
             }),
         );
 
+        let adapter_lines: Vec<String> = self
+            .adapter_loads
+            .iter()
+            .map(|(adapter, (connected, assigned))| {
+                format!(
+                    "{}: {} connected / {} assigned",
+                    adapter, connected, assigned
+                )
+            })
+            .collect();
+        let adapter_block = Block::default()
+            .title("Adapters")
+            .borders(ratatui::widgets::Borders::ALL);
+        let adapter_paragraph = Paragraph::new(adapter_lines.join("\n")).block(adapter_block);
+        frame.render_widget(adapter_paragraph, chunks[2]);
+
+        let rows = self.get_rows();
         let light_status_block = Block::default()
-            .title("Lights")
+            .title(self.lights_title())
             .borders(ratatui::widgets::Borders::ALL);
-        let light_status_inner_area = light_status_block.inner(chunks[2]);
+        let light_status_inner_area = light_status_block.inner(chunks[3]);
         let light_status_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints(
-                (0..self.light_status.len() * 2)
-                    .map(|i| {
-                        if i % 2 == 0 {
-                            Constraint::Length(1)
-                        } else {
-                            Constraint::Length(1)
-                        }
-                    })
+                rows.iter()
+                    .map(|row| Constraint::Length(Self::row_height(row)))
                     .collect::<Vec<_>>(),
             )
             .split(light_status_inner_area);
 
-        frame.render_widget(light_status_block, chunks[2]);
+        frame.render_widget(light_status_block, chunks[3]);
+
+        for (row, area) in rows.iter().zip(light_status_layout.iter()) {
+            match row {
+                Row::Group(group) => self.render_group(frame, *area, group),
+                Row::Light(id) => self.render_light(frame, *area, id),
+            }
+        }
+    }
+
+    fn lights_title(&self) -> String {
+        match self.profile.as_deref() {
+            Some(profile) => format!(
+                "Lights, profile {} (Up/Down select, Left/Right fold groups, i identify, p next profile, q quit)",
+                profile
+            ),
+            None => {
+                "Lights (Up/Down select, Left/Right fold groups, i identify, q quit)".to_string()
+            }
+        }
+    }
+
+    fn row_height(row: &Row) -> u16 {
+        match row {
+            Row::Group(_) => 1,
+            Row::Light(_) => 3,
+        }
+    }
+
+    fn rows_height(&self) -> u16 {
+        self.get_rows().iter().map(Self::row_height).sum()
+    }
+
+    fn render_group(&self, frame: &mut Frame, area: ratatui::layout::Rect, group: &str) {
+        let members: Vec<&TerminalStatus> = self
+            .light_status
+            .values()
+            .filter(|status| status.group == group)
+            .collect();
+        let ready = members
+            .iter()
+            .filter(|status| status.status == ConnectionState::Ready.to_string())
+            .count();
+        let marker = if self.collapsed.contains(group) {
+            '▸'
+        } else {
+            '▾'
+        };
 
-        for (i, (_id, status)) in self.light_status.iter().enumerate() {
-            let paragraph_index = i * 2;
-            let sparkline_index = paragraph_index + 1;
+        let mut style = Style::default().add_modifier(Modifier::BOLD);
+        if self.selected == Some(Row::Group(group.to_string())) {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        let paragraph = Paragraph::new(format!(
+            "{} {}: {} lights, {} ready",
+            marker,
+            group,
+            members.len(),
+            ready
+        ))
+        .style(style);
+        frame.render_widget(paragraph, area);
+    }
 
-            let paragraph =
-                Paragraph::new(format!("{}: {}", _id, status.status)).style(status.color);
-            frame.render_widget(paragraph, light_status_layout[paragraph_index]);
+    fn render_light(&self, frame: &mut Frame, area: ratatui::layout::Rect, id: &str) {
+        let Some(status) = self.light_status.get(id) else {
+            return;
+        };
+        let lines = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1); 3])
+            .split(area);
 
-            // Adding sparkline for each light status
-            let sparkline = ratatui::widgets::Sparkline::default()
-                .data(status.event_counter.get_history().as_slices().0)
-                .style(status.color);
-            frame.render_widget(sparkline, light_status_layout[sparkline_index]);
+        let mut title = id.to_string();
+        if !status.group.is_empty() {
+            // indented under the group header
+            title.insert_str(0, "  ");
+        }
+        if !status.device.is_empty() && status.device != id {
+            title.push_str(format!(" ({})", status.device).as_str());
+        }
+        if !status.model.is_empty() {
+            title.push_str(format!(" [{}]", status.model).as_str());
+        }
+        if !status.adapter.is_empty() {
+            title.push_str(format!(" via {}", status.adapter).as_str());
+        }
+        let mut title_style = Style::default().fg(status.color);
+        if self.selected == Some(Row::Light(id.to_string())) {
+            title_style = title_style.add_modifier(Modifier::REVERSED);
         }
+        let paragraph = Paragraph::new(format!("{}: {}", title, status.status)).style(title_style);
+        frame.render_widget(paragraph, lines[0]);
+
+        let readback_color = if status.in_step {
+            Color::Reset
+        } else {
+            Color::Yellow
+        };
+        let readback = Paragraph::new(format!(
+            "  Commanded: {} | Readback: {} | Frames rx {} tx {} coalesced {} | Writes {} retried {} failed {}",
+            status.commanded,
+            status.readback,
+            status.counters.received,
+            status.counters.sent,
+            status.counters.coalesced,
+            status.write_mode,
+            status.counters.retried,
+            status.counters.failed
+        ))
+        .style(readback_color);
+        frame.render_widget(readback, lines[1]);
+
+        // Adding sparkline for each light status
+        let sparkline = ratatui::widgets::Sparkline::default()
+            .data(status.event_counter.get_history().as_slices().0)
+            .style(status.color);
+        frame.render_widget(sparkline, lines[2]);
+    }
+}
+
+/// Mirrors a status change to the log, which is all there is to see when headless. The
+/// colour is the only severity a status carries.
+fn log_status(source: &str, status: &str, color: Color) {
+    let level = match color {
+        Color::Red => log::Level::Error,
+        Color::Yellow => log::Level::Warn,
+        _ => log::Level::Info,
+    };
+    log::log!(level, "{}: {}", source, status);
+}
+
+impl Default for TerminalUi {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -0,0 +1,169 @@
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    use clap::Parser;
+
+    use crate::cli::{Cli, Command, ConfigAction, DEFAULT_CONFIG_PATH};
+    use crate::config::{Config, Overrides};
+    use crate::export::{ExportKind, GDTF_FILE_NAME};
+    use crate::scan::ScanOptions;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("sacn-neewer-lite").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_runs_by_default() {
+        let cli = parse(&[]).unwrap();
+
+        assert_eq!(*cli.get_command(), Command::Run);
+        assert_eq!(cli.config, DEFAULT_CONFIG_PATH);
+        assert_eq!(cli.log_level, log::LevelFilter::Info);
+        assert!(cli.shows_tui(false));
+        assert!(!cli.shows_tui(true));
+    }
+
+    #[test]
+    fn test_global_flags_after_subcommand() {
+        let cli = parse(&[
+            "validate",
+            "--config",
+            "/etc/sacn-neewer-lite/config.json",
+            "--headless",
+        ])
+        .unwrap();
+
+        assert_eq!(*cli.get_command(), Command::Validate { path: None });
+        assert_eq!(cli.config, "/etc/sacn-neewer-lite/config.json");
+        assert!(cli.headless);
+    }
+
+    #[test]
+    fn test_config_show() {
+        let cli = parse(&["config", "show", "--effective"]).unwrap();
+        assert_eq!(
+            *cli.get_command(),
+            Command::Config {
+                action: ConfigAction::Show { effective: true }
+            }
+        );
+        assert_eq!(
+            *parse(&["config", "show"]).unwrap().get_command(),
+            Command::Config {
+                action: ConfigAction::Show { effective: false }
+            }
+        );
+        assert!(parse(&["config"]).is_err());
+    }
+
+    #[test]
+    fn test_export_kinds() {
+        let cli = parse(&["export", "patch", "-o", "patch.html", "--scan", "2"]).unwrap();
+        assert_eq!(
+            *cli.get_command(),
+            Command::Export {
+                kind: ExportKind::Patch {
+                    format: None,
+                    output: Some("patch.html".to_string()),
+                    scan: Some(Duration::from_secs(2)),
+                }
+            }
+        );
+
+        let cli = parse(&["export", "gdtf"]).unwrap();
+        assert_eq!(
+            *cli.get_command(),
+            Command::Export {
+                kind: ExportKind::Gdtf {
+                    output: GDTF_FILE_NAME.to_string()
+                }
+            }
+        );
+        assert!(parse(&["export", "patch", "--format", "pdf"]).is_err());
+    }
+
+    #[test]
+    fn test_profile_flag_and_command() {
+        let cli = parse(&["--profile", "club"]).unwrap();
+        assert_eq!(cli.profile.as_deref(), Some("club"));
+        assert_eq!(*cli.get_command(), Command::Run);
+
+        let cli = parse(&["profile", "arena"]).unwrap();
+        assert_eq!(
+            *cli.get_command(),
+            Command::Profile {
+                name: Some("arena".to_string())
+            }
+        );
+        assert!(!cli.shows_tui(false));
+    }
+
+    #[test]
+    fn test_identify_needs_a_positive_duration() {
+        let cli = parse(&["identify", "Stage Left Key", "2.5"]).unwrap();
+        assert_eq!(
+            *cli.get_command(),
+            Command::Identify {
+                target: "Stage Left Key".to_string(),
+                duration: Some(Duration::from_millis(2500)),
+            }
+        );
+        // the bridge would refuse these, so they never leave
+        assert!(parse(&["identify", "key", "0"]).is_err());
+        assert!(parse(&["identify", "key", "-1"]).is_err());
+    }
+
+    #[test]
+    fn test_scan_options() {
+        let cli = parse(&["scan", "--duration", "2.5", "--json"]).unwrap();
+        assert_eq!(
+            *cli.get_command(),
+            Command::Scan(ScanOptions {
+                duration: Duration::from_millis(2500),
+                all: false,
+                json: true,
+            })
+        );
+
+        let Command::Scan(options) = parse(&["scan"]).unwrap().command.unwrap() else {
+            panic!("expected scan");
+        };
+        assert_eq!(options, ScanOptions::default());
+
+        assert!(parse(&["scan", "--duration"]).is_err());
+        assert!(parse(&["scan", "--duration", "soon"]).is_err());
+        assert!(parse(&["scan", "--loud"]).is_err());
+    }
+
+    #[test]
+    fn test_send_takes_the_rest_of_the_line() {
+        let cli = parse(&["send", "identify", "stage left", "3"]).unwrap();
+
+        assert_eq!(
+            *cli.get_command(),
+            Command::Send {
+                command: vec![
+                    "identify".to_string(),
+                    "stage left".to_string(),
+                    "3".to_string()
+                ]
+            }
+        );
+        assert!(parse(&["send"]).is_err());
+    }
+
+    #[test]
+    fn test_overrides_apply_to_config() {
+        let cli = parse(&["--interface", "10.0.0.5", "--headless"]).unwrap();
+        let config = cli.get_overrides().apply(Config::default());
+
+        assert_eq!(config.sacn.interface, Ipv4Addr::new(10, 0, 0, 5));
+        assert!(config.ui.headless);
+
+        let untouched = Overrides::default().apply(Config::default());
+        assert_eq!(untouched.sacn, Config::default().sacn);
+        assert!(!untouched.ui.headless);
+    }
+}
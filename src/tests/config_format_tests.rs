@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::config::Config;
+    use crate::config_format::{to_toml_template, ConfigFormat};
+
+    const JSON: &str = r#"{
+  "version": 1,
+  "sacn": { "interface": "10.0.0.5", "port": 5568, "timeout_ms": 2000 },
+  "lights": [
+    { "id": "CB:11:33:33:A3:67", "label": "Key", "universe": 1, "address": 1 },
+    {
+      "name": "NW-20220016&00000000",
+      "index": 1,
+      "universe": 2,
+      "address": 10,
+      "personality": "cct",
+      "interpolation": { "mode": "smooth", "time_constant_ms": 80 }
+    }
+  ]
+}"#;
+
+    const YAML: &str = r#"
+version: 1
+sacn:
+  interface: 10.0.0.5
+  port: 5568
+  timeout_ms: 2000
+lights:
+  # the key light
+  - id: "CB:11:33:33:A3:67"
+    label: Key
+    universe: 1
+    address: 1
+  - name: NW-20220016&00000000
+    index: 1
+    universe: 2
+    address: 10
+    personality: cct
+    interpolation:
+      mode: smooth
+      time_constant_ms: 80
+"#;
+
+    fn load(text: &str, format: ConfigFormat) -> Config {
+        Config::from_value(format.parse(text).unwrap()).unwrap()
+    }
+
+    fn assert_same(a: &Config, b: &Config) {
+        assert_eq!(a.version, b.version);
+        assert_eq!(a.sacn, b.sacn);
+        assert_eq!(a.bluetooth, b.bluetooth);
+        assert_eq!(a.ui, b.ui);
+        assert_eq!(a.lights, b.lights);
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(
+            ConfigFormat::from_path("data/config.json"),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path("/etc/neewer.TOML"),
+            ConfigFormat::Toml
+        );
+        assert_eq!(ConfigFormat::from_path("rig.yml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("rig.yaml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("config"), ConfigFormat::Json);
+    }
+
+    #[test]
+    fn test_yaml_loads_like_json() {
+        assert_same(
+            &load(YAML, ConfigFormat::Yaml),
+            &load(JSON, ConfigFormat::Json),
+        );
+    }
+
+    #[test]
+    fn test_toml_template_round_trips() {
+        let document = ConfigFormat::Json.parse(JSON).unwrap();
+
+        let template = to_toml_template(document, "config.json").unwrap();
+
+        assert!(template.contains("# UDP port"));
+        assert!(template.contains("interpolation = { mode = \"smooth\", time_constant_ms = 80 }"));
+        // only the first light leaves personality unset
+        assert_eq!(template.matches("# personality = ").count(), 1);
+        assert_same(
+            &load(&template, ConfigFormat::Toml),
+            &load(JSON, ConfigFormat::Json),
+        );
+    }
+
+    #[test]
+    fn test_toml_template_migrates_legacy_arrays() {
+        let legacy = json!([{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 }]);
+
+        let template = to_toml_template(legacy, "config.json").unwrap();
+
+        let config = load(&template, ConfigFormat::Toml);
+        assert!(!config.is_legacy());
+        assert_eq!(config.lights.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_errors_have_lines() {
+        let toml = ConfigFormat::Toml
+            .parse("version = 1\n\n[[lights]]\nid = \n")
+            .unwrap_err();
+        assert_eq!(toml.line, Some(4));
+        assert!(toml.message.starts_with("Invalid TOML"));
+
+        let yaml = ConfigFormat::Yaml
+            .parse("version: 1\nlights:\n  - id: [\n")
+            .unwrap_err();
+        assert!(yaml.line.is_some());
+        assert!(yaml.message.starts_with("Invalid YAML"));
+    }
+}
@@ -0,0 +1,244 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::Ipv4Addr;
+
+    use serde_json::json;
+
+    use crate::config::Overrides;
+    use crate::config_layers::{self, Layer, Setting};
+
+    const BASE: &str = r#"
+version = 1
+
+[sacn]
+timeout_ms = 2000
+
+[groups.wash]
+adapter = "hci0"
+
+[[lights]]
+id = "CB:11:33:33:A3:67"
+universe = 1
+address = 1
+group = "wash"
+"#;
+
+    const HOST: &str = r#"
+sacn:
+  interface: 10.0.0.5
+groups:
+  wash:
+    adapter: hci1
+"#;
+
+    fn layer(path: &str, text: &str) -> Layer {
+        Layer {
+            path: path.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    fn layers() -> Vec<Layer> {
+        vec![
+            layer("/etc/sacn-neewer-lite/config.toml", BASE),
+            layer("data/config.yaml", HOST),
+        ]
+    }
+
+    fn setting<'a>(settings: &'a [Setting], path: &str) -> &'a Setting {
+        settings
+            .iter()
+            .find(|setting| setting.path == path)
+            .unwrap_or_else(|| panic!("no setting {}", path))
+    }
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_later_layers_merge_over_earlier_ones() {
+        let layered = config_layers::merge(&layers()).unwrap();
+        let config = layered.to_config(None, &Overrides::default()).unwrap();
+
+        assert_eq!(config.sacn.interface, Ipv4Addr::new(10, 0, 0, 5));
+        assert_eq!(config.sacn.timeout_ms, 2000);
+        assert_eq!(config.lights.len(), 1);
+        assert_eq!(config.lights[0].adapter.as_deref(), Some("hci1"));
+        assert_eq!(
+            layered.sources["sacn.timeout_ms"],
+            "/etc/sacn-neewer-lite/config.toml"
+        );
+        assert_eq!(layered.sources["groups.wash.adapter"], "data/config.yaml");
+    }
+
+    #[test]
+    fn test_lists_are_replaced_whole() {
+        let local = r#"{ "lights": [
+            { "id": "CB:11:33:33:A3:68", "universe": 2, "address": 1 },
+            { "id": "CB:11:33:33:A3:69", "universe": 2, "address": 3 }
+        ] }"#;
+        let mut layers = layers();
+        layers.push(layer("local.json", local));
+
+        let layered = config_layers::merge(&layers).unwrap();
+        let config = layered.to_config(None, &Overrides::default()).unwrap();
+
+        assert_eq!(config.lights.len(), 2);
+        assert!(config.lights.iter().all(|light| light.universe == 2));
+        assert!(!layered.sources.contains_key("lights[0].group"));
+        assert_eq!(layered.sources["lights[1].address"], "local.json");
+    }
+
+    #[test]
+    fn test_effective_settings_show_their_sources() {
+        let profiled = r#"{ "profiles": { "arena": { "sacn": { "port": 6000 } } } }"#;
+        let mut layers = layers();
+        layers.push(layer("local.json", profiled));
+        let layered = config_layers::merge(&layers).unwrap();
+        let overrides = Overrides {
+            headless: true,
+            settings: config_layers::env_settings(env(&[(
+                "SACN_NEEWER_STATE_PATH",
+                "/srv/state.json",
+            )]))
+            .unwrap(),
+            ..Default::default()
+        };
+
+        let settings = layered.effective(Some("arena"), &overrides).unwrap();
+
+        let port = setting(&settings, "sacn.port");
+        assert_eq!(port.value, json!(6000));
+        assert_eq!(port.source, "local.json, profile arena");
+        // the profile only replaces the settings it sets
+        assert_eq!(
+            setting(&settings, "sacn.timeout_ms").source,
+            "/etc/sacn-neewer-lite/config.toml"
+        );
+        assert_eq!(setting(&settings, "profile").source, "--profile");
+        assert_eq!(setting(&settings, "ui.headless").source, "--headless");
+        let state_path = setting(&settings, "state.path");
+        assert_eq!(state_path.value, json!("/srv/state.json"));
+        assert_eq!(state_path.source, "SACN_NEEWER_STATE_PATH");
+        assert_eq!(
+            setting(&settings, "lights[0].id").source,
+            "/etc/sacn-neewer-lite/config.toml"
+        );
+        assert!(!settings
+            .iter()
+            .any(|setting| setting.path.starts_with("profiles")));
+
+        let shown = config_layers::format_settings(&[setting(&settings, "version").clone()]);
+        assert_eq!(shown, "version = 1  # /etc/sacn-neewer-lite/config.toml\n");
+    }
+
+    #[test]
+    fn test_env_settings_are_typed_and_checked() {
+        let settings = config_layers::env_settings(env(&[
+            ("SACN_NEEWER_SACN_PORT", "6000"),
+            ("SACN_NEEWER_UI_HEADLESS", "yes"),
+            ("SACN_NEEWER_SACN_INTERFACE", "10.0.0.7"),
+            // handled by the command line, not a setting
+            ("SACN_NEEWER_ADAPTER", "hci1"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            json!(settings),
+            json!({
+                "sacn": { "interface": "10.0.0.7", "port": 6000 },
+                "ui": { "headless": true },
+            })
+        );
+
+        let overrides = Overrides {
+            settings,
+            ..Default::default()
+        };
+        let layered = config_layers::merge(&layers()).unwrap();
+        let config = layered.to_config(None, &overrides).unwrap();
+        assert_eq!(config.sacn.port, 6000);
+        assert_eq!(config.sacn.interface, Ipv4Addr::new(10, 0, 0, 7));
+        assert!(config.ui.headless);
+        // and a profile switch keeps them
+        assert_eq!(config.overrides, overrides);
+
+        let error =
+            config_layers::env_settings(env(&[("SACN_NEEWER_SACN_PORT", "lots")])).unwrap_err();
+        assert_eq!(
+            error,
+            "SACN_NEEWER_SACN_PORT must be a whole number, not lots"
+        );
+        let error = config_layers::env_settings(env(&[("SACN_NEEWER_SACN_INTERFACE", "eth0")]))
+            .unwrap_err();
+        assert!(error.starts_with("SACN_NEEWER_SACN_INTERFACE is not a valid interface"));
+    }
+
+    #[test]
+    fn test_problems_name_the_merged_files() {
+        let mut layers = layers();
+        layers.push(layer(
+            "local.json",
+            r#"{ "lights": [{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 600 }] }"#,
+        ));
+        let problems = config_layers::check(&layers);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(
+            problems[0],
+            "/etc/sacn-neewer-lite/config.toml + data/config.yaml + local.json: \
+             CB:11:33:33:A3:67: Rgb footprint at 600-602 is outside 1-512"
+        );
+
+        let broken = vec![
+            layer("base.toml", BASE),
+            layer("host.json", "{\n  \"sacn\": \n}"),
+            layer("legacy.json", "[]"),
+        ];
+        let problems = config_layers::check(&broken);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].starts_with("host.json:3: Invalid JSON"));
+        assert!(problems[1].starts_with("legacy.json: Only a table of settings"));
+    }
+
+    #[test]
+    fn test_finds_first_config_in_each_dir() {
+        let root =
+            std::env::temp_dir().join(format!("sacn-neewer-lite-layers-{}", std::process::id()));
+        let system = root.join("system");
+        let user = root.join("user");
+        std::fs::create_dir_all(&system).unwrap();
+        std::fs::create_dir_all(&user).unwrap();
+        std::fs::write(system.join("config.toml"), BASE).unwrap();
+        std::fs::write(system.join("config.json"), "{}").unwrap();
+        let local = root.join("local.json").to_string_lossy().to_string();
+        let system_config = system.join("config.toml").to_string_lossy().to_string();
+
+        let dirs = vec![system.clone(), user.clone()];
+        // a missing local config is fine with a shared one to run on
+        assert_eq!(
+            config_layers::find_layer_paths(&dirs, &local),
+            vec![system_config.clone()]
+        );
+        std::fs::write(&local, HOST).unwrap();
+        assert_eq!(
+            config_layers::find_layer_paths(&dirs, &local),
+            vec![system_config.clone(), local.clone()]
+        );
+        // pointing --config at a shared file doesn't read it twice
+        assert_eq!(
+            config_layers::find_layer_paths(&dirs, &system_config),
+            vec![system_config.clone()]
+        );
+        assert_eq!(
+            config_layers::find_layer_paths(&[user], &local),
+            vec![local.clone()]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
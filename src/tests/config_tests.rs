@@ -0,0 +1,395 @@
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use btleplug::api::BDAddr;
+    use serde_json::json;
+
+    use crate::config::{
+        expand_light, migrate, migrate_file, Config, LightConfig, LightMatch, NameSlots, Overrides,
+        CONFIG_VERSION,
+    };
+    use crate::personality::Personality;
+
+    fn parse(json: &str) -> Result<LightConfig, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    fn addr(s: &str) -> BDAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_matches_by_id() {
+        let light = parse(r#"{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 }"#).unwrap();
+
+        assert_eq!(
+            light.matcher,
+            LightMatch::Address(addr("CB:11:33:33:A3:67"))
+        );
+        assert_eq!(light.get_label(), "CB:11:33:33:A3:67");
+    }
+
+    #[test]
+    fn test_matches_by_name_and_index() {
+        let light = parse(
+            r#"{ "name": "NEEWER-RGB660", "index": 2, "label": "Back Light", "universe": 1, "address": 1 }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            light.matcher,
+            LightMatch::Name {
+                name: "NEEWER-RGB660".to_string(),
+                index: 2
+            }
+        );
+        assert_eq!(light.get_label(), "Back Light");
+    }
+
+    #[test]
+    fn test_unlabelled_name_match_label() {
+        let first = parse(r#"{ "name": "NEEWER-RGB660", "universe": 1, "address": 1 }"#).unwrap();
+        let third =
+            parse(r#"{ "name": "NEEWER-RGB660", "index": 2, "universe": 1, "address": 1 }"#)
+                .unwrap();
+
+        assert_eq!(first.get_label(), "NEEWER-RGB660");
+        assert_eq!(third.get_label(), "NEEWER-RGB660 #2");
+    }
+
+    #[test]
+    fn test_rejects_ambiguous_or_missing_match() {
+        assert!(parse(r#"{ "universe": 1, "address": 1 }"#).is_err());
+        assert!(parse(
+            r#"{ "id": "CB:11:33:33:A3:67", "name": "NEEWER-RGB660", "universe": 1, "address": 1 }"#
+        )
+        .is_err());
+        assert!(
+            parse(r#"{ "id": "CB:11:33:33:A3:67", "index": 1, "universe": 1, "address": 1 }"#)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_name_slots_count_in_first_heard_order() {
+        let mut slots = NameSlots::default();
+        // heard in the opposite of address order
+        slots.heard("NEEWER-RGB660", addr("00:00:00:00:00:09"), "later address");
+        slots.heard(
+            "NEEWER-RGB660",
+            addr("00:00:00:00:00:01"),
+            "earlier address",
+        );
+        slots.heard("NEEWER-RGB660", addr("00:00:00:00:00:09"), "heard again");
+
+        assert_eq!(slots.pick("NEEWER-RGB660", 0), Some(&"heard again"));
+        assert_eq!(slots.pick("NEEWER-RGB660", 1), Some(&"earlier address"));
+        assert_eq!(slots.pick("NEEWER-RGB530", 0), None);
+
+        // a new address takes over the slot of one that went away
+        slots.forget_where(|address, _| address == addr("00:00:00:00:00:09"));
+        slots.heard("NEEWER-RGB660", addr("00:00:00:00:00:05"), "rotated");
+        assert_eq!(slots.pick("NEEWER-RGB660", 0), Some(&"rotated"));
+
+        let rule = LightMatch::Name {
+            name: "NEEWER-RGB660".to_string(),
+            index: 1,
+        };
+        assert!(rule.may_select(addr("00:00:00:00:00:09"), Some("NEEWER-RGB660")));
+        assert!(!rule.may_select(addr("00:00:00:00:00:09"), Some("NEEWER-RGB530")));
+    }
+
+    #[test]
+    fn test_loads_legacy_array() {
+        let config = Config::from_value(json!([
+            { "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 }
+        ]))
+        .unwrap();
+
+        assert!(config.is_legacy());
+        assert_eq!(config.lights.len(), 1);
+        assert_eq!(config.sacn.port, 5568);
+        assert_eq!(config.bluetooth.max_concurrent_connects, 2);
+    }
+
+    #[test]
+    fn test_loads_versioned_document() {
+        let config = Config::from_value(json!({
+            "version": 1,
+            "sacn": { "interface": "192.168.1.20", "timeout_ms": 2500 },
+            "ui": { "headless": true },
+            "lights": [{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 }]
+        }))
+        .unwrap();
+
+        assert!(!config.is_legacy());
+        assert_eq!(config.sacn.interface, Ipv4Addr::new(192, 168, 1, 20));
+        assert_eq!(config.sacn.port, 5568);
+        assert_eq!(config.sacn.timeout_ms, 2500);
+        assert!(config.ui.headless);
+        assert_eq!(config.ui.control_address, "127.0.0.1:7170");
+    }
+
+    #[test]
+    fn test_rejects_unknown_version() {
+        assert!(Config::from_value(json!({ "version": 2, "lights": [] })).is_err());
+        assert!(Config::from_value(json!({ "lights": [] })).is_err());
+    }
+
+    #[test]
+    fn test_rejects_short_save_interval() {
+        let with_interval = |interval: u64| {
+            Config::from_value(json!({
+                "version": 1,
+                "state": { "save_interval_ms": interval },
+                "lights": []
+            }))
+        };
+
+        let error = with_interval(0).err().unwrap().to_string();
+        assert!(error.contains("save_interval_ms must be at least 1000, not 0"));
+        assert!(with_interval(999).is_err());
+        assert_eq!(with_interval(1000).unwrap().state.save_interval_ms, 1000);
+
+        let mut config = with_interval(5000).unwrap();
+        let fields = json!({ "save_interval_ms": 0 });
+        assert!(config
+            .set_section_fields("state", fields.as_object().unwrap())
+            .is_err());
+        assert_eq!(config.state.save_interval_ms, 5000);
+    }
+
+    #[test]
+    fn test_migrate_keeps_lights_untouched() {
+        let light =
+            json!({ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1, "label": "Key" });
+
+        let migrated = migrate(json!([light.clone()])).unwrap();
+
+        assert_eq!(migrated["version"], CONFIG_VERSION);
+        assert_eq!(migrated["lights"], json!([light]));
+        assert_eq!(migrated["sacn"]["port"], 5568);
+        let config = Config::from_value(migrated.clone()).unwrap();
+        assert!(!config.is_legacy());
+
+        // already current, so left alone
+        assert_eq!(migrate(migrated.clone()).unwrap(), migrated);
+    }
+
+    #[tokio::test]
+    async fn test_migrate_file_keeps_a_backup() {
+        let dir =
+            std::env::temp_dir().join(format!("sacn-neewer-lite-migrate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json").to_string_lossy().to_string();
+        let legacy = r#"[{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 }]"#;
+        std::fs::write(&path, legacy).unwrap();
+
+        let backup = migrate_file(&path).await.unwrap().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), legacy);
+        let config = Config::from_file(&path).await.unwrap();
+        assert!(!config.is_legacy());
+        assert!(!std::path::Path::new(&format!("{}.tmp", path)).exists());
+        // already current, so nothing to do
+        assert_eq!(migrate_file(&path).await.unwrap(), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_repatch_only_moves_the_light() {
+        let light = parse(r#"{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 }"#).unwrap();
+        let moved = parse(
+            r#"{ "id": "CB:11:33:33:A3:67", "label": "Key", "universe": 2, "address": 9, "personality": "cct" }"#,
+        )
+        .unwrap();
+        let reframed = parse(
+            r#"{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1, "protocol": "infinity" }"#,
+        )
+        .unwrap();
+        let other = parse(r#"{ "id": "11:22:33:44:55:66", "universe": 1, "address": 1 }"#).unwrap();
+
+        assert!(light.is_repatch_of(&light));
+        assert!(light.is_repatch_of(&moved));
+        assert!(!light.is_repatch_of(&reframed));
+        assert!(!light.is_repatch_of(&other));
+    }
+
+    #[test]
+    fn test_lights_inherit_group_settings() {
+        let config = Config::from_value(json!({
+            "version": CONFIG_VERSION,
+            "groups": {
+                "wash": { "universe": 3, "personality": "cct", "adapter": "hci1" }
+            },
+            "lights": [
+                { "id": "CB:11:33:33:A3:67", "group": "wash", "address": 1 },
+                { "id": "11:22:33:44:55:66", "group": "wash", "address": 10, "universe": 4 }
+            ]
+        }))
+        .unwrap();
+
+        let first = &config.lights[0];
+        assert_eq!(first.group.as_deref(), Some("wash"));
+        assert_eq!(first.universe, 3);
+        assert_eq!(first.personality, Personality::Cct);
+        assert_eq!(first.adapter.as_deref(), Some("hci1"));
+        // the light's own setting wins
+        assert_eq!(config.lights[1].universe, 4);
+    }
+
+    #[test]
+    fn test_range_shorthand_patches_consecutive_addresses() {
+        let groups = serde_json::Map::new();
+
+        let by_ids = expand_light(
+            &json!({
+                "ids": ["CB:11:33:33:A3:67", "11:22:33:44:55:66"],
+                "label": "Key",
+                "universe": 1,
+                "address": 1
+            }),
+            &groups,
+        )
+        .unwrap();
+        assert_eq!(by_ids.len(), 2);
+        assert_eq!(by_ids[1]["id"], "11:22:33:44:55:66");
+        assert_eq!(by_ids[1]["address"], 4);
+        assert_eq!(by_ids[1]["label"], "Key 2");
+
+        let by_count = expand_light(
+            &json!({
+                "name": "NEEWER-RGB660",
+                "index": 1,
+                "count": 3,
+                "personality": "cct",
+                "spacing": 5,
+                "universe": 1,
+                "address": 101
+            }),
+            &groups,
+        )
+        .unwrap();
+        let lights: Vec<LightConfig> = by_count
+            .into_iter()
+            .map(|light| serde_json::from_value(light).unwrap())
+            .collect();
+        assert_eq!(
+            lights.iter().map(|light| light.address).collect::<Vec<_>>(),
+            vec![101, 106, 111]
+        );
+        assert_eq!(
+            lights[2].matcher,
+            LightMatch::Name {
+                name: "NEEWER-RGB660".to_string(),
+                index: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_rejects_bad_groups_and_ranges() {
+        let groups = json!({ "wash": { "label": "Wash" }, "odd": 3 });
+        let groups = groups.as_object().unwrap();
+        let light = |entry: serde_json::Value| expand_light(&entry, groups).unwrap_err();
+
+        assert_eq!(
+            light(json!({ "id": "CB:11:33:33:A3:67", "group": "spots" })),
+            "Unknown group spots"
+        );
+        assert_eq!(
+            light(json!({ "id": "CB:11:33:33:A3:67", "group": "wash" })),
+            "Group wash can't set label, it belongs on each light"
+        );
+        assert_eq!(
+            light(json!({ "id": "CB:11:33:33:A3:67", "group": "odd" })),
+            "Group odd must be a table of settings"
+        );
+        assert_eq!(
+            light(json!({ "ids": [], "address": 1 })),
+            "ids or count must give at least one light"
+        );
+        assert_eq!(
+            light(json!({ "id": "CB:11:33:33:A3:67", "spacing": 4 })),
+            "spacing only applies with ids or count"
+        );
+        assert_eq!(
+            light(json!({ "name": "NEEWER-RGB660", "ids": ["CB:11:33:33:A3:67"] })),
+            "ids replaces id and name"
+        );
+        assert_eq!(
+            light(json!({ "count": 2 })),
+            "count needs a name to number the lights by"
+        );
+        // 3-channel lights from 501 only have room for four
+        assert_eq!(
+            light(json!({ "name": "NEEWER-RGB660", "count": 5, "address": 501 })),
+            "count 5 is more than the 4 light(s) that fit in the universe"
+        );
+        assert_eq!(
+            light(json!({ "name": "NEEWER-RGB660", "count": 4_000_000_000u64, "address": 1 })),
+            "count 4000000000 is more than the 170 light(s) that fit in the universe"
+        );
+    }
+
+    fn touring() -> serde_json::Value {
+        json!({
+            "version": CONFIG_VERSION,
+            "profile": "club",
+            "sacn": { "timeout_ms": 2000 },
+            "profiles": {
+                "club": {
+                    "lights": [{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 }]
+                },
+                "arena": {
+                    "sacn": { "port": 5569 },
+                    "lights": [{ "id": "CB:11:33:33:A3:67", "universe": 7, "address": 100 }]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_profile_merges_over_top_level_settings() {
+        let club = Config::from_value(touring()).unwrap();
+        assert_eq!(club.profile.as_deref(), Some("club"));
+        assert_eq!(club.lights[0].universe, 1);
+        assert_eq!(club.sacn.port, 5568);
+        assert_eq!(club.get_profile_names(), vec!["club", "arena"]);
+
+        let arena = Config::from_value_with_profile(touring(), Some("arena")).unwrap();
+        assert_eq!(arena.profile.as_deref(), Some("arena"));
+        assert_eq!(arena.lights[0].universe, 7);
+        assert_eq!(arena.sacn.port, 5569);
+        // the rest of the section is left as it was
+        assert_eq!(arena.sacn.timeout_ms, 2000);
+
+        let error = Config::from_value_with_profile(touring(), Some("pub"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Unknown profile pub, expected one of club, arena"
+        );
+    }
+
+    #[test]
+    fn test_switching_profile_keeps_overrides() {
+        let overrides = Overrides {
+            interface: Some(Ipv4Addr::new(10, 0, 0, 2)),
+            headless: true,
+            ..Default::default()
+        };
+        let club = overrides.apply(Config::from_value(touring()).unwrap());
+
+        let arena = club.with_profile("arena").unwrap();
+
+        assert_eq!(arena.lights[0].address, 100);
+        assert_eq!(arena.sacn.interface, Ipv4Addr::new(10, 0, 0, 2));
+        assert!(arena.ui.headless);
+        // and back again from the switched config
+        assert_eq!(arena.with_profile("club").unwrap().lights[0].address, 1);
+    }
+}
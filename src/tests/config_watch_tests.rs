@@ -0,0 +1,121 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ratatui::style::Color;
+    use tokio::sync::RwLock;
+
+    use crate::config::{Config, Overrides};
+    use crate::config_layers;
+    use crate::config_watch;
+    use crate::light_controller::LightController;
+    use crate::terminal_ui::TerminalUi;
+
+    const POLL: Duration = Duration::from_millis(20);
+
+    const ONE_LIGHT: &str = r#"{
+  "version": 1,
+  "lights": [
+    { "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 }
+  ]
+}"#;
+
+    fn temp_config(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "sacn-neewer-lite-{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    async fn wait_for_status(terminal: &RwLock<TerminalUi>, condition: impl Fn(&str) -> bool) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !condition(terminal.read().await.get_app_status().status.as_str()) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("app status not updated in time");
+    }
+
+    #[tokio::test]
+    async fn test_applies_changed_config() {
+        let path = temp_config("reload", ONE_LIGHT);
+        let config = Config::from_file(&path).await.unwrap();
+        let controller = LightController::with_sacn_client(&config, vec![], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let overrides = Overrides::default();
+
+        tokio::select! {
+            _ = config_watch::watch(std::slice::from_ref(&path), &overrides, POLL, &controller, &terminal) => panic!("watch exited"),
+            _ = async {
+                tokio::time::sleep(POLL * 2).await;
+                std::fs::write(&path, ONE_LIGHT.replace("\"address\": 1", "\"address\": 7")).unwrap();
+                wait_for_status(&terminal, |status| status.starts_with("Reloaded")).await;
+            } => {},
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let lock = terminal.read().await;
+        assert_eq!(
+            lock.get_app_status().status,
+            "Reloaded: 0 added, 0 removed, 1 repatched"
+        );
+        assert_eq!(lock.get_app_status().color, Color::Green);
+        assert_eq!(controller.get_lights()[0].get_address(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_reports_invalid_config_and_keeps_running_patch() {
+        let path = temp_config("invalid", ONE_LIGHT);
+        let config = Config::from_file(&path).await.unwrap();
+        let controller = LightController::with_sacn_client(&config, vec![], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let overrides = Overrides::default();
+
+        tokio::select! {
+            _ = config_watch::watch(std::slice::from_ref(&path), &overrides, POLL, &controller, &terminal) => panic!("watch exited"),
+            _ = async {
+                tokio::time::sleep(POLL * 2).await;
+                std::fs::write(&path, ONE_LIGHT.replace("\"address\": 1", "\"address\": 600")).unwrap();
+                wait_for_status(&terminal, |status| status.starts_with("Reload of")).await;
+            } => {},
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        let lock = terminal.read().await;
+        let status = lock.get_app_status();
+        assert_eq!(status.color, Color::Red);
+        assert!(status.status.contains(&format!("{}:4:", path)));
+        assert_eq!(controller.get_lights()[0].get_address(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reloads_when_an_override_layer_changes() {
+        let base = temp_config("base", ONE_LIGHT);
+        let host = temp_config("host", r#"{ "sacn": { "timeout_ms": 2000 } }"#);
+        let paths = vec![base.clone(), host.clone()];
+        let config = config_layers::load(&paths, None, &Overrides::default())
+            .await
+            .unwrap();
+        let controller = LightController::with_sacn_client(&config, vec![], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let overrides = Overrides::default();
+
+        tokio::select! {
+            _ = config_watch::watch(&paths, &overrides, POLL, &controller, &terminal) => panic!("watch exited"),
+            _ = async {
+                tokio::time::sleep(POLL * 2).await;
+                let moved = r#"{ "lights": [{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 9 }] }"#;
+                std::fs::write(&host, moved).unwrap();
+                wait_for_status(&terminal, |status| status.starts_with("Reloaded")).await;
+            } => {},
+        }
+        std::fs::remove_file(&base).unwrap();
+        std::fs::remove_file(&host).unwrap();
+
+        assert_eq!(controller.get_lights()[0].get_address(), 9);
+    }
+}
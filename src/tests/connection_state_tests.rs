@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::connection_state::{Backoff, ConnectionState};
+
+    #[test]
+    fn test_backoff_doubles_within_jitter() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut backoff = Backoff::with_limits(Duration::from_millis(100), Duration::from_secs(10));
+
+        for window_ms in [100, 200, 400, 800] {
+            let delay = backoff.next_delay(&mut rng);
+            assert!(delay >= Duration::from_millis(window_ms / 2));
+            assert!(delay <= Duration::from_millis(window_ms));
+        }
+        assert_eq!(backoff.get_failures(), 4);
+    }
+
+    #[test]
+    fn test_backoff_capped() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut backoff = Backoff::with_limits(Duration::from_millis(100), Duration::from_secs(1));
+
+        for _ in 0..40 {
+            assert!(backoff.next_delay(&mut rng) <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn test_backoff_reset() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut backoff = Backoff::with_limits(Duration::from_millis(100), Duration::from_secs(10));
+        backoff.next_delay(&mut rng);
+        backoff.next_delay(&mut rng);
+
+        backoff.reset();
+
+        assert_eq!(backoff.get_failures(), 0);
+        assert!(backoff.next_delay(&mut rng) <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_backoff_status_includes_reason() {
+        let state = ConnectionState::Backoff {
+            delay: Duration::from_millis(1500),
+            reason: "Failed to connect: DeviceNotFound".to_string(),
+        };
+
+        assert_eq!(
+            state.to_string(),
+            "Retrying in 1.5s after Failed to connect: DeviceNotFound"
+        );
+    }
+}
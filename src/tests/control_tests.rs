@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::net::TcpListener;
+    use tokio::sync::RwLock;
+
+    use crate::control::{self, ControlCommand};
+    use crate::identify::DEFAULT_IDENTIFY_DURATION;
+    use crate::light_controller::LightController;
+    use crate::terminal_ui::TerminalUi;
+    use crate::tests::{config, LIGHT_ID};
+    use crate::transport::mock::MockTransport;
+
+    #[test]
+    fn test_parse_identify() {
+        assert_eq!(
+            ControlCommand::parse("identify CB:11:33:33:A3:67"),
+            Ok(ControlCommand::Identify {
+                target: LIGHT_ID.to_string(),
+                duration: DEFAULT_IDENTIFY_DURATION,
+            })
+        );
+        assert_eq!(
+            ControlCommand::parse("identify CB:11:33:33:A3:67 2.5"),
+            Ok(ControlCommand::Identify {
+                target: LIGHT_ID.to_string(),
+                duration: Duration::from_millis(2500),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        assert_eq!(
+            ControlCommand::parse("profile club"),
+            Ok(ControlCommand::Profile {
+                name: Some("club".to_string())
+            })
+        );
+        assert_eq!(
+            ControlCommand::parse("profile"),
+            Ok(ControlCommand::Profile { name: None })
+        );
+        assert_eq!(
+            ControlCommand::parse("profile club").unwrap().to_string(),
+            "profile club"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_commands() {
+        assert!(ControlCommand::parse("").is_err());
+        assert!(ControlCommand::parse("identify").is_err());
+        assert!(ControlCommand::parse("identify CB:11:33:33:A3:67 -1").is_err());
+        assert!(ControlCommand::parse("identify CB:11:33:33:A3:67 0").is_err());
+        assert!(ControlCommand::parse("explode everything").is_err());
+    }
+
+    #[test]
+    fn test_command_round_trips_through_display() {
+        let command = ControlCommand::Identify {
+            target: LIGHT_ID.to_string(),
+            duration: Duration::from_secs(3),
+        };
+        assert_eq!(ControlCommand::parse(&command.to_string()), Ok(command));
+    }
+
+    #[test]
+    fn test_spaced_names_round_trip() {
+        let commands = [
+            ControlCommand::Identify {
+                target: "Stage Left Key".to_string(),
+                duration: Duration::from_secs(5),
+            },
+            // a default label, and a group
+            ControlCommand::Identify {
+                target: "NW-RGB660 #1".to_string(),
+                duration: Duration::from_secs(5),
+            },
+            ControlCommand::Identify {
+                target: "Back \"wash\" \\ truss".to_string(),
+                duration: Duration::from_secs(5),
+            },
+            ControlCommand::Profile {
+                name: Some("Late Show".to_string()),
+            },
+        ];
+        for command in commands {
+            assert_eq!(ControlCommand::parse(&command.to_string()), Ok(command));
+        }
+
+        assert_eq!(
+            ControlCommand::parse("identify \"Stage Left Key\" 5")
+                .unwrap()
+                .to_string(),
+            "identify \"Stage Left Key\" 5"
+        );
+        assert_eq!(
+            ControlCommand::parse("profile \"Late Show\""),
+            Ok(ControlCommand::Profile {
+                name: Some("Late Show".to_string())
+            })
+        );
+        assert_eq!(
+            ControlCommand::from_words(&["identify".to_string(), "Stage Left Key".to_string()]),
+            Ok(ControlCommand::Identify {
+                target: "Stage Left Key".to_string(),
+                duration: DEFAULT_IDENTIFY_DURATION,
+            })
+        );
+        assert!(ControlCommand::parse("identify \"Stage Left Key").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_identify_over_tcp() {
+        let transport = Arc::new(MockTransport::new());
+        let controller = LightController::with_sacn_client(&config(), vec![transport], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let (known, unknown) = tokio::select! {
+            _ = control::serve_listener(listener, &controller, &terminal) => panic!("server exited"),
+            replies = async {
+                let known = control::send(
+                    &address,
+                    &ControlCommand::parse("identify cb:11:33:33:a3:67").unwrap(),
+                )
+                .await
+                .unwrap();
+                let unknown = control::send(
+                    &address,
+                    &ControlCommand::parse("identify 00:00:00:00:00:01").unwrap(),
+                )
+                .await
+                .unwrap_err()
+                .to_string();
+                (known, unknown)
+            } => replies,
+        };
+
+        assert!(known.starts_with("Identifying CB:11:33:33:A3:67"));
+        assert_eq!(unknown, "No light matching 00:00:00:00:00:01");
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use serde_json::json;
+
+    use crate::config::Config;
+    use crate::export::{gdtf_archive, patch_rows, to_csv, to_html, to_qxf};
+    use crate::personality::Personality;
+    use crate::scan::ScanResult;
+    use crate::tests::config_with;
+
+    fn config() -> Config {
+        config_with(&[
+            json!({ "label": "Key, left", "universe": 2 }),
+            json!({ "name": "NEEWER-RGB660", "index": 1, "address": 10, "personality": "cct" }),
+        ])
+    }
+
+    fn heard(address: &str, name: &str) -> ScanResult {
+        ScanResult {
+            address: address.parse().unwrap(),
+            name: Some(name.to_string()),
+            rssi: Some(-60),
+            model: Some("RGB660".to_string()),
+            adapter: "hci0".to_string(),
+            configured: false,
+            by_name: true,
+        }
+    }
+
+    #[test]
+    fn test_patch_sheet_rows() {
+        let rows = patch_rows(&config(), &[]);
+
+        // in universe then address order
+        assert_eq!(rows[0].label, "NEEWER-RGB660 #1");
+        assert_eq!(rows[0].mac, None);
+        assert_eq!(rows[0].model.as_deref(), Some("RGB660"));
+        assert_eq!(rows[0].footprint, 2);
+        assert_eq!(rows[0].personality, Personality::Cct);
+        assert_eq!(rows[1].mac, Some("CB:11:33:33:A3:67".parse().unwrap()));
+        assert_eq!(rows[1].model, None);
+
+        // a scan can't tell which of these the bridge heard first, so names neither
+        let scanned = [
+            heard("5A:00:00:00:00:02", "NEEWER-RGB660"),
+            heard("5A:00:00:00:00:01", "NEEWER-RGB660"),
+        ];
+        let rows = patch_rows(&config(), &scanned);
+        assert_eq!(rows[0].mac, None);
+        assert_eq!(rows[0].model.as_deref(), Some("RGB660"));
+    }
+
+    #[test]
+    fn test_csv_and_html_escape_fields() {
+        let rows = patch_rows(&config(), &[]);
+
+        assert_eq!(
+            to_csv(&rows),
+            "Label,MAC,Model,Universe,Address,Footprint,Personality\n\
+             NEEWER-RGB660 #1,\"first-heard order, unresolved\",RGB660,1,10,2,CCT\n\
+             \"Key, left\",CB:11:33:33:A3:67,,2,1,3,RGB\n"
+        );
+        let html = to_html(&rows, "Patch <club>");
+        assert!(html.contains("<title>Patch &lt;club&gt;</title>"));
+        assert!(html.contains("<td>Key, left</td><td>CB:11:33:33:A3:67</td>"));
+    }
+
+    #[test]
+    fn test_fixture_definitions_have_a_mode_per_personality() {
+        let qxf = to_qxf();
+        assert!(qxf.contains("<Mode Name=\"RGB\">"));
+        assert!(qxf.contains("<Mode Name=\"CCT\">"));
+        assert!(qxf.contains("<Channel Number=\"1\">Colour Temperature</Channel>"));
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(gdtf_archive().unwrap()))
+            .expect("a .gdtf is a zip");
+        let mut description = String::new();
+        archive
+            .by_name("description.xml")
+            .unwrap()
+            .read_to_string(&mut description)
+            .unwrap();
+        assert_eq!(description.matches("<DMXMode ").count(), 2);
+        assert!(description.contains("Attribute=\"CTC\""));
+        assert!(description.contains("PhysicalFrom=\"3200\" PhysicalTo=\"5600\""));
+    }
+}
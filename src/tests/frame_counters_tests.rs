@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use crate::frame_counters::FrameCounters;
+
+    #[test]
+    fn test_unchanged_frames_only_received() {
+        let mut counters = FrameCounters::new();
+
+        counters.record_received(false);
+        counters.record_received(false);
+
+        assert_eq!(counters.received, 2);
+        assert_eq!(counters.coalesced, 0);
+    }
+
+    #[test]
+    fn test_change_replaced_before_send_is_coalesced() {
+        let mut counters = FrameCounters::new();
+
+        counters.record_received(true);
+        counters.record_received(true);
+        counters.record_received(true);
+        counters.record_sent();
+
+        assert_eq!(counters.received, 3);
+        assert_eq!(counters.sent, 1);
+        assert_eq!(counters.coalesced, 2);
+    }
+
+    #[test]
+    fn test_sent_change_is_not_coalesced() {
+        let mut counters = FrameCounters::new();
+
+        counters.record_received(true);
+        counters.record_sent();
+        counters.record_received(true);
+
+        assert_eq!(counters.coalesced, 0);
+    }
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use crate::identify::Identify;
+
+    #[test]
+    fn test_flashes_on_then_off() {
+        let start = Instant::now();
+        let identify = Identify::new(start, Duration::from_secs(2));
+
+        assert!(identify.is_lit(start));
+        assert!(identify.is_lit(start + Duration::from_millis(249)));
+        assert!(!identify.is_lit(start + Duration::from_millis(250)));
+        assert!(identify.is_lit(start + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_ends_after_duration() {
+        let start = Instant::now();
+        let identify = Identify::new(start, Duration::from_secs(2));
+
+        assert!(!identify.is_over(start + Duration::from_millis(1999)));
+        assert!(identify.is_over(start + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_next_change() {
+        let start = Instant::now();
+        let identify = Identify::new(start, Duration::from_millis(600));
+
+        assert_eq!(identify.next_change(start - Duration::from_secs(1)), start);
+        assert_eq!(
+            identify.next_change(start + Duration::from_millis(10)),
+            start + Duration::from_millis(250)
+        );
+        assert_eq!(
+            identify.next_change(start + Duration::from_millis(250)),
+            start + Duration::from_millis(500)
+        );
+        // the last change is the end, not a full period later
+        assert_eq!(
+            identify.next_change(start + Duration::from_millis(500)),
+            start + Duration::from_millis(600)
+        );
+    }
+
+    #[test]
+    fn test_next_write_waits_for_the_flash_to_change() {
+        let start = Instant::now();
+        let mut identify = Identify::new(start, Duration::from_secs(2));
+
+        assert_eq!(identify.next_write(), start);
+        identify.record_write(start);
+        assert_eq!(identify.next_write(), start + Duration::from_millis(250));
+    }
+}
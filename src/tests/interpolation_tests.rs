@@ -0,0 +1,127 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use crate::color::Color;
+    use crate::interpolation::{Interpolation, Interpolator};
+    use crate::light_state::LightState;
+
+    fn rgb(red: u8, green: u8, blue: u8) -> LightState {
+        LightState::Rgb(Color::new(red, green, blue))
+    }
+
+    fn red(state: LightState) -> u8 {
+        match state {
+            LightState::Rgb(color) => color.red,
+            _ => panic!("expected an RGB state"),
+        }
+    }
+
+    #[test]
+    fn test_snap_jumps_to_target() {
+        let start = Instant::now();
+        let mut interpolator = Interpolator::new(Interpolation::Snap, rgb(0, 0, 0));
+
+        interpolator.set_target(rgb(200, 100, 50), start);
+
+        assert_eq!(interpolator.sample(start), rgb(200, 100, 50));
+        assert!(interpolator.is_settled());
+    }
+
+    #[test]
+    fn test_smooth_eases_towards_target() {
+        let start = Instant::now();
+        let mode = Interpolation::Smooth {
+            time_constant_ms: 100,
+        };
+        let mut interpolator = Interpolator::new(mode, rgb(0, 0, 0));
+
+        interpolator.set_target(rgb(200, 0, 0), start);
+
+        let first = red(interpolator.sample(start + Duration::from_millis(50)));
+        assert!(first > 0 && first < 200, "got {}", first);
+        assert!(!interpolator.is_settled());
+
+        let second = red(interpolator.sample(start + Duration::from_millis(100)));
+        assert!(second > first, "{} should be past {}", second, first);
+
+        assert_eq!(
+            interpolator.sample(start + Duration::from_secs(2)),
+            rgb(200, 0, 0)
+        );
+        assert!(interpolator.is_settled());
+    }
+
+    #[test]
+    fn test_snap_above_threshold() {
+        let start = Instant::now();
+        let mode = Interpolation::SnapAbove {
+            threshold: 50,
+            time_constant_ms: 100,
+        };
+        let mut interpolator = Interpolator::new(mode, rgb(0, 0, 0));
+
+        interpolator.set_target(rgb(30, 0, 0), start);
+        let eased = red(interpolator.sample(start + Duration::from_millis(20)));
+        assert!(eased < 30, "small moves should ease, got {}", eased);
+
+        interpolator.set_target(rgb(250, 0, 0), start + Duration::from_millis(40));
+        assert_eq!(
+            interpolator.sample(start + Duration::from_millis(40)),
+            rgb(250, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_personality_change_snaps() {
+        let start = Instant::now();
+        let mode = Interpolation::Smooth {
+            time_constant_ms: 100,
+        };
+        let mut interpolator = Interpolator::new(mode, rgb(0, 0, 0));
+
+        let cct = LightState::Cct {
+            dimmer: 80,
+            temperature: 40,
+        };
+        interpolator.set_target(cct, start);
+
+        assert_eq!(interpolator.sample(start), cct);
+    }
+
+    #[test]
+    fn test_prediction_follows_steady_fade() {
+        let start = Instant::now();
+        let frame = Duration::from_millis(100);
+        let mode = Interpolation::Smooth {
+            time_constant_ms: 10,
+        };
+        let mut interpolator = Interpolator::new(mode, rgb(100, 0, 0));
+
+        interpolator.set_target(rgb(100, 0, 0), start);
+        interpolator.set_target(rgb(120, 0, 0), start + frame);
+
+        // halfway to the next frame, the fade should carry on past the last value
+        let ahead = red(interpolator.sample(start + frame + frame / 2));
+        assert!(ahead > 120 && ahead <= 130, "got {}", ahead);
+
+        // once the next frame is overdue the desk has stopped, so it settles on the last value
+        let settled = red(interpolator.sample(start + frame * 5));
+        assert_eq!(settled, 120);
+        assert!(interpolator.is_settled());
+    }
+
+    #[test]
+    fn test_linear_is_read_as_smooth() {
+        let mode: Interpolation =
+            serde_json::from_str(r#"{ "mode": "linear", "time_constant_ms": 80 }"#).unwrap();
+        assert_eq!(
+            mode,
+            Interpolation::Smooth {
+                time_constant_ms: 80
+            }
+        );
+    }
+}
@@ -0,0 +1,758 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use btleplug::api::{BDAddr, WriteType};
+    use serde_json::json;
+    use tokio::sync::RwLock;
+
+    use crate::config::Config;
+    use crate::control::ControlCommand;
+    use crate::light::Light;
+    use crate::light_controller::{LightController, ReloadSummary};
+    use crate::model::ModelInfo;
+    use crate::protocol::Protocol;
+    use crate::sacn_packet::SacnDmxPacket;
+    use crate::terminal_ui::TerminalUi;
+    use crate::tests::{config, config_with, LIGHT_ID};
+    use crate::transport::mock::{MockLink, MockTransport};
+    use crate::transport::{BleTransport, LightLink};
+
+    fn packet(red: u8, green: u8, blue: u8) -> SacnDmxPacket {
+        let mut dmx_data = vec![0; 513];
+        dmx_data[1] = red;
+        dmx_data[2] = green;
+        dmx_data[3] = blue;
+        SacnDmxPacket::new("test".to_string(), 1, 100, 0, 0, dmx_data, [0; 16])
+    }
+
+    fn hsi_writes(link: &MockLink) -> Vec<Vec<u8>> {
+        link.writes()
+            .into_iter()
+            .map(|w| w.data)
+            .filter(|data| data[1] == 0x86)
+            .collect()
+    }
+
+    fn power_query() -> Vec<u8> {
+        let [power, _, _] = Protocol::Legacy.status_queries().unwrap();
+        power
+    }
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(30), async {
+            while !condition() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("condition not met in time");
+    }
+
+    async fn with_running_controller<F>(transport: Arc<MockTransport>, test: F)
+    where
+        F: std::future::Future<Output = ()>,
+    {
+        let controller = LightController::with_sacn_client(&config(), vec![transport], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = test => {},
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sends_color_after_packet() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let controller =
+            LightController::with_sacn_client(&config(), vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| !hsi_writes(&link).is_empty()).await;
+                controller.handle_packet(&packet(255, 0, 0)).await.unwrap();
+                wait_for(|| hsi_writes(&link).len() == 2).await;
+            } => {},
+        }
+
+        let expected = Protocol::Legacy.hsi_command(BDAddr::default(), 0, 100, 100);
+        assert_eq!(hsi_writes(&link)[1], expected);
+    }
+
+    #[tokio::test]
+    async fn test_retries_failed_connects() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        link.fail_next_connects(2);
+
+        with_running_controller(transport.clone(), async {
+            wait_for(|| !hsi_writes(&link).is_empty()).await;
+        })
+        .await;
+
+        assert_eq!(link.connect_attempts(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_after_disconnect() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+
+        with_running_controller(transport.clone(), async {
+            wait_for(|| hsi_writes(&link).len() == 1).await;
+            link.simulate_disconnect();
+            wait_for(|| link.connect_attempts() == 2).await;
+            wait_for(|| hsi_writes(&link).len() == 2).await;
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_queries_status_after_connect() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+
+        with_running_controller(transport.clone(), async {
+            wait_for(|| link.writes().iter().any(|w| w.data == power_query())).await;
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_infinity_lights_are_not_sent_legacy_queries() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-TL60");
+        let controller =
+            LightController::with_sacn_client(&config(), vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| !link.writes().is_empty()).await;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            } => {},
+        }
+
+        assert!(link.writes().iter().all(|w| w.data[1] == 0x8F));
+        assert_eq!(
+            terminal
+                .read()
+                .await
+                .get_light_status(LIGHT_ID)
+                .unwrap()
+                .readback,
+            "Not supported for Infinity lights"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_survives_adapter_errors() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let controller = LightController::with_sacn_client(&config(), vec![transport], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let mut dmx_data = vec![0; 513];
+        dmx_data[1] = 255;
+        let red = SacnDmxPacket::new("test".to_string(), 1, 100, 0, 0, dmx_data, [0; 16]);
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| !hsi_writes(&link).is_empty()).await;
+                // the failed check counts as a lost link, and the light carries on
+                link.fail_next_adapter_calls(1);
+                controller.handle_packet(&red).await.unwrap();
+                wait_for(|| hsi_writes(&link).len() >= 2).await;
+            } => {},
+        }
+
+        link.fail_next_adapter_calls(1);
+        controller.disconnect(&terminal).await;
+        let lock = terminal.read().await;
+        let status = lock.get_light_status(LIGHT_ID).unwrap();
+        assert!(status.status.starts_with("Failed to disconnect"));
+        assert_eq!(status.color, ratatui::style::Color::Red);
+    }
+
+    #[tokio::test]
+    async fn test_reports_missing_adapter_without_panicking() {
+        let controller = LightController::with_sacn_client(&config(), vec![], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = tokio::time::sleep(Duration::from_millis(50)) => {},
+        }
+
+        let lock = terminal.read().await;
+        let status = lock.get_light_status(LIGHT_ID).unwrap();
+        assert_eq!(status.status, "No Bluetooth adapter");
+        assert_eq!(status.color, ratatui::style::Color::Red);
+    }
+
+    #[tokio::test]
+    async fn test_connects_light_discovered_later() {
+        let transport = Arc::new(MockTransport::new());
+        let other = transport.add_light("11:22:33:44:55:66".parse().unwrap(), "NEEWER-RGB660");
+
+        let late_transport = transport.clone();
+        with_running_controller(transport.clone(), async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let link = late_transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+            wait_for(|| !hsi_writes(&link).is_empty()).await;
+        })
+        .await;
+
+        assert_eq!(other.connect_attempts(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_balances_lights_across_adapters() {
+        let config = config_with(&[
+            json!({ "adapter": "mock0" }),
+            json!({ "id": "CB:11:33:33:A3:68", "address": 4, "adapter": "mock0" }),
+            json!({ "id": "CB:11:33:33:A3:69", "address": 7 }),
+            json!({ "id": "CB:11:33:33:A3:6A", "address": 10 }),
+            json!({ "id": "CB:11:33:33:A3:6B", "address": 13, "adapter": "hci9" }),
+        ]);
+        let transports: Vec<Arc<dyn BleTransport>> = vec![
+            Arc::new(MockTransport::named("mock0")),
+            Arc::new(MockTransport::named("mock1")),
+        ];
+
+        let controller = LightController::with_sacn_client(&config, transports, None);
+
+        assert_eq!(controller.get_adapter_name(0), Some("mock0"));
+        assert_eq!(controller.get_adapter_name(1), Some("mock0"));
+        assert_eq!(controller.get_adapter_name(2), Some("mock1"));
+        assert_eq!(controller.get_adapter_name(3), Some("mock1"));
+        assert_eq!(controller.get_adapter_name(4), None);
+    }
+
+    #[tokio::test]
+    async fn test_connects_through_assigned_adapter() {
+        let first = Arc::new(MockTransport::named("mock0"));
+        let second = Arc::new(MockTransport::named("mock1"));
+        let first_link = first.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let second_link = second.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let config = config_with(&[json!({ "adapter": "MOCK1" })]);
+        let controller = LightController::with_sacn_client(&config, vec![first, second], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = wait_for(|| !hsi_writes(&second_link).is_empty()) => {},
+        }
+
+        assert_eq!(first_link.connect_attempts(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_limits_concurrent_connects() {
+        let config = config_with(&[
+            json!({}),
+            json!({ "id": "CB:11:33:33:A3:68", "address": 4 }),
+            json!({ "id": "CB:11:33:33:A3:69", "address": 7 }),
+            json!({ "id": "CB:11:33:33:A3:6A", "address": 10 }),
+        ]);
+        let transport = Arc::new(MockTransport::new());
+        let links: Vec<_> = config
+            .lights
+            .iter()
+            .map(|light| {
+                let link =
+                    transport.add_light(light.matcher.get_address().unwrap(), "NEEWER-RGB660");
+                link.set_connect_delay(Duration::from_millis(50));
+                link
+            })
+            .collect();
+        let controller = LightController::with_sacn_client(&config, vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = wait_for(|| links.iter().all(|link| !hsi_writes(link).is_empty())) => {},
+        }
+
+        assert_eq!(transport.peak_concurrent_connects(), 2);
+    }
+
+    fn hsi_write_times(link: &MockLink) -> Vec<tokio::time::Instant> {
+        link.writes()
+            .into_iter()
+            .filter(|w| w.data[1] == 0x86)
+            .map(|w| w.at)
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_resends_on_keepalive() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+
+        with_running_controller(transport.clone(), async {
+            wait_for(|| hsi_writes(&link).len() == 2).await;
+        })
+        .await;
+
+        let times = hsi_write_times(&link);
+        assert!(times[1] - times[0] >= Duration::from_secs(10));
+        assert_eq!(hsi_writes(&link)[0], hsi_writes(&link)[1]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_respects_min_write_interval() {
+        let config = config_with(&[json!({ "min_write_interval_ms": 200 })]);
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let controller = LightController::with_sacn_client(&config, vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| hsi_writes(&link).len() == 1).await;
+                controller.handle_packet(&packet(255, 0, 0)).await.unwrap();
+                wait_for(|| hsi_writes(&link).len() == 2).await;
+                controller.handle_packet(&packet(0, 255, 0)).await.unwrap();
+                controller.handle_packet(&packet(0, 0, 255)).await.unwrap();
+                wait_for(|| hsi_writes(&link).len() == 3).await;
+            } => {},
+        }
+
+        let times = hsi_write_times(&link);
+        assert!(times[1] - times[0] >= Duration::from_millis(200));
+        assert!(times[2] - times[1] >= Duration::from_millis(200));
+        let expected = Protocol::Legacy.hsi_command(BDAddr::default(), 240, 100, 100);
+        assert_eq!(hsi_writes(&link)[2], expected);
+    }
+
+    #[test]
+    fn test_write_gap_follows_model_rate() {
+        let infinity = ModelInfo::lookup("NEEWER-TL60");
+        let legacy = ModelInfo::lookup("NEEWER-RGB660");
+
+        let light = Light::new(&config().lights[0]);
+        assert_eq!(light.get_write_gap(infinity), Duration::from_secs(1) / 30);
+        assert_eq!(light.get_write_gap(legacy), Duration::from_millis(50));
+
+        let slowed = config_with(&[json!({ "min_write_interval_ms": 200 })]);
+        let light = Light::new(&slowed.lights[0]);
+        assert_eq!(light.get_write_gap(infinity), Duration::from_millis(200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limit_coalesces_to_newest_state() {
+        let config = config_with(&[json!({ "max_update_hz": 5 })]);
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let controller = LightController::with_sacn_client(&config, vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| hsi_writes(&link).len() == 1).await;
+                for level in 1..=20 {
+                    controller.handle_packet(&packet(level, 0, 0)).await.unwrap();
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                controller.handle_packet(&packet(255, 0, 0)).await.unwrap();
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            } => {},
+        }
+
+        let times = hsi_write_times(&link);
+        for pair in times.windows(2) {
+            assert!(pair[1] - pair[0] >= Duration::from_millis(200));
+        }
+        let expected = Protocol::Legacy.hsi_command(BDAddr::default(), 0, 100, 100);
+        assert_eq!(hsi_writes(&link).last().unwrap(), &expected);
+
+        let counters = controller.get_lights()[0].get_counters().await;
+        assert_eq!(counters.received, 21);
+        assert_eq!(counters.sent as usize, times.len());
+        assert!(counters.coalesced > 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retries_failed_writes_with_response() {
+        let config = config_with(&[json!({ "write_policy": "with_response" })]);
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let controller = LightController::with_sacn_client(&config, vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| hsi_writes(&link).len() == 1).await;
+                link.drop_next_writes(2);
+                controller.handle_packet(&packet(255, 0, 0)).await.unwrap();
+                wait_for(|| hsi_writes(&link).len() == 2).await;
+            } => {},
+        }
+
+        assert!(link
+            .writes()
+            .iter()
+            .all(|w| w.write_type == WriteType::WithResponse));
+        let counters = controller.get_lights()[0].get_counters().await;
+        assert_eq!(counters.retried, 2);
+        assert_eq!(counters.failed, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_adaptive_falls_back_after_silent_failures() {
+        let config = config_with(&[json!({ "write_policy": "adaptive" })]);
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let controller = LightController::with_sacn_client(&config, vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        // the light claims to be at half brightness after we sent it black
+        let mut wrong_level = vec![0x78, 0x01, 0x02, 0x02, 50];
+        wrong_level.push(Protocol::checksum(&wrong_level));
+        let notify_uuid = "69400003-B5A3-F393-E0A9-E50E24DCCA99".parse().unwrap();
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                let query_rounds =
+                    || link.writes().iter().filter(|w| w.data == power_query()).count();
+                wait_for(|| hsi_writes(&link).len() == 1).await;
+                for round in 1..=3 {
+                    wait_for(|| query_rounds() == round).await;
+                    // every reply in a round disagrees, but the round is one failure
+                    for _ in 0..3 {
+                        link.notify(notify_uuid, &wrong_level);
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                    if round == 1 {
+                        wait_for(|| query_rounds() == 2).await;
+                        assert_eq!(
+                            controller.get_lights()[0].get_write_mode().await,
+                            "adaptive, without response"
+                        );
+                    }
+                }
+                wait_for(|| {
+                    link.writes()
+                        .iter()
+                        .any(|w| w.data[1] == 0x86 && w.write_type == WriteType::WithResponse)
+                })
+                .await;
+            } => {},
+        }
+
+        let last = link
+            .writes()
+            .into_iter()
+            .rfind(|w| w.data[1] == 0x86)
+            .unwrap();
+        assert_eq!(last.write_type, WriteType::WithResponse);
+        assert_eq!(
+            controller.get_lights()[0].get_write_mode().await,
+            "adaptive, with response"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_identify_flashes_then_returns_to_live_data() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let controller =
+            LightController::with_sacn_client(&config(), vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let white = Protocol::Legacy.hsi_command(BDAddr::default(), 0, 0, 100);
+        let off = Protocol::Legacy.hsi_command(BDAddr::default(), 0, 0, 0);
+        let red = Protocol::Legacy.hsi_command(BDAddr::default(), 0, 100, 100);
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| hsi_writes(&link).len() == 1).await;
+                controller.handle_packet(&packet(255, 0, 0)).await.unwrap();
+                wait_for(|| hsi_writes(&link).len() == 2).await;
+
+                let identify = ControlCommand::parse(&format!("identify {} 1", LIGHT_ID)).unwrap();
+                controller.execute(&identify, &terminal).await.unwrap();
+                // sACN keeps arriving but shouldn't interrupt the flash
+                controller.handle_packet(&packet(255, 0, 1)).await.unwrap();
+                controller.handle_packet(&packet(255, 0, 0)).await.unwrap();
+                tokio::time::sleep(Duration::from_secs(2)).await;
+            } => {},
+        }
+
+        let writes = hsi_writes(&link);
+        let flashes = &writes[2..writes.len() - 1];
+        assert!(flashes.len() >= 4, "only {} flash writes", flashes.len());
+        for pair in flashes.windows(2) {
+            assert_ne!(pair[0], pair[1]);
+            assert!(pair[0] == white || pair[0] == off);
+        }
+        assert_eq!(flashes[0], white);
+        assert_eq!(writes.last().unwrap(), &red);
+    }
+
+    #[tokio::test]
+    async fn test_matches_light_by_name_and_index() {
+        let config = config_with(&[
+            json!({ "name": "NW-20220016&00000000", "index": 1, "label": "Stage Left Key" }),
+        ]);
+        let transport = Arc::new(MockTransport::new());
+        // counted in the order heard, not by address
+        let first =
+            transport.add_light("5A:00:00:00:00:02".parse().unwrap(), "NW-20220016&00000000");
+        let second =
+            transport.add_light("5A:00:00:00:00:01".parse().unwrap(), "NW-20220016&00000000");
+        let other = transport.add_light("5A:00:00:00:00:03".parse().unwrap(), "NEEWER-RGB660");
+        let controller = LightController::with_sacn_client(&config, vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = wait_for(|| !hsi_writes(&second).is_empty()) => {},
+        }
+
+        assert_eq!(first.connect_attempts(), 0);
+        assert_eq!(other.connect_attempts(), 0);
+        let lock = terminal.read().await;
+        let status = lock.get_light_status("Stage Left Key").unwrap();
+        assert_eq!(status.device, "5A:00:00:00:00:01");
+        assert!(lock.get_light_status("5A:00:00:00:00:01").is_none());
+        drop(lock);
+
+        let light = controller.find_light("stage left key").await.unwrap();
+        assert_eq!(light.get_label(), "Stage Left Key");
+        assert!(controller.find_light("5A:00:00:00:00:01").await.is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_name_and_index_follow_a_rotated_address() {
+        let config = config_with(&[json!({ "name": "NW-20220016&00000000", "index": 1 })]);
+        let transport = Arc::new(MockTransport::new());
+        let kept =
+            transport.add_light("5A:00:00:00:00:01".parse().unwrap(), "NW-20220016&00000000");
+        let old = transport.add_light("5A:00:00:00:00:02".parse().unwrap(), "NW-20220016&00000000");
+        let controller = LightController::with_sacn_client(&config, vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| !hsi_writes(&old).is_empty()).await;
+                // the light comes back under a new address, after one that sorts first
+                old.fail_next_connects(usize::MAX);
+                old.simulate_disconnect();
+                let new_address = "5A:00:00:00:00:00".parse().unwrap();
+                let new = transport.add_light(new_address, "NW-20220016&00000000");
+                wait_for(|| !hsi_writes(&new).is_empty()).await;
+            } => {},
+        }
+
+        assert_eq!(kept.connect_attempts(), 0);
+        let lock = terminal.read().await;
+        assert_eq!(
+            lock.get_light_status("NW-20220016&00000000 #1")
+                .unwrap()
+                .device,
+            "5A:00:00:00:00:00"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ignores_packets_too_short_for_a_light() {
+        let config = config_with(&[json!({ "address": 511 })]);
+        let controller = LightController::with_sacn_client(&config, vec![], None);
+        let short = SacnDmxPacket::new("test".to_string(), 1, 100, 0, 0, vec![0; 100], [0; 16]);
+
+        controller.handle_packet(&short).await.unwrap();
+        controller.handle_packet(&packet(255, 0, 0)).await.unwrap();
+
+        assert_eq!(controller.get_lights()[0].get_counters().await.received, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reload_repatch_keeps_connection() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let controller =
+            LightController::with_sacn_client(&config(), vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let moved = config_with(&[json!({ "label": "Key", "address": 4 })]);
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| hsi_writes(&link).len() == 1).await;
+                let summary = controller.reload(&moved, &terminal).await.unwrap();
+                assert_eq!(
+                    summary,
+                    ReloadSummary {
+                        repatched: 1,
+                        ..Default::default()
+                    }
+                );
+
+                // red now lands on the light's new address
+                let mut dmx_data = vec![0; 513];
+                dmx_data[4] = 255;
+                let packet = SacnDmxPacket::new("test".to_string(), 1, 100, 0, 0, dmx_data, [0; 16]);
+                controller.handle_packet(&packet).await.unwrap();
+                wait_for(|| hsi_writes(&link).len() >= 2).await;
+            } => {},
+        }
+
+        assert_eq!(link.connect_attempts(), 1);
+        let expected = Protocol::Legacy.hsi_command(BDAddr::default(), 0, 100, 100);
+        assert_eq!(hsi_writes(&link).last().unwrap(), &expected);
+        let lock = terminal.read().await;
+        assert!(lock.get_light_status("Key").is_some());
+        assert!(lock.get_light_status(LIGHT_ID).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reload_adds_and_removes_lights() {
+        let transport = Arc::new(MockTransport::new());
+        let old = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let new = transport.add_light("11:22:33:44:55:66".parse().unwrap(), "NEEWER-RGB660");
+        let controller =
+            LightController::with_sacn_client(&config(), vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let replaced = config_with(&[json!({ "id": "11:22:33:44:55:66", "universe": 2 })]);
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| !hsi_writes(&old).is_empty()).await;
+                let summary = controller.reload(&replaced, &terminal).await.unwrap();
+                assert_eq!(summary.added, 1);
+                assert_eq!(summary.removed, 1);
+                wait_for(|| !hsi_writes(&new).is_empty()).await;
+            } => {},
+        }
+
+        assert!(!old.is_connected().await.unwrap());
+        // retired before the disconnect, so nothing reconnected it
+        assert_eq!(old.connect_attempts(), 1);
+        assert_eq!(controller.get_lights().len(), 1);
+        assert!(terminal.read().await.get_light_status(LIGHT_ID).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reload_reconnects_when_connection_settings_change() {
+        let transport = Arc::new(MockTransport::new());
+        let link = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let controller =
+            LightController::with_sacn_client(&config(), vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let reframed = config_with(&[json!({ "protocol": "infinity" })]);
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| link.connect_attempts() == 1 && !hsi_writes(&link).is_empty()).await;
+                let summary = controller.reload(&reframed, &terminal).await.unwrap();
+                assert_eq!((summary.added, summary.removed), (1, 1));
+                wait_for(|| link.connect_attempts() == 2).await;
+            } => {},
+        }
+    }
+
+    #[tokio::test]
+    async fn test_identifies_every_light_in_a_group() {
+        let config = Config::from_value(serde_json::json!({
+            "version": 1,
+            "groups": { "wash": { "universe": 2 } },
+            "lights": [
+                { "ids": ["CB:11:33:33:A3:67", "11:22:33:44:55:66"], "group": "wash", "address": 1 },
+                { "id": "5A:00:00:00:00:01", "label": "Key", "universe": 1, "address": 1 }
+            ]
+        }))
+        .unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let links: Vec<Arc<MockLink>> = [
+            "CB:11:33:33:A3:67",
+            "11:22:33:44:55:66",
+            "5A:00:00:00:00:01",
+        ]
+        .iter()
+        .map(|id| transport.add_light(id.parse().unwrap(), "NEEWER-RGB660"))
+        .collect();
+        let controller = LightController::with_sacn_client(&config, vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+        let white = Protocol::Legacy.hsi_command(BDAddr::default(), 0, 0, 100);
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| links.iter().all(|link| !hsi_writes(link).is_empty())).await;
+                let identify = ControlCommand::parse("identify WASH 1").unwrap();
+                let message = controller.execute(&identify, &terminal).await.unwrap();
+                assert_eq!(message, "Identifying group WASH (2 lights) for 1.0s");
+                wait_for(|| links[..2].iter().all(|link| hsi_writes(link).contains(&white))).await;
+            } => {},
+        }
+
+        assert!(!hsi_writes(&links[2]).contains(&white));
+        let lock = terminal.read().await;
+        assert_eq!(lock.get_light_status(LIGHT_ID).unwrap().group, "wash");
+        assert_eq!(lock.get_light_status("Key").unwrap().group, "");
+    }
+
+    #[tokio::test]
+    async fn test_switching_profile_keeps_lights_it_leaves_connected() {
+        let config = Config::from_value(serde_json::json!({
+            "version": 1,
+            "profile": "club",
+            "profiles": {
+                "club": {
+                    "lights": [
+                        { "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 },
+                        { "id": "11:22:33:44:55:66", "universe": 1, "address": 4 }
+                    ]
+                },
+                "arena": {
+                    "lights": [
+                        { "id": "CB:11:33:33:A3:67", "universe": 5, "address": 10 },
+                        { "id": "11:22:33:44:55:66", "universe": 5, "address": 20, "protocol": "infinity" }
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+        let transport = Arc::new(MockTransport::new());
+        let kept = transport.add_light(LIGHT_ID.parse().unwrap(), "NEEWER-RGB660");
+        let reframed = transport.add_light("11:22:33:44:55:66".parse().unwrap(), "NEEWER-RGB660");
+        let controller = LightController::with_sacn_client(&config, vec![transport.clone()], None);
+        let terminal = RwLock::new(TerminalUi::headless());
+
+        tokio::select! {
+            _ = controller.find_light_loop(&terminal) => panic!("light loop exited"),
+            _ = async {
+                wait_for(|| !hsi_writes(&kept).is_empty() && !hsi_writes(&reframed).is_empty()).await;
+                let switch = ControlCommand::parse("profile arena").unwrap();
+                let message = controller.execute(&switch, &terminal).await.unwrap();
+                assert_eq!(
+                    message,
+                    "Switched to profile arena. Reloaded: 1 added, 1 removed, 1 repatched"
+                );
+                wait_for(|| reframed.connect_attempts() == 2).await;
+            } => {},
+        }
+
+        assert_eq!(kept.connect_attempts(), 1);
+        assert_eq!(controller.get_profile().as_deref(), Some("arena"));
+        assert_eq!(controller.get_lights()[0].get_universe(), 5);
+        assert_eq!(terminal.read().await.get_profile(), Some("arena"));
+        let unknown = ControlCommand::parse("profile pub").unwrap();
+        assert!(controller.execute(&unknown, &terminal).await.is_err());
+    }
+}
@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod tests {
+    use btleplug::api::BDAddr;
+
+    use crate::light_state::LightState;
+    use crate::model::ModelInfo;
+    use crate::personality::Personality;
+    use crate::protocol::Protocol;
+
+    #[test]
+    fn test_rgb_personality_decode() {
+        let state = Personality::Rgb.decode(&[255, 0, 128]);
+
+        assert_eq!(
+            state,
+            LightState::Rgb(crate::color::Color::new(255, 0, 128))
+        );
+    }
+
+    #[test]
+    fn test_cct_uses_model_range() {
+        let mac = BDAddr::default();
+        let model = ModelInfo::lookup("NEEWER-GL1");
+        let state = Personality::Cct.decode(&[255, 255]);
+
+        let result = state.to_command(Protocol::Legacy, mac, model);
+
+        let mut expected = vec![0x78, 0x87, 0x02, 100, 70];
+        expected.push(Protocol::checksum(&expected));
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_cct_warmest_without_model() {
+        let mac = BDAddr::default();
+        let state = Personality::Cct.decode(&[0, 0]);
+
+        let result = state.to_command(Protocol::Legacy, mac, None);
+
+        let mut expected = vec![0x78, 0x87, 0x02, 0, 32];
+        expected.push(Protocol::checksum(&expected));
+        assert_eq!(result, expected);
+    }
+}
@@ -1,2 +1,58 @@
+pub mod cli_tests;
 pub mod color_tests;
+pub mod config_format_tests;
+pub mod config_layers_tests;
+pub mod config_tests;
+pub mod config_watch_tests;
+pub mod connection_state_tests;
+pub mod control_tests;
 pub mod event_counter_tests;
+pub mod export_tests;
+pub mod frame_counters_tests;
+pub mod identify_tests;
+pub mod interpolation_tests;
+pub mod light_controller_tests;
+pub mod light_state_tests;
+pub mod model_tests;
+pub mod patch_tests;
+pub mod protocol_tests;
+pub mod readback_tests;
+pub mod scan_tests;
+pub mod state_file_tests;
+pub mod terminal_ui_tests;
+pub mod validate_tests;
+pub mod write_policy_tests;
+
+/// The light most tests patch.
+#[cfg(test)]
+pub const LIGHT_ID: &str = "CB:11:33:33:A3:67";
+
+/// A config with one light per entry in `lights` and every other setting at its default.
+/// Entries only need the fields their test checks; the rest patch `LIGHT_ID` at universe 1,
+/// address 1.
+#[cfg(test)]
+pub fn config_with(lights: &[serde_json::Value]) -> crate::config::Config {
+    let lights = lights
+        .iter()
+        .map(|fields| {
+            let mut light = serde_json::Map::new();
+            if fields.get("name").is_none() && fields.get("ids").is_none() {
+                light.insert("id".to_string(), LIGHT_ID.into());
+            }
+            light.insert("universe".to_string(), 1.into());
+            light.insert("address".to_string(), 1.into());
+            light.extend(fields.as_object().expect("a light is an object").clone());
+            serde_json::from_value(serde_json::Value::Object(light)).unwrap()
+        })
+        .collect();
+    crate::config::Config {
+        lights,
+        ..Default::default()
+    }
+}
+
+/// A config with just the default light.
+#[cfg(test)]
+pub fn config() -> crate::config::Config {
+    config_with(&[serde_json::json!({})])
+}
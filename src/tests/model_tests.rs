@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests {
+    use crate::model::{ColorSupport, ModelInfo};
+    use crate::personality::Personality;
+    use crate::protocol::Protocol;
+
+    #[test]
+    fn test_lookup_neewer_prefix() {
+        let model = ModelInfo::lookup("NEEWER-RGB660").unwrap();
+
+        assert_eq!(model.name, "RGB660");
+        assert_eq!(model.color_support, ColorSupport::Rgb);
+        assert_eq!(model.protocol, Protocol::Legacy);
+    }
+
+    #[test]
+    fn test_lookup_nw_prefix() {
+        let model = ModelInfo::lookup("NW-RGB176").unwrap();
+
+        assert_eq!(model.name, "RGB176");
+    }
+
+    #[test]
+    fn test_lookup_prefers_longest_name() {
+        let model = ModelInfo::lookup("NEEWER-RGB660 PRO").unwrap();
+
+        assert_eq!(model.name, "RGB660 PRO");
+    }
+
+    #[test]
+    fn test_lookup_does_not_confuse_similar_names() {
+        assert_eq!(ModelInfo::lookup("NEEWER-RGB62").unwrap().name, "RGB62");
+        assert_eq!(ModelInfo::lookup("NEEWER-RGB660").unwrap().name, "RGB660");
+    }
+
+    #[test]
+    fn test_lookup_unknown() {
+        assert!(ModelInfo::lookup("Some Speaker").is_none());
+    }
+
+    #[test]
+    fn test_bicolor_rejects_rgb_personality() {
+        let model = ModelInfo::lookup("NEEWER-SNL660").unwrap();
+
+        assert!(!model.supports(Personality::Rgb));
+        assert!(model.supports(Personality::Cct));
+    }
+}
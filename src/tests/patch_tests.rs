@@ -0,0 +1,190 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::config::{LightConfig, Overrides};
+    use crate::config_layers::Layer;
+    use crate::patch::{hidden_lights, merge, AddressAllocator, PatchEntry};
+    use crate::personality::Personality;
+    use crate::tests::config_with;
+
+    fn existing() -> Vec<LightConfig> {
+        config_with(&[
+            json!({ "address": 4 }),
+            json!({ "id": "CB:11:33:33:A3:68", "address": 7, "personality": "cct" }),
+        ])
+        .lights
+    }
+
+    fn entry(id: &str, address: u16) -> PatchEntry {
+        PatchEntry {
+            id: id.parse().unwrap(),
+            label: "Key".to_string(),
+            personality: Personality::Rgb,
+            universe: 1,
+            address,
+        }
+    }
+
+    #[test]
+    fn test_allocates_by_footprint() {
+        let mut allocator = AddressAllocator::new(2, 10, &[]);
+
+        assert_eq!(allocator.allocate(3), Ok((2, 10)));
+        assert_eq!(allocator.allocate(2), Ok((2, 13)));
+        assert_eq!(allocator.allocate(3), Ok((2, 15)));
+    }
+
+    #[test]
+    fn test_steps_around_existing_lights() {
+        // 4-6 and 7-8 are taken
+        let mut allocator = AddressAllocator::new(1, 1, &existing());
+
+        assert_eq!(allocator.allocate(3), Ok((1, 1)));
+        assert_eq!(allocator.allocate(3), Ok((1, 9)));
+    }
+
+    #[test]
+    fn test_moves_to_next_universe_when_full() {
+        let mut allocator = AddressAllocator::new(1, 509, &[]);
+
+        assert_eq!(allocator.allocate(3), Ok((1, 509)));
+        assert_eq!(allocator.allocate(3), Ok((2, 1)));
+    }
+
+    #[test]
+    fn test_reports_running_out_of_universes() {
+        let mut allocator = AddressAllocator::new(63998, 511, &[]);
+
+        assert_eq!(allocator.allocate(3), Ok((63999, 1)));
+        assert_eq!(allocator.allocate(509), Ok((63999, 4)));
+        assert_eq!(
+            allocator.allocate(3),
+            Err("No universe has room for another light".to_string())
+        );
+    }
+
+    #[test]
+    fn test_first_free_follows_existing_lights() {
+        assert_eq!(AddressAllocator::first_free(1, &existing()), 9);
+        assert_eq!(AddressAllocator::first_free(2, &existing()), 1);
+    }
+
+    #[test]
+    fn test_merge_keeps_existing_entries() {
+        let document = json!([
+            { "id": "CB:11:33:33:A3:67", "universe": 1, "address": 4, "adapter": "hci1" },
+        ]);
+
+        let merged = merge(
+            Some(document.clone()),
+            &[entry("cb:11:33:33:a3:67", 1), entry("CB:11:33:33:A3:6A", 9)],
+        )
+        .unwrap();
+
+        let lights = merged.as_array().unwrap();
+        assert_eq!(lights.len(), 2);
+        assert_eq!(lights[0], document[0]);
+        assert_eq!(
+            lights[1],
+            json!({
+                "id": "CB:11:33:33:A3:6A",
+                "label": "Key",
+                "universe": 1,
+                "address": 9,
+                "personality": "rgb",
+            })
+        );
+        let _: Vec<LightConfig> = serde_json::from_value(merged).unwrap();
+    }
+
+    #[test]
+    fn test_merge_into_new_file() {
+        let merged = merge(None, &[entry("CB:11:33:33:A3:6A", 1)]).unwrap();
+
+        assert_eq!(merged["version"], 1);
+        assert_eq!(merged["lights"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_merge_into_versioned_document() {
+        let document = json!({ "version": 1, "sacn": { "port": 5569 }, "lights": [] });
+
+        let merged = merge(Some(document), &[entry("CB:11:33:33:A3:6A", 1)]).unwrap();
+
+        assert_eq!(merged["sacn"]["port"], 5569);
+        assert_eq!(merged["lights"][0]["id"], "CB:11:33:33:A3:6A");
+    }
+
+    #[test]
+    fn test_merge_into_default_profile() {
+        let document = json!({
+            "version": 1,
+            "profile": "club",
+            "profiles": {
+                "club": { "lights": [{ "ids": ["CB:11:33:33:A3:67"], "universe": 1, "address": 1 }] }
+            }
+        });
+
+        let merged = merge(
+            Some(document),
+            &[entry("CB:11:33:33:A3:67", 1), entry("CB:11:33:33:A3:6A", 4)],
+        )
+        .unwrap();
+
+        let lights = merged["profiles"]["club"]["lights"].as_array().unwrap();
+        assert_eq!(lights.len(), 2);
+        assert_eq!(lights[1]["id"], "CB:11:33:33:A3:6A");
+        assert!(merged.get("lights").is_none());
+    }
+
+    #[test]
+    fn test_merge_rejects_unknown_layout() {
+        assert!(merge(Some(json!({ "version": 1 })), &[]).is_err());
+        assert!(merge(Some(json!("lights")), &[]).is_err());
+    }
+
+    #[test]
+    fn test_finds_shared_lights_a_local_list_would_hide() {
+        let base = Layer {
+            path: "/etc/sacn-neewer-lite/config.json".to_string(),
+            text: json!({
+                "version": 1,
+                "lights": [{ "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 }]
+            })
+            .to_string(),
+        };
+        let hidden = |layers: &[Layer], document| {
+            hidden_lights(
+                layers,
+                "config.json",
+                &document,
+                None,
+                &Overrides::default(),
+            )
+            .unwrap()
+        };
+
+        // a fresh local config's empty list replaces the shared one
+        let fresh = merge(None, &[]).unwrap();
+        let lost = hidden(std::slice::from_ref(&base), fresh);
+        assert_eq!(lost.len(), 1);
+        assert_eq!(lost[0].matcher.to_string(), "CB:11:33:33:A3:67");
+
+        // a local list that already replaces it hides nothing more
+        let local = json!({
+            "version": 1,
+            "lights": [{ "id": "CB:11:33:33:A3:68", "universe": 1, "address": 1 }]
+        });
+        let layers = [
+            base,
+            Layer {
+                path: "config.json".to_string(),
+                text: local.to_string(),
+            },
+        ];
+        let patched = merge(Some(local), &[entry("CB:11:33:33:A3:6A", 4)]).unwrap();
+        assert!(hidden(&layers, patched).is_empty());
+        assert!(hidden(&[], merge(None, &[]).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use btleplug::api::BDAddr;
+
+    use crate::model::ModelInfo;
+    use crate::protocol::Protocol;
+
+    #[test]
+    fn test_detect_legacy() {
+        assert_eq!(Protocol::detect("NEEWER-RGB660"), Protocol::Legacy);
+        assert_eq!(Protocol::detect("NW-RGB176"), Protocol::Legacy);
+    }
+
+    #[test]
+    fn test_detect_infinity() {
+        assert_eq!(Protocol::detect("NEEWER-RGB62"), Protocol::Infinity);
+        assert_eq!(Protocol::detect("nw-tl60"), Protocol::Infinity);
+    }
+
+    #[test]
+    fn test_detect_every_infinity_model() {
+        // every model detected as Infinity before the model table took over
+        for name in ["RGB62", "TL60", "TL90", "TL120", "SL90", "CB60"] {
+            assert_eq!(
+                Protocol::detect(&format!("NEEWER-{}", name)),
+                Protocol::Infinity,
+                "{}",
+                name
+            );
+        }
+        assert_eq!(
+            ModelInfo::lookup("NEEWER-TL120").map(|model| model.name),
+            Some("TL120")
+        );
+    }
+
+    #[test]
+    fn test_legacy_hsi_command() {
+        let mac = "CB:11:33:33:A3:67".parse::<BDAddr>().unwrap();
+
+        let result = Protocol::Legacy.hsi_command(mac, 300, 100, 50);
+
+        assert_eq!(result, vec![0x78, 0x86, 0x04, 0x2C, 0x01, 0x64, 0x32, 0xC5]);
+    }
+
+    #[test]
+    fn test_infinity_hsi_command() {
+        let mac = "CB:11:33:33:A3:67".parse::<BDAddr>().unwrap();
+
+        let result = Protocol::Infinity.hsi_command(mac, 300, 100, 50);
+
+        let mut expected = vec![
+            0x78, 0x8F, 0x0B, 0xCB, 0x11, 0x33, 0x33, 0xA3, 0x67, 0x86, 0x2C, 0x01, 0x64, 0x32,
+        ];
+        expected.push(Protocol::checksum(&expected));
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use crate::color::Color;
+    use crate::light_state::LightState;
+    use crate::protocol::Protocol;
+    use crate::readback::{Readback, ReadbackMode};
+
+    fn frame(tag: u8, payload: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0x78, tag, payload.len() as u8];
+        bytes.extend_from_slice(payload);
+        bytes.push(Protocol::checksum(&bytes));
+        bytes
+    }
+
+    #[test]
+    fn test_power_on() {
+        let mut readback = Readback::new();
+
+        assert!(readback.apply(&[0x78, 0x02, 0x01, 0x01, 0x7C]));
+
+        assert_eq!(readback.power, Some(true));
+    }
+
+    #[test]
+    fn test_power_off() {
+        let mut readback = Readback::new();
+
+        assert!(readback.apply(&frame(0x02, &[0x02])));
+
+        assert_eq!(readback.power, Some(false));
+    }
+
+    #[test]
+    fn test_channel_status() {
+        let mut readback = Readback::new();
+
+        assert!(readback.apply(&frame(0x01, &[0x02, 50])));
+
+        assert_eq!(readback.mode, Some(ReadbackMode::Hsi));
+        assert_eq!(readback.brightness, Some(50));
+    }
+
+    #[test]
+    fn test_battery() {
+        let mut readback = Readback::new();
+
+        assert!(readback.apply(&frame(0x95, &[80])));
+
+        assert_eq!(readback.battery, Some(80));
+    }
+
+    #[test]
+    fn test_bad_checksum_ignored() {
+        let mut readback = Readback::new();
+
+        assert!(!readback.apply(&[0x78, 0x02, 0x01, 0x01, 0x00]));
+
+        assert_eq!(readback, Readback::new());
+    }
+
+    #[test]
+    fn test_matches_commanded_state() {
+        let mut readback = Readback::new();
+        readback.apply(&frame(0x02, &[0x01]));
+        readback.apply(&frame(0x01, &[0x02, 100]));
+
+        assert!(readback.matches(&LightState::Rgb(Color::new(255, 0, 0))));
+        assert!(!readback.matches(&LightState::Rgb(Color::new(64, 0, 0))));
+        assert!(!readback.matches(&LightState::Cct {
+            dimmer: 255,
+            temperature: 0
+        }));
+    }
+
+    #[test]
+    fn test_unreported_fields_match() {
+        let readback = Readback::new();
+
+        assert!(readback.matches(&LightState::Rgb(Color::new(12, 34, 56))));
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use btleplug::api::{BDAddr, PeripheralProperties};
+    use serde_json::json;
+
+    use crate::scan::{self, is_neewer, ScanOptions};
+    use crate::tests::config_with;
+    use crate::transport::mock::MockTransport;
+    use crate::transport::BleTransport;
+
+    fn named(name: &str) -> PeripheralProperties {
+        PeripheralProperties {
+            local_name: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_neewer_filter() {
+        assert!(is_neewer(&named("NEEWER-RGB660 PRO")));
+        assert!(is_neewer(&named("NW-20220016&00000000")));
+        assert!(is_neewer(&named("RGB62")));
+        assert!(!is_neewer(&named("JBL Flip 5")));
+        assert!(!is_neewer(&PeripheralProperties::default()));
+
+        let unnamed = PeripheralProperties {
+            services: vec!["69400001-B5A3-F393-E0A9-E50E24DCCA99".parse().unwrap()],
+            ..Default::default()
+        };
+        assert!(is_neewer(&unnamed));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_scan_lists_neewer_lights_strongest_first() {
+        let near: BDAddr = "CB:11:33:33:A3:67".parse().unwrap();
+        let far: BDAddr = "CB:11:33:33:A3:68".parse().unwrap();
+        let first = Arc::new(MockTransport::named("hci0"));
+        let second = Arc::new(MockTransport::named("hci1"));
+        first.add_light(far, "NEEWER-RGB660").set_rssi(-80);
+        first.add_light(near, "NEEWER-RGB660").set_rssi(-70);
+        second.add_light(near, "NEEWER-RGB660").set_rssi(-50);
+        first.add_light("11:22:33:44:55:66".parse().unwrap(), "JBL Flip 5");
+
+        let config = config_with(&[json!({ "id": "CB:11:33:33:A3:68" })]);
+        let transports: Vec<Arc<dyn BleTransport>> = vec![first.clone(), second.clone()];
+
+        let results = scan::scan(&transports, &ScanOptions::default(), Some(&config))
+            .await
+            .unwrap();
+
+        assert!(first.is_scanning() && second.is_scanning());
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].address, near);
+        assert_eq!(results[0].rssi, Some(-50));
+        assert_eq!(results[0].adapter, "hci1");
+        assert_eq!(results[0].model.as_deref(), Some("RGB660"));
+        assert!(!results[0].configured);
+        assert_eq!(results[1].address, far);
+        assert!(results[1].configured);
+
+        let json = serde_json::to_value(&results[1]).unwrap();
+        assert_eq!(json["address"], "CB:11:33:33:A3:68");
+        assert_eq!(json["rssi"], -80);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_scan_all_includes_other_devices() {
+        let transport = Arc::new(MockTransport::new());
+        transport.add_light("11:22:33:44:55:66".parse().unwrap(), "JBL Flip 5");
+        let transports: Vec<Arc<dyn BleTransport>> = vec![transport];
+        let options = ScanOptions {
+            all: true,
+            ..Default::default()
+        };
+
+        let results = scan::scan(&transports, &options, None).await.unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].model, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_scan_leaves_name_rules_unresolved() {
+        let transport = Arc::new(MockTransport::new());
+        // heard in the opposite of address order, so a scan can't say which is index 0
+        transport.add_light("5A:00:00:00:00:02".parse().unwrap(), "NEEWER-RGB660");
+        transport.add_light("5A:00:00:00:00:01".parse().unwrap(), "NEEWER-RGB660");
+        transport.add_light("CB:11:33:33:A3:67".parse().unwrap(), "NEEWER-RGB530");
+        let config = config_with(&[json!({ "name": "NEEWER-RGB660", "index": 0 })]);
+        let transports: Vec<Arc<dyn BleTransport>> = vec![transport];
+
+        let results = scan::scan(&transports, &ScanOptions::default(), Some(&config))
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        for result in results.iter() {
+            let named = result.name.as_deref() == Some("NEEWER-RGB660");
+            assert!(!result.configured);
+            assert_eq!(result.by_name, named);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::KeyCode;
+    use ratatui::style::Color;
+
+    use crate::control::ControlCommand;
+    use crate::terminal_ui::{Row, TerminalUi};
+
+    #[test]
+    fn test_arrow_keys_move_selection_in_order() {
+        let mut ui = TerminalUi::headless();
+        ui.set_light_status("BB:00:00:00:00:02", "Ready", Color::Green);
+        ui.set_light_status("AA:00:00:00:00:01", "Ready", Color::Green);
+
+        assert_eq!(ui.get_selected(), None);
+        ui.handle_key(KeyCode::Down);
+        assert_eq!(ui.get_selected(), Some("AA:00:00:00:00:01"));
+        ui.handle_key(KeyCode::Down);
+        ui.handle_key(KeyCode::Down);
+        assert_eq!(ui.get_selected(), Some("BB:00:00:00:00:02"));
+        ui.handle_key(KeyCode::Up);
+        assert_eq!(ui.get_selected(), Some("AA:00:00:00:00:01"));
+    }
+
+    #[test]
+    fn test_identify_key_sends_selected_light() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut ui = TerminalUi::headless();
+        ui.set_command_sender(sender);
+        ui.set_light_status("AA:00:00:00:00:01", "Ready", Color::Green);
+
+        // nothing selected yet
+        ui.handle_key(KeyCode::Char('i'));
+        assert!(receiver.try_recv().is_err());
+
+        ui.handle_key(KeyCode::Down);
+        ui.handle_key(KeyCode::Char('i'));
+        match receiver.try_recv().unwrap() {
+            ControlCommand::Identify { target, .. } => assert_eq!(target, "AA:00:00:00:00:01"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_groups_fold_and_take_commands() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut ui = TerminalUi::headless();
+        ui.set_command_sender(sender);
+        ui.set_light_status("Key", "Ready", Color::Green);
+        ui.set_light_status("Wash 1", "Ready", Color::Green);
+        ui.set_light_status("Wash 2", "Ready", Color::Green);
+        ui.set_light_group("Wash 1", Some("wash"));
+        ui.set_light_group("Wash 2", Some("wash"));
+
+        let light = |id: &str| Row::Light(id.to_string());
+        let group = Row::Group("wash".to_string());
+        assert_eq!(
+            ui.get_rows(),
+            vec![
+                light("Key"),
+                group.clone(),
+                light("Wash 1"),
+                light("Wash 2")
+            ]
+        );
+
+        // folding from a member selects the header, and the members go
+        ui.handle_key(KeyCode::Down);
+        ui.handle_key(KeyCode::Down);
+        ui.handle_key(KeyCode::Down);
+        assert_eq!(ui.get_selected(), Some("Wash 1"));
+        ui.handle_key(KeyCode::Left);
+        assert_eq!(ui.get_selected(), Some("wash"));
+        assert_eq!(ui.get_rows(), vec![light("Key"), group.clone()]);
+        ui.handle_key(KeyCode::Down);
+        assert_eq!(ui.get_selected(), Some("wash"));
+
+        ui.handle_key(KeyCode::Char('i'));
+        match receiver.try_recv().unwrap() {
+            ControlCommand::Identify { target, .. } => assert_eq!(target, "wash"),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        ui.handle_key(KeyCode::Enter);
+        assert_eq!(ui.get_rows().len(), 4);
+    }
+
+    #[test]
+    fn test_renamed_lights_can_swap_labels() {
+        let mut ui = TerminalUi::headless();
+        ui.set_light_status("Key", "Ready", Color::Green);
+        ui.set_light_status("Fill", "Scanning", Color::Yellow);
+        ui.handle_key(KeyCode::Down);
+        assert_eq!(ui.get_selected(), Some("Fill"));
+
+        ui.rename_lights(&[
+            ("Key".to_string(), "Fill".to_string()),
+            ("Fill".to_string(), "Key".to_string()),
+        ]);
+
+        assert_eq!(ui.get_light_status("Fill").unwrap().status, "Ready");
+        assert_eq!(ui.get_light_status("Key").unwrap().status, "Scanning");
+        assert_eq!(ui.get_selected(), Some("Key"));
+    }
+
+    #[test]
+    fn test_p_switches_to_the_next_profile() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut ui = TerminalUi::headless();
+        ui.set_command_sender(sender);
+
+        // no profiles, nothing to switch to
+        ui.handle_key(KeyCode::Char('p'));
+        assert!(receiver.try_recv().is_err());
+
+        let profiles = vec!["arena".to_string(), "club".to_string()];
+        ui.set_profiles(profiles.clone(), Some("arena"));
+        ui.handle_key(KeyCode::Char('p'));
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            ControlCommand::Profile {
+                name: Some("club".to_string())
+            }
+        );
+
+        ui.set_profiles(profiles, Some("club"));
+        ui.handle_key(KeyCode::Char('p'));
+        assert_eq!(
+            receiver.try_recv().unwrap(),
+            ControlCommand::Profile {
+                name: Some("arena".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_q_exits() {
+        let mut ui = TerminalUi::headless();
+        assert!(!ui.handle_key(KeyCode::Char('x')));
+        assert!(ui.handle_key(KeyCode::Char('q')));
+    }
+}
@@ -0,0 +1,222 @@
+#[cfg(test)]
+mod tests {
+    use crate::config_format::ConfigFormat;
+    use crate::validate::{validate, Problem};
+
+    fn lines(problems: &[Problem]) -> Vec<Option<usize>> {
+        problems.iter().map(|problem| problem.line).collect()
+    }
+
+    #[test]
+    fn test_valid_config_has_no_problems() {
+        let text = r#"{
+  "version": 1,
+  "lights": [
+    { "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 },
+    { "id": "CB:11:33:33:A3:68", "universe": 1, "address": 4, "personality": "cct" },
+    { "name": "NEEWER-RGB660", "universe": 2, "address": 510 }
+  ]
+}"#;
+
+        assert_eq!(validate(text, ConfigFormat::Json), vec![]);
+    }
+
+    #[test]
+    fn test_reports_every_problem_with_its_line() {
+        let text = r#"{
+  "version": 1,
+  "lights": [
+    { "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 },
+    {
+      "id": "CB:11:33:33:A3:67",
+      "universe": 1,
+      "address": 3
+    },
+    { "id": "CB:11:33:33:A3:69", "universe": 0, "address": 20 },
+    { "id": "CB:11:33:33:A3:6A", "universe": 1, "address": 511 },
+    { "id": "CB:11:33:33:A3:6B", "universe": 1, "address": 30, "personality": "rbg" }
+  ]
+}"#;
+
+        let problems = validate(text, ConfigFormat::Json);
+
+        assert_eq!(
+            lines(&problems),
+            vec![Some(5), Some(5), Some(10), Some(11), Some(12)]
+        );
+        let messages: Vec<&str> = problems.iter().map(|p| p.message.as_str()).collect();
+        assert!(messages.contains(&"CB:11:33:33:A3:67 is already patched (line 4)"));
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("channels 3-5 overlap CB:11:33:33:A3:67 at 1-3")));
+        assert!(messages.contains(&"CB:11:33:33:A3:69: universe 0 is outside 1-63999"));
+        assert!(messages.contains(&"CB:11:33:33:A3:6A: Rgb footprint at 511-513 is outside 1-512"));
+        assert!(messages
+            .iter()
+            .any(|m| m.starts_with("Light 5: unknown variant `rbg`")));
+    }
+
+    #[test]
+    fn test_legacy_array_lines() {
+        let text = r#"[
+    { "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1, "label": "Key" },
+    { "id": "CB:11:33:33:A3:68", "universe": 1, "address": 10, "label": "key" }
+]"#;
+
+        let problems = validate(text, ConfigFormat::Json);
+
+        assert_eq!(lines(&problems), vec![Some(3)]);
+        assert_eq!(problems[0].message, "Label key is already used (line 2)");
+    }
+
+    #[test]
+    fn test_reports_json_syntax_errors() {
+        let problems = validate(
+            "{\n  \"version\": 1,\n  \"lights\": [\n}",
+            ConfigFormat::Json,
+        );
+
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(4));
+        assert!(problems[0].message.starts_with("Invalid JSON"));
+    }
+
+    #[test]
+    fn test_reports_bad_settings() {
+        let problems = validate(
+            r#"{ "version": 1, "sacn": { "port": "many" }, "lights": [] }"#,
+            ConfigFormat::Json,
+        );
+        assert_eq!(problems.len(), 1);
+
+        let problems = validate(r#"{ "version": 7, "lights": [] }"#, ConfigFormat::Json);
+        assert_eq!(
+            problems[0].message,
+            "Config version 7 is not supported, expected 1"
+        );
+    }
+
+    #[test]
+    fn test_describe_uses_path_and_line() {
+        let problem = Problem {
+            line: Some(12),
+            message: "Something".to_string(),
+        };
+        assert_eq!(
+            problem.describe("data/config.json"),
+            "data/config.json:12: Something"
+        );
+    }
+
+    #[test]
+    fn test_toml_and_yaml_light_lines() {
+        let toml = r#"version = 1
+
+[[lights]]
+id = "CB:11:33:33:A3:67"
+universe = 1
+address = 1
+
+# same light again
+[[lights]]
+id = "CB:11:33:33:A3:67"
+universe = 1
+address = 600
+"#;
+        let problems = validate(toml, ConfigFormat::Toml);
+        assert_eq!(lines(&problems), vec![Some(9), Some(9)]);
+
+        let yaml = r#"version: 1
+lights:
+  - id: "CB:11:33:33:A3:67"
+    universe: 1
+    address: 1
+  - id: "CB:11:33:33:A3:67"
+    universe: 1
+    address: 600
+"#;
+        let problems = validate(yaml, ConfigFormat::Yaml);
+        assert_eq!(lines(&problems), vec![Some(6), Some(6)]);
+    }
+
+    #[test]
+    fn test_reports_group_and_range_problems_on_their_entry() {
+        let text = r#"{
+  "version": 1,
+  "groups": { "wash": { "universe": 2, "personality": "cct" } },
+  "lights": [
+    { "ids": ["CB:11:33:33:A3:67", "CB:11:33:33:A3:68"], "group": "wash", "address": 1 },
+    { "id": "CB:11:33:33:A3:69", "group": "spots", "address": 1 },
+    { "id": "CB:11:33:33:A3:6A", "label": "Wash", "universe": 1, "address": 1 },
+    { "id": "CB:11:33:33:A3:6B", "group": "wash", "address": 4 }
+  ]
+}"#;
+
+        let problems = validate(text, ConfigFormat::Json);
+
+        assert_eq!(
+            problems
+                .iter()
+                .map(|problem| problem.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "line 6: Light 2: Unknown group spots",
+                "line 7: Label wash is also the name of a group",
+                "line 8: CB:11:33:33:A3:6B: channels 4-5 overlap CB:11:33:33:A3:68 at 3-4 in universe 2 (line 5)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checks_every_profile() {
+        let text = r#"{
+  "version": 1,
+  "profile": "club",
+  "profiles": {
+    "club": {
+      "lights": [
+        { "id": "CB:11:33:33:A3:67", "universe": 1, "address": 1 },
+        { "id": "CB:11:33:33:A3:68", "universe": 1, "address": 2 }
+      ]
+    },
+    "arena": {
+      "version": 2,
+      "lights": [{ "id": "CB:11:33:33:A3:67", "universe": 0, "address": 1 }]
+    },
+    "pub": { "sacn": { "port": "loud" } }
+  }
+}"#;
+
+        let problems = validate(text, ConfigFormat::Json);
+
+        let messages: Vec<String> = problems.iter().map(|problem| problem.to_string()).collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert_eq!(messages[0], "Profile arena can't set version");
+        assert!(messages[1].starts_with("Profile pub: "), "{}", messages[1]);
+        assert_eq!(messages[2], "Profile pub: Missing lights");
+        assert!(
+            messages[3]
+                .starts_with("line 8: Profile club: CB:11:33:33:A3:68: channels 2-4 overlap"),
+            "{}",
+            messages[3]
+        );
+    }
+
+    #[test]
+    fn test_toml_profile_light_lines() {
+        let text = r#"version = 1
+
+[profiles.club]
+
+[[profiles.club.lights]]
+id = "CB:11:33:33:A3:67"
+universe = 0
+address = 1
+"#;
+
+        let problems = validate(text, ConfigFormat::Toml);
+
+        assert_eq!(lines(&problems), vec![Some(5)]);
+        assert!(problems[0].message.starts_with("Profile club: "));
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use btleplug::api::WriteType;
+
+    use crate::write_policy::{WritePolicy, WriteTracker};
+
+    #[test]
+    fn test_fixed_policies_never_change() {
+        let mut without = WriteTracker::new(WritePolicy::WithoutResponse);
+        let mut with = WriteTracker::new(WritePolicy::WithResponse);
+
+        for _ in 0..10 {
+            assert!(!without.record_failure());
+            assert!(!with.record_failure());
+        }
+
+        assert_eq!(without.write_type(), WriteType::WithoutResponse);
+        assert_eq!(with.write_type(), WriteType::WithResponse);
+    }
+
+    #[test]
+    fn test_adaptive_falls_back_after_repeated_failures() {
+        let mut tracker = WriteTracker::new(WritePolicy::Adaptive);
+
+        assert!(!tracker.record_failure());
+        assert!(!tracker.record_failure());
+        assert_eq!(tracker.write_type(), WriteType::WithoutResponse);
+
+        assert!(tracker.record_failure());
+        assert_eq!(tracker.write_type(), WriteType::WithResponse);
+        assert_eq!(tracker.to_string(), "adaptive, with response");
+
+        // only reported once
+        assert!(!tracker.record_failure());
+    }
+
+    #[test]
+    fn test_adaptive_success_resets_failures() {
+        let mut tracker = WriteTracker::new(WritePolicy::Adaptive);
+
+        tracker.record_failure();
+        tracker.record_failure();
+        tracker.record_success();
+        tracker.record_failure();
+        tracker.record_failure();
+
+        assert_eq!(tracker.write_type(), WriteType::WithoutResponse);
+    }
+
+    #[test]
+    fn test_policy_deserializes_snake_case() {
+        let policy: WritePolicy = serde_json::from_str(r#""with_response""#).unwrap();
+        assert_eq!(policy, WritePolicy::WithResponse);
+    }
+}
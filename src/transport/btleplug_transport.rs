@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use btleplug::api::{
+    BDAddr, Central, CentralEvent, Characteristic, Peripheral as _, PeripheralProperties,
+    ScanFilter, WriteType,
+};
+use btleplug::platform::{Adapter, Peripheral};
+use futures::StreamExt;
+use uuid::Uuid;
+
+use crate::transport::{BleTransport, EventStream, LightLink, NotificationStream, TransportEvent};
+
+pub struct BtleplugTransport {
+    adapter: Adapter,
+    name: String,
+    address: Option<BDAddr>,
+}
+
+impl BtleplugTransport {
+    pub async fn new(adapter: Adapter) -> Self {
+        let info = adapter.adapter_info().await.unwrap_or_default();
+        let name = info
+            .split_whitespace()
+            .next()
+            .unwrap_or("unknown")
+            .to_string();
+        let address = Self::read_address(&name);
+        Self {
+            adapter,
+            name,
+            address,
+        }
+    }
+
+    // btleplug doesn't expose the controller address, but BlueZ publishes it in sysfs.
+    fn read_address(name: &str) -> Option<BDAddr> {
+        std::fs::read_to_string(format!("/sys/class/bluetooth/{}/address", name))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    }
+}
+
+#[async_trait]
+impl BleTransport for BtleplugTransport {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address(&self) -> Option<BDAddr> {
+        self.address
+    }
+
+    async fn start_scan(&self) -> Result<(), btleplug::Error> {
+        self.adapter.start_scan(ScanFilter::default()).await
+    }
+
+    async fn peripherals(&self) -> Result<Vec<Arc<dyn LightLink>>, btleplug::Error> {
+        let peripherals = self.adapter.peripherals().await?;
+        Ok(peripherals
+            .into_iter()
+            .map(|p| Arc::new(BtleplugLink::new(p)) as Arc<dyn LightLink>)
+            .collect())
+    }
+
+    async fn events(&self) -> Result<EventStream, btleplug::Error> {
+        let events = self.adapter.events().await?;
+        let adapter = self.adapter.clone();
+        Ok(Box::pin(events.filter_map(move |event| {
+            let adapter = adapter.clone();
+            async move {
+                match event {
+                    CentralEvent::DeviceDiscovered(id) | CentralEvent::DeviceUpdated(id) => {
+                        let peripheral = adapter.peripheral(&id).await.ok()?;
+                        Some(TransportEvent::Discovered(Arc::new(BtleplugLink::new(
+                            peripheral,
+                        ))))
+                    }
+                    CentralEvent::DeviceDisconnected(id) => {
+                        let peripheral = adapter.peripheral(&id).await.ok()?;
+                        Some(TransportEvent::Disconnected(peripheral.address()))
+                    }
+                    _ => None,
+                }
+            }
+        })))
+    }
+}
+
+pub struct BtleplugLink {
+    peripheral: Peripheral,
+}
+
+impl BtleplugLink {
+    pub fn new(peripheral: Peripheral) -> Self {
+        Self { peripheral }
+    }
+
+    fn characteristic(&self, uuid: Uuid) -> Result<Characteristic, btleplug::Error> {
+        self.peripheral
+            .characteristics()
+            .into_iter()
+            .find(|c| c.uuid == uuid)
+            .ok_or(btleplug::Error::NoSuchCharacteristic)
+    }
+}
+
+#[async_trait]
+impl LightLink for BtleplugLink {
+    fn address(&self) -> BDAddr {
+        self.peripheral.address()
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>, btleplug::Error> {
+        self.peripheral.properties().await
+    }
+
+    async fn connect(&self) -> Result<(), btleplug::Error> {
+        self.peripheral.connect().await
+    }
+
+    async fn disconnect(&self) -> Result<(), btleplug::Error> {
+        self.peripheral.disconnect().await
+    }
+
+    async fn is_connected(&self) -> Result<bool, btleplug::Error> {
+        self.peripheral.is_connected().await
+    }
+
+    async fn discover_services(&self) -> Result<(), btleplug::Error> {
+        self.peripheral.discover_services().await
+    }
+
+    async fn write(
+        &self,
+        characteristic: Uuid,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<(), btleplug::Error> {
+        let characteristic = self.characteristic(characteristic)?;
+        self.peripheral
+            .write(&characteristic, data, write_type)
+            .await
+    }
+
+    async fn subscribe(&self, characteristic: Uuid) -> Result<(), btleplug::Error> {
+        let characteristic = self.characteristic(characteristic)?;
+        self.peripheral.subscribe(&characteristic).await
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, btleplug::Error> {
+        self.peripheral.notifications().await
+    }
+}
@@ -0,0 +1,325 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use btleplug::api::{BDAddr, PeripheralProperties, ValueNotification, WriteType};
+use futures::{Stream, StreamExt};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::transport::{BleTransport, EventStream, LightLink, NotificationStream, TransportEvent};
+
+/// In-process stand-in for an adapter, handing out scripted `MockLink`s.
+pub struct MockTransport {
+    name: String,
+    links: Mutex<Vec<Arc<MockLink>>>,
+    scanning: AtomicBool,
+    advertiser: broadcast::Sender<MockEvent>,
+    connects: Arc<ConnectTracker>,
+}
+
+#[derive(Clone)]
+enum MockEvent {
+    Discovered(Arc<MockLink>),
+    Disconnected(BDAddr),
+}
+
+/// Counts connection attempts in flight across every light on a transport.
+#[derive(Default)]
+pub struct ConnectTracker {
+    active: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::named("mock0")
+    }
+
+    pub fn named(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            links: Mutex::new(vec![]),
+            scanning: AtomicBool::new(false),
+            advertiser: broadcast::channel(16).0,
+            connects: Arc::new(ConnectTracker::default()),
+        }
+    }
+
+    /// Adds a light and advertises it to anyone listening for discoveries.
+    pub fn add_light(&self, address: BDAddr, local_name: &str) -> Arc<MockLink> {
+        let mut link = MockLink::new(address, local_name);
+        link.connects = self.connects.clone();
+        link.advertiser = Some(self.advertiser.clone());
+        let link = Arc::new(link);
+        self.links.lock().unwrap().push(link.clone());
+        self.advertise(&link);
+        link
+    }
+
+    pub fn advertise(&self, link: &Arc<MockLink>) {
+        let _ = self.advertiser.send(MockEvent::Discovered(link.clone()));
+    }
+
+    /// Most connection attempts that were ever in flight at once.
+    pub fn peak_concurrent_connects(&self) -> usize {
+        self.connects.peak.load(Ordering::SeqCst)
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.scanning.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for MockTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl BleTransport for MockTransport {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address(&self) -> Option<BDAddr> {
+        None
+    }
+
+    async fn start_scan(&self) -> Result<(), btleplug::Error> {
+        self.scanning.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn peripherals(&self) -> Result<Vec<Arc<dyn LightLink>>, btleplug::Error> {
+        Ok(self
+            .links
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|link| link.clone() as Arc<dyn LightLink>)
+            .collect())
+    }
+
+    async fn events(&self) -> Result<EventStream, btleplug::Error> {
+        Ok(Box::pin(receiver_stream(self.advertiser.subscribe()).map(
+            |event| match event {
+                MockEvent::Discovered(link) => TransportEvent::Discovered(link),
+                MockEvent::Disconnected(address) => TransportEvent::Disconnected(address),
+            },
+        )))
+    }
+}
+
+fn receiver_stream<T: Clone + Send + 'static>(
+    receiver: broadcast::Receiver<T>,
+) -> impl Stream<Item = T> + Send {
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(item) => return Some((item, receiver)),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedWrite {
+    pub characteristic: Uuid,
+    pub data: Vec<u8>,
+    pub write_type: WriteType,
+    pub at: tokio::time::Instant,
+}
+
+/// A fake light that records every write and can be told to misbehave.
+pub struct MockLink {
+    address: BDAddr,
+    local_name: String,
+    rssi: Mutex<Option<i16>>,
+    services: Mutex<Vec<Uuid>>,
+    connected: AtomicBool,
+    connect_attempts: AtomicUsize,
+    connect_failures: AtomicUsize,
+    connect_delay: Mutex<Duration>,
+    connects: Arc<ConnectTracker>,
+    advertiser: Option<broadcast::Sender<MockEvent>>,
+    dropped_writes: AtomicUsize,
+    adapter_failures: AtomicUsize,
+    writes: Mutex<Vec<RecordedWrite>>,
+    notifier: Mutex<broadcast::Sender<ValueNotification>>,
+}
+
+impl MockLink {
+    pub fn new(address: BDAddr, local_name: &str) -> Self {
+        Self {
+            address,
+            local_name: local_name.to_string(),
+            rssi: Mutex::new(None),
+            services: Mutex::new(vec![]),
+            connected: AtomicBool::new(false),
+            connect_attempts: AtomicUsize::new(0),
+            connect_failures: AtomicUsize::new(0),
+            connect_delay: Mutex::new(Duration::ZERO),
+            connects: Arc::new(ConnectTracker::default()),
+            advertiser: None,
+            dropped_writes: AtomicUsize::new(0),
+            adapter_failures: AtomicUsize::new(0),
+            writes: Mutex::new(vec![]),
+            notifier: Mutex::new(broadcast::channel(16).0),
+        }
+    }
+
+    pub fn set_rssi(&self, rssi: i16) {
+        *self.rssi.lock().unwrap() = Some(rssi);
+    }
+
+    pub fn set_services(&self, services: Vec<Uuid>) {
+        *self.services.lock().unwrap() = services;
+    }
+
+    /// Makes the next `count` connection attempts fail.
+    pub fn fail_next_connects(&self, count: usize) {
+        self.connect_failures.store(count, Ordering::SeqCst);
+    }
+
+    /// Makes each connection attempt take this long.
+    pub fn set_connect_delay(&self, delay: Duration) {
+        *self.connect_delay.lock().unwrap() = delay;
+    }
+
+    /// Loses the next `count` writes. Writes without response still report success, as they
+    /// would over the air.
+    pub fn drop_next_writes(&self, count: usize) {
+        self.dropped_writes.store(count, Ordering::SeqCst);
+    }
+
+    /// Makes the next `count` connection checks and disconnects fail, as they do when the
+    /// adapter goes away.
+    pub fn fail_next_adapter_calls(&self, count: usize) {
+        self.adapter_failures.store(count, Ordering::SeqCst);
+    }
+
+    /// Drops the connection, ends any open notification streams and tells the transport.
+    pub fn simulate_disconnect(&self) {
+        self.connected.store(false, Ordering::SeqCst);
+        *self.notifier.lock().unwrap() = broadcast::channel(16).0;
+        if let Some(advertiser) = self.advertiser.as_ref() {
+            let _ = advertiser.send(MockEvent::Disconnected(self.address));
+        }
+    }
+
+    pub fn notify(&self, characteristic: Uuid, value: &[u8]) {
+        let _ = self.notifier.lock().unwrap().send(ValueNotification {
+            uuid: characteristic,
+            value: value.to_vec(),
+        });
+    }
+
+    pub fn writes(&self) -> Vec<RecordedWrite> {
+        self.writes.lock().unwrap().clone()
+    }
+
+    pub fn connect_attempts(&self) -> usize {
+        self.connect_attempts.load(Ordering::SeqCst)
+    }
+
+    fn take_one(counter: &AtomicUsize) -> bool {
+        counter
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+    }
+}
+
+#[async_trait]
+impl LightLink for MockLink {
+    fn address(&self) -> BDAddr {
+        self.address
+    }
+
+    async fn properties(&self) -> Result<Option<PeripheralProperties>, btleplug::Error> {
+        Ok(Some(PeripheralProperties {
+            address: self.address,
+            local_name: Some(self.local_name.clone()),
+            rssi: *self.rssi.lock().unwrap(),
+            services: self.services.lock().unwrap().clone(),
+            ..Default::default()
+        }))
+    }
+
+    async fn connect(&self) -> Result<(), btleplug::Error> {
+        self.connect_attempts.fetch_add(1, Ordering::SeqCst);
+        let active = self.connects.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.connects.peak.fetch_max(active, Ordering::SeqCst);
+        let delay = *self.connect_delay.lock().unwrap();
+        tokio::time::sleep(delay).await;
+        self.connects.active.fetch_sub(1, Ordering::SeqCst);
+
+        if Self::take_one(&self.connect_failures) {
+            return Err(btleplug::Error::DeviceNotFound);
+        }
+        self.connected.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn disconnect(&self) -> Result<(), btleplug::Error> {
+        if Self::take_one(&self.adapter_failures) {
+            return Err(btleplug::Error::RuntimeError("adapter gone".to_string()));
+        }
+        self.simulate_disconnect();
+        Ok(())
+    }
+
+    async fn is_connected(&self) -> Result<bool, btleplug::Error> {
+        if Self::take_one(&self.adapter_failures) {
+            return Err(btleplug::Error::RuntimeError("adapter gone".to_string()));
+        }
+        Ok(self.connected.load(Ordering::SeqCst))
+    }
+
+    async fn discover_services(&self) -> Result<(), btleplug::Error> {
+        if !self.connected.load(Ordering::SeqCst) {
+            return Err(btleplug::Error::NotConnected);
+        }
+        Ok(())
+    }
+
+    async fn write(
+        &self,
+        characteristic: Uuid,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<(), btleplug::Error> {
+        if !self.connected.load(Ordering::SeqCst) {
+            return Err(btleplug::Error::NotConnected);
+        }
+        if Self::take_one(&self.dropped_writes) {
+            return match write_type {
+                WriteType::WithoutResponse => Ok(()),
+                WriteType::WithResponse => Err(btleplug::Error::TimedOut(Default::default())),
+            };
+        }
+        self.writes.lock().unwrap().push(RecordedWrite {
+            characteristic,
+            data: data.to_vec(),
+            write_type,
+            at: tokio::time::Instant::now(),
+        });
+        Ok(())
+    }
+
+    async fn subscribe(&self, _characteristic: Uuid) -> Result<(), btleplug::Error> {
+        if !self.connected.load(Ordering::SeqCst) {
+            return Err(btleplug::Error::NotConnected);
+        }
+        Ok(())
+    }
+
+    async fn notifications(&self) -> Result<NotificationStream, btleplug::Error> {
+        let receiver = self.notifier.lock().unwrap().subscribe();
+        Ok(Box::pin(receiver_stream(receiver)))
+    }
+}
@@ -0,0 +1,61 @@
+pub mod btleplug_transport;
+#[cfg(test)]
+pub mod mock;
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use btleplug::api::{BDAddr, PeripheralProperties, ValueNotification, WriteType};
+use futures::Stream;
+use uuid::Uuid;
+
+pub type NotificationStream = Pin<Box<dyn Stream<Item = ValueNotification> + Send>>;
+pub type EventStream = Pin<Box<dyn Stream<Item = TransportEvent> + Send>>;
+
+pub enum TransportEvent {
+    /// A peripheral was discovered or re-advertised while scanning.
+    Discovered(Arc<dyn LightLink>),
+    Disconnected(BDAddr),
+}
+
+/// A single Bluetooth peripheral that a `Light` can drive.
+#[async_trait]
+pub trait LightLink: Send + Sync {
+    fn address(&self) -> BDAddr;
+    async fn properties(&self) -> Result<Option<PeripheralProperties>, btleplug::Error>;
+    async fn connect(&self) -> Result<(), btleplug::Error>;
+    async fn disconnect(&self) -> Result<(), btleplug::Error>;
+    async fn is_connected(&self) -> Result<bool, btleplug::Error>;
+    async fn discover_services(&self) -> Result<(), btleplug::Error>;
+    async fn write(
+        &self,
+        characteristic: Uuid,
+        data: &[u8],
+        write_type: WriteType,
+    ) -> Result<(), btleplug::Error>;
+    async fn subscribe(&self, characteristic: Uuid) -> Result<(), btleplug::Error>;
+    async fn notifications(&self) -> Result<NotificationStream, btleplug::Error>;
+}
+
+/// Finds peripherals to hand to lights.
+#[async_trait]
+pub trait BleTransport: Send + Sync {
+    /// HCI name of the adapter, e.g. "hci0".
+    fn name(&self) -> &str;
+    fn address(&self) -> Option<BDAddr>;
+
+    /// Whether a configured adapter selector (HCI name or controller address) refers to this one.
+    fn matches(&self, selector: &str) -> bool {
+        selector.eq_ignore_ascii_case(self.name())
+            || selector
+                .parse::<BDAddr>()
+                .is_ok_and(|address| Some(address) == self.address())
+    }
+
+    async fn start_scan(&self) -> Result<(), btleplug::Error>;
+    /// Peripherals the adapter already knows about.
+    async fn peripherals(&self) -> Result<Vec<Arc<dyn LightLink>>, btleplug::Error>;
+    /// Discoveries and disconnections as the adapter reports them.
+    async fn events(&self) -> Result<EventStream, btleplug::Error>;
+}
@@ -0,0 +1,486 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use serde_json::{Map, Value};
+
+use crate::config::{self, Config, LightConfig, LightMatch};
+use crate::config_format::{self, ConfigFormat};
+
+pub const MIN_UNIVERSE: u16 = 1;
+pub const MAX_UNIVERSE: u16 = 63999;
+const DMX_CHANNELS: u16 = 512;
+
+/// Something wrong with a config, and the line it starts on when we can tell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(line: Option<usize>, message: String) -> Self {
+        Self { line, message }
+    }
+
+    /// `path:line: message`, the way compilers report them so editors can jump to the line.
+    pub fn describe(&self, path: &str) -> String {
+        let mut described = String::new();
+        let _ = self.write_to(&mut described, Some(path));
+        described
+    }
+
+    // the one place a problem is formatted, with or without the file it's in
+    fn write_to(&self, out: &mut impl fmt::Write, path: Option<&str>) -> fmt::Result {
+        match (path, self.line) {
+            (Some(path), Some(line)) => write!(out, "{}:{}: ", path, line)?,
+            (Some(path), None) => write!(out, "{}: ", path)?,
+            (None, Some(line)) => write!(out, "line {}: ", line)?,
+            (None, None) => {}
+        }
+        out.write_str(&self.message)
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_to(f, None)
+    }
+}
+
+/// Checks a config document, reporting every problem rather than stopping at the first.
+/// Every profile is checked, not just the one in use.
+pub fn validate(text: &str, format: ConfigFormat) -> Vec<Problem> {
+    let document = match format.parse(text) {
+        Ok(document) => document,
+        Err(e) => return vec![Problem::new(e.line, e.message)],
+    };
+
+    let mut problems = match &document {
+        Value::Array(_) => check_light_entries(&document, &light_lines(text, format, None)),
+        _ => check_document(text, format, &document),
+    };
+    problems.sort_by_key(|problem| problem.line);
+    problems
+}
+
+/// Checks a document that isn't the text of one file, such as several files merged, so the
+/// problems come without lines.
+pub fn validate_document(document: &Value) -> Vec<Problem> {
+    let text = serde_json::to_string_pretty(document).unwrap_or_default();
+    validate(&text, ConfigFormat::Json)
+        .into_iter()
+        .map(|problem| Problem::new(None, problem.message))
+        .collect()
+}
+
+fn check_document(text: &str, format: ConfigFormat, document: &Value) -> Vec<Problem> {
+    let mut problems = check_settings(document);
+    let profiles = match document.get("profiles") {
+        Some(Value::Object(profiles)) => profiles.clone(),
+        Some(_) => {
+            problems.push(Problem::new(
+                None,
+                "profiles must be a table of profile names".to_string(),
+            ));
+            Map::new()
+        }
+        None => Map::new(),
+    };
+
+    // with profiles, the top-level lights are only there for profiles that don't set any
+    if profiles.is_empty() || document.get("lights").is_some() {
+        problems.extend(check_light_entries(
+            document,
+            &light_lines(text, format, None),
+        ));
+    }
+    match document.get("profile") {
+        Some(Value::String(profile)) => {
+            if let Err(e) = config::select_profile(&mut document.clone(), profile) {
+                problems.push(Problem::new(None, e));
+            }
+        }
+        Some(_) => problems.push(Problem::new(None, "profile must be a name".to_string())),
+        None => {}
+    }
+
+    for (name, settings) in profiles.iter() {
+        let mut merged = document.clone();
+        if let Err(e) = config::select_profile(&mut merged, name) {
+            problems.push(Problem::new(None, e));
+            continue;
+        }
+        let sets = |key: &str| settings.get(key).is_some();
+        let mut found = vec![];
+        // only check what the profile changes, so shared problems are reported once
+        let sets_other = settings.as_object().is_some_and(|settings| {
+            settings
+                .keys()
+                .any(|key| key != "lights" && key != "groups")
+        });
+        if sets_other {
+            found.extend(check_settings(&merged));
+        }
+        if sets("lights") || sets("groups") || document.get("lights").is_none() {
+            let owner = sets("lights").then_some(name.as_str());
+            found.extend(check_light_entries(
+                &merged,
+                &light_lines(text, format, owner),
+            ));
+        }
+        problems.extend(found.into_iter().map(|problem| {
+            Problem::new(
+                problem.line,
+                format!("Profile {}: {}", name, problem.message),
+            )
+        }));
+    }
+    problems
+}
+
+/// Checks everything but the lights, which are checked with their lines separately.
+fn check_settings(document: &Value) -> Vec<Problem> {
+    let Value::Object(fields) = document else {
+        return match Config::from_value(document.clone()) {
+            Ok(_) => vec![],
+            Err(e) => vec![Problem::new(None, e.to_string())],
+        };
+    };
+    let mut settings = fields.clone();
+    settings.insert("lights".to_string(), Value::Array(vec![]));
+    // profiles are checked one by one, and group settings on the lights that use them
+    settings.remove("profile");
+    settings.remove("profiles");
+    if let Some(Value::Object(groups)) = settings.get_mut("groups") {
+        groups.clear();
+    }
+    match Config::from_value(Value::Object(settings)) {
+        Ok(_) => vec![],
+        Err(e) => vec![Problem::new(None, e.to_string())],
+    }
+}
+
+/// Checks the light list of a document, or a legacy bare list, with `lines` giving the line
+/// each entry starts on.
+fn check_light_entries(document: &Value, lines: &[usize]) -> Vec<Problem> {
+    let groups = match document.get("groups") {
+        Some(Value::Object(groups)) => groups.clone(),
+        _ => Map::new(),
+    };
+    let entries = match document {
+        Value::Array(entries) => entries,
+        _ => match document.get("lights") {
+            Some(Value::Array(entries)) => entries,
+            Some(_) => return vec![Problem::new(None, "lights must be a list".to_string())],
+            None => return vec![Problem::new(None, "Missing lights".to_string())],
+        },
+    };
+
+    let mut problems = vec![];
+    let mut lights = vec![];
+    for (index, entry) in entries.iter().enumerate() {
+        let line = lines.get(index).copied();
+        // a range entry's lights all report the entry's line
+        let expanded = match config::expand_light(entry, &groups) {
+            Ok(expanded) => expanded,
+            Err(e) => {
+                problems.push(Problem::new(line, format!("Light {}: {}", index + 1, e)));
+                continue;
+            }
+        };
+        for light in expanded {
+            match serde_json::from_value::<LightConfig>(light) {
+                Ok(light) => lights.push((line, light)),
+                Err(e) => problems.push(Problem::new(line, format!("Light {}: {}", index + 1, e))),
+            }
+        }
+    }
+
+    problems.extend(check_lights(&lights));
+    problems.extend(check_groups(&groups, &lights));
+    problems
+}
+
+pub async fn validate_file(path: &str) -> std::io::Result<Vec<Problem>> {
+    let text = tokio::fs::read_to_string(path).await?;
+    Ok(validate(&text, ConfigFormat::from_path(path)))
+}
+
+fn check_lights(lights: &[(Option<usize>, LightConfig)]) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut matchers: HashMap<&LightMatch, Option<usize>> = HashMap::new();
+    let mut labels: HashMap<String, Option<usize>> = HashMap::new();
+
+    for (i, (line, light)) in lights.iter().enumerate() {
+        let label = light.get_label();
+        let footprint = light.personality.footprint();
+        let end = light.address as u32 + footprint as u32 - 1;
+
+        if let Some(first) = matchers.insert(&light.matcher, *line) {
+            problems.push(Problem::new(
+                *line,
+                format!("{} is already patched{}", light.matcher, on_line(first)),
+            ));
+        }
+        // unlabelled lights go by their match rule, which is checked above
+        if light.label.is_some() {
+            if let Some(first) = labels.insert(label.to_lowercase(), *line) {
+                problems.push(Problem::new(
+                    *line,
+                    format!("Label {} is already used{}", label, on_line(first)),
+                ));
+            }
+        }
+        if !(MIN_UNIVERSE..=MAX_UNIVERSE).contains(&light.universe) {
+            problems.push(Problem::new(
+                *line,
+                format!(
+                    "{}: universe {} is outside {}-{}",
+                    label, light.universe, MIN_UNIVERSE, MAX_UNIVERSE
+                ),
+            ));
+        }
+        if light.address < 1 || end > DMX_CHANNELS as u32 {
+            problems.push(Problem::new(
+                *line,
+                format!(
+                    "{}: {:?} footprint at {}-{} is outside 1-{}",
+                    label, light.personality, light.address, end, DMX_CHANNELS
+                ),
+            ));
+        }
+
+        for (other_line, other) in lights[..i].iter() {
+            let other_end = other.address as u32 + other.personality.footprint() as u32 - 1;
+            if other.universe == light.universe
+                && light.address as u32 <= other_end
+                && other.address as u32 <= end
+            {
+                problems.push(Problem::new(
+                    *line,
+                    format!(
+                        "{}: channels {}-{} overlap {} at {}-{} in universe {}{}",
+                        label,
+                        light.address,
+                        end,
+                        other.get_label(),
+                        other.address,
+                        other_end,
+                        light.universe,
+                        on_line(*other_line)
+                    ),
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Groups and labels share the namespace control commands look targets up in.
+fn check_groups(
+    groups: &Map<String, Value>,
+    lights: &[(Option<usize>, LightConfig)],
+) -> Vec<Problem> {
+    groups
+        .keys()
+        .filter_map(|group| {
+            let (line, _) = lights.iter().find(|(_, light)| {
+                light.label.is_some() && light.get_label().eq_ignore_ascii_case(group)
+            })?;
+            Some(Problem::new(
+                *line,
+                format!("Label {} is also the name of a group", group),
+            ))
+        })
+        .collect()
+}
+
+fn on_line(line: Option<usize>) -> String {
+    line.map_or(String::new(), |line| format!(" (line {})", line))
+}
+
+// Which container a light entry is nested in, tracked while walking the raw text.
+enum Frame {
+    Object {
+        key: Option<String>,
+        expecting_key: bool,
+    },
+    Array,
+}
+
+/// The line, counting from 1, that each entry of the light list starts on, or of a profile's
+/// light list. None of the parsers keep positions, so these scan the text for just enough
+/// structure to find them.
+fn light_lines(text: &str, format: ConfigFormat, profile: Option<&str>) -> Vec<usize> {
+    match (format, profile) {
+        (ConfigFormat::Json, _) => json_light_lines(text, profile),
+        (ConfigFormat::Toml, _) => toml_light_lines(text, profile),
+        (ConfigFormat::Yaml, None) => yaml_light_lines(text),
+        // nested YAML lists aren't tracked; their problems go without lines
+        (ConfigFormat::Yaml, Some(_)) => vec![],
+    }
+}
+
+/// Each `[[lights]]` header, or `[[profiles.name.lights]]` for a profile. Lights written as
+/// an inline array aren't found.
+fn toml_light_lines(text: &str, profile: Option<&str>) -> Vec<usize> {
+    let header = match profile {
+        Some(profile) => format!("[[profiles.{}.lights]]", config_format::toml_key(profile)),
+        None => "[[lights]]".to_string(),
+    };
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| line.trim_start().starts_with(header.as_str()))
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// Each `- ` item of the top-level list in a legacy config, or of the `lights:` block in a
+/// current one. Flow-style lists aren't found.
+fn yaml_light_lines(text: &str) -> Vec<usize> {
+    let mut lines = vec![];
+    let mut in_lights = false;
+    let mut item_indent = None;
+    for (index, line) in text.lines().enumerate() {
+        let content = line.trim_start();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - content.len();
+        let is_item = content == "-" || content.starts_with("- ");
+
+        if indent == 0 && !is_item {
+            in_lights = content.trim_end() == "lights:";
+            item_indent = None;
+            continue;
+        }
+        // a top-level item means a legacy list of lights
+        let in_legacy_list = indent == 0 && is_item;
+        if !in_lights && !in_legacy_list {
+            continue;
+        }
+        if is_item && *item_indent.get_or_insert(indent) == indent {
+            lines.push(index + 1);
+        }
+    }
+    lines
+}
+
+fn json_light_lines(text: &str, profile: Option<&str>) -> Vec<usize> {
+    let mut stack: Vec<Frame> = vec![];
+    let mut lines = vec![];
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() || c == ':' => {}
+            ',' => {
+                if let Some(Frame::Object { expecting_key, .. }) = stack.last_mut() {
+                    *expecting_key = true;
+                }
+            }
+            '}' | ']' => {
+                stack.pop();
+            }
+            '"' => {
+                let string = read_string(&mut chars);
+                match stack.last_mut() {
+                    Some(Frame::Object {
+                        key,
+                        expecting_key: expecting_key @ true,
+                    }) => {
+                        *key = Some(string);
+                        *expecting_key = false;
+                    }
+                    _ => {
+                        if in_light_list(&stack, profile) {
+                            lines.push(line);
+                        }
+                    }
+                }
+            }
+            '{' | '[' => {
+                if in_light_list(&stack, profile) {
+                    lines.push(line);
+                }
+                stack.push(if c == '{' {
+                    Frame::Object {
+                        key: None,
+                        expecting_key: true,
+                    }
+                } else {
+                    Frame::Array
+                });
+            }
+            _ => {
+                // a number, true, false or null
+                if in_light_list(&stack, profile) {
+                    lines.push(line);
+                }
+                while chars
+                    .peek()
+                    .is_some_and(|c| !c.is_whitespace() && !",]}".contains(*c))
+                {
+                    chars.next();
+                }
+            }
+        }
+    }
+    lines
+}
+
+fn read_string(chars: &mut Peekable<Chars>) -> String {
+    let mut string = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    string.push(escaped);
+                }
+            }
+            c => string.push(c),
+        }
+    }
+    string
+}
+
+/// Whether the next value is an entry of the light list: the top-level array in a legacy
+/// config, the `lights` array in a current one, or the named profile's `lights` array.
+fn in_light_list(stack: &[Frame], profile: Option<&str>) -> bool {
+    match (stack, profile) {
+        ([Frame::Array], None) => true,
+        ([Frame::Object { key: Some(key), .. }, Frame::Array], None) => key == "lights",
+        (
+            [Frame::Object {
+                key: Some(profiles),
+                ..
+            }, Frame::Object {
+                key: Some(name), ..
+            }, Frame::Object { key: Some(key), .. }, Frame::Array],
+            Some(profile),
+        ) => profiles == "profiles" && name == profile && key == "lights",
+        _ => false,
+    }
+}
+
+/// Fails with a readable report if the config at `path` has problems.
+pub async fn check_file(path: &str) -> Result<(), String> {
+    let problems = validate_file(path)
+        .await
+        .map_err(|e| format!("{}: {}", path, e))?;
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let mut report: Vec<String> = problems
+        .iter()
+        .map(|problem| problem.describe(path))
+        .collect();
+    report.push(format!("{} problem(s) in {}", problems.len(), path));
+    Err(report.join("\n"))
+}
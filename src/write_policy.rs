@@ -0,0 +1,78 @@
+use std::fmt;
+
+use btleplug::api::WriteType;
+use serde::Deserialize;
+
+// Consecutive failed writes before an adaptive light starts asking for responses.
+const ADAPTIVE_FALLBACK_FAILURES: u32 = 3;
+
+/// How writes to a light are acknowledged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WritePolicy {
+    /// Fire and forget. Fastest, but a lost write goes unnoticed until readback disagrees.
+    #[default]
+    WithoutResponse,
+    /// Every write waits for the light to acknowledge it.
+    WithResponse,
+    /// Starts without response and switches to with response after repeated failures.
+    Adaptive,
+}
+
+/// Picks the write type for each write and decides when an adaptive light falls back.
+pub struct WriteTracker {
+    policy: WritePolicy,
+    consecutive_failures: u32,
+    fallen_back: bool,
+}
+
+impl WriteTracker {
+    pub fn new(policy: WritePolicy) -> Self {
+        Self {
+            policy,
+            consecutive_failures: 0,
+            fallen_back: false,
+        }
+    }
+
+    pub fn write_type(&self) -> WriteType {
+        match self.policy {
+            WritePolicy::WithoutResponse => WriteType::WithoutResponse,
+            WritePolicy::WithResponse => WriteType::WithResponse,
+            WritePolicy::Adaptive if self.fallen_back => WriteType::WithResponse,
+            WritePolicy::Adaptive => WriteType::WithoutResponse,
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+    }
+
+    /// Counts a failed write, or a readback that shows a write went missing. Returns true if
+    /// this failure made an adaptive light fall back to writes with response.
+    ///
+    /// The fallback lasts for the rest of the session, since a light that loses writes once
+    /// tends to keep doing so.
+    pub fn record_failure(&mut self) -> bool {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        if self.policy == WritePolicy::Adaptive
+            && !self.fallen_back
+            && self.consecutive_failures >= ADAPTIVE_FALLBACK_FAILURES
+        {
+            self.fallen_back = true;
+            return true;
+        }
+        false
+    }
+}
+
+impl fmt::Display for WriteTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.policy {
+            WritePolicy::WithoutResponse => write!(f, "without response"),
+            WritePolicy::WithResponse => write!(f, "with response"),
+            WritePolicy::Adaptive if self.fallen_back => write!(f, "adaptive, with response"),
+            WritePolicy::Adaptive => write!(f, "adaptive, without response"),
+        }
+    }
+}